@@ -1,6 +1,12 @@
 struct SquareColors {
     [[location(0)]] color_off: vec4<f32>;
     [[location(1)]] color_on: vec4<f32>;
+    [[location(2)]] color_old: vec4<f32>;
+    [[location(3)]] color_dying: vec4<f32>;
+    // zero when not coloring by age
+    [[location(4)]] age_span: f32;
+    // more than two for rules from the Generations family
+    [[location(5)]] states: f32;
 };
 
 [[group(0), binding(0)]]
@@ -22,24 +28,71 @@ struct GridZoom {
 [[group(0), binding(2)]]
 var<uniform> zoom: GridZoom;
 
+struct LatticeInfo {
+    // how far apart the columns and rows are
+    [[location(0)]] spacing: vec2<f32>;
+    // 0 squares, 1 hexagons and 2 triangles
+    [[location(1)]] lattice: u32;
+    // 0 for none, otherwise one more than the column and row the blocks of block rules start at
+    [[location(2)]] partition: u32;
+};
+
+[[group(0), binding(3)]]
+var<uniform> lattice_info: LatticeInfo;
+
+struct Palette {
+    // the colors of rule tables, zero alpha for states without one
+    colors: array<vec4<f32>, 256>;
+};
+
+[[group(0), binding(4)]]
+var<uniform> palette: Palette;
+
 struct VertexOutput {
     [[builtin(position)]] clip_position: vec4<f32>;
+    [[location(0), interpolate(flat)]] state: u32;
 };
 
 // Vertex shader main
 [[stage(vertex)]]
 fn vs_main(
     [[location(0)]] vertex_pos: vec2<f32>,
-    [[location(1)]] instance_pos: vec2<u32>
+    [[location(1)]] instance_pos: vec2<i32>,
+    [[location(2)]] instance_state: u32
 ) -> VertexOutput {
     var out: VertexOutput;
 
     var position: vec2<f32> = vertex_pos;
 
-    // the x and y distances between the squares
-    let distance = vec2<f32>(abs(vertex_pos.x) * 2.0, abs(vertex_pos.y) * 2.0);
+    // the rows go downwards, the first one being at the top
+    var center = vec2<f32>(f32(instance_pos.x), -f32(instance_pos.y));
+
+    // the squares of every block move towards its middle, closing the gaps inside of it
+    if (lattice_info.partition != 0u) {
+        let offset = i32(lattice_info.partition) - 1;
+        let shift = (1.0 - square_info.scale) * 0.5;
+        if (((instance_pos.x - offset) & 1) == 0) {
+            center.x = center.x + shift;
+        } else {
+            center.x = center.x - shift;
+        }
+        if (((instance_pos.y - offset) & 1) == 0) {
+            center.y = center.y - shift;
+        } else {
+            center.y = center.y + shift;
+        }
+    }
 
-    let inst_pos_float = vec2<f32>(f32(instance_pos.x), f32(instance_pos.y)) * distance * zoom.z;
+    // odd rows of hexagons are shifted half a cell to the right
+    if (lattice_info.lattice == 1u && (instance_pos.y & 1) == 1) {
+        center.x = center.x + 0.5;
+    }
+    // every other triangle points down, turning it around keeps it counter-clockwise
+    if (lattice_info.lattice == 2u && ((instance_pos.x + instance_pos.y) & 1) == 1) {
+        position = -position;
+    }
+
+    let inst_pos_float = center * lattice_info.spacing * zoom.z;
 
     position = position * square_info.scale * zoom.z;
     position = 
@@ -48,11 +101,46 @@ fn vs_main(
         + inst_pos_float;
 
     out.clip_position = vec4<f32>(position, 0.0, 1.0);
+    out.state = instance_state;
     return out;
 }
 
 // Fragment shader main
 [[stage(fragment)]]
 fn fs_main(in: VertexOutput) -> [[location(0)]] vec4<f32> {
-    return square_colors.color_off;
+    // 0 off, 1 on and from 2 up dying
+    let cell = in.state & 255u;
+    let on = cell == 1u;
+    // generations survived when on, generations since dying plus one when not
+    let age = in.state >> 8u;
+
+    let color = palette.colors[cell];
+    if (color.a > 0.0) {
+        return color;
+    }
+
+    // the dying states go from the dying color most of the way to the off one
+    if (cell >= 2u) {
+        let t = f32(cell - 2u) / max(square_colors.states - 2.0, 1.0);
+        return mix(square_colors.color_dying, square_colors.color_off, t);
+    }
+
+    if (square_colors.age_span <= 0.0) {
+        if (on) {
+            return square_colors.color_on;
+        }
+        return square_colors.color_off;
+    }
+
+    if (on) {
+        let t = min(f32(age) / square_colors.age_span, 1.0);
+        return mix(square_colors.color_on, square_colors.color_old, t);
+    }
+
+    if (age == 0u) {
+        return square_colors.color_off;
+    }
+
+    let t = min(f32(age - 1u) / square_colors.age_span, 1.0);
+    return mix(square_colors.color_dying, square_colors.color_off, t);
 }
\ No newline at end of file
@@ -1,5 +1,7 @@
 use wgpu::*;
 
+use gol::Age;
+
 use std::mem::size_of;
 
 #[repr(C)]
@@ -29,17 +31,73 @@ pub const DEFAULT_SQUARE_VERTICES: [Vertex; 4] = [
 
 pub const DEFAULT_SQUARE_INDICES: [u16; 6] = [0, 1, 2, 3, 0, 2];
 
+// Pointy on top, 0.1 across like the squares
+pub const HEXAGON_VERTICES: [Vertex; 6] = [
+    Vertex {
+        pos: [0.0, 0.057735027],
+    },
+    Vertex {
+        pos: [-0.05, 0.028867513],
+    },
+    Vertex {
+        pos: [-0.05, -0.028867513],
+    },
+    Vertex {
+        pos: [0.0, -0.057735027],
+    },
+    Vertex {
+        pos: [0.05, -0.028867513],
+    },
+    Vertex {
+        pos: [0.05, 0.028867513],
+    },
+];
+
+pub const HEXAGON_INDICES: [u16; 12] = [0, 1, 2, 0, 2, 3, 0, 3, 4, 0, 4, 5];
+
+// Pointing up with sides of 0.1, the ones pointing down get turned around in the shader
+pub const TRIANGLE_VERTICES: [Vertex; 3] = [
+    Vertex {
+        pos: [-0.05, -0.04330127],
+    },
+    Vertex {
+        pos: [0.05, -0.04330127],
+    },
+    Vertex {
+        pos: [0.0, 0.04330127],
+    },
+];
+
+pub const TRIANGLE_INDICES: [u16; 3] = [0, 1, 2];
+
 // Alignment for wgpu:
-// Two colors taking up 16 bytes = 32
+// Four colors taking up 16 bytes + age span 4 + states 4 + padding 8 = 80
 #[repr(C)]
 #[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
 pub struct SquareColors {
     pub color_off: [f32; 4],
     pub color_on: [f32; 4],
+    /// What live squares fade into as they get older
+    pub color_old: [f32; 4],
+    /// What squares that just died start off as before fading into `color_off`,
+    /// also what the dying states of Generations rules fade through
+    pub color_dying: [f32; 4],
+    /// Generations it takes to fade, zero makes every square either on or off
+    pub age_span: f32,
+    /// How many states the rule has
+    pub states: f32,
+    pub padding: [f32; 2],
 }
 
 pub const SQCOLOR_OFF_OFFSET: BufferAddress = 0;
 pub const SQCOLOR_ON_OFFSET: BufferAddress = 16;
+pub const SQCOLOR_OLD_OFFSET: BufferAddress = 32;
+pub const SQCOLOR_DYING_OFFSET: BufferAddress = 48;
+pub const SQCOLOR_AGE_SPAN_OFFSET: BufferAddress = 64;
+pub const SQCOLOR_STATES_OFFSET: BufferAddress = 68;
+
+/// A color for every state, the ones with zero alpha are colored as usual
+pub const PALETTE_SIZE: usize = 256;
 
 // Alignment for wgpu:
 // Translation 8 + scale 4 + corner radius 4 = 16
@@ -52,7 +110,9 @@ pub struct SquareInfo {
 }
 
 pub const SQINFO_TRANSLATION_OFFSET: BufferAddress = 0;
+#[allow(dead_code)]
 pub const SQINFO_SCALE_OFFSET: BufferAddress = 8;
+#[allow(dead_code)]
 pub const SQINFO_CORNER_RADIUS_OFFSET: BufferAddress = 12;
 
 pub const DEFAULT_SQUARE_INFO: SquareInfo = SquareInfo {
@@ -62,10 +122,10 @@ pub const DEFAULT_SQUARE_INFO: SquareInfo = SquareInfo {
 };
 
 #[repr(C)]
-#[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+#[derive(Debug, Clone, Copy, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
 pub struct Instance {
-    // TODO: Use u16s
-    pub pos: [u32; 2], // [0, 0] is the first square at the top-left
+    // TODO: Use u16s for grids with borders
+    pub pos: [i32; 2], // [0, 0] is the first square at the top-left of a grid
 }
 
 impl Instance {
@@ -73,7 +133,54 @@ impl Instance {
         VertexBufferLayout {
             array_stride: size_of::<Instance>() as BufferAddress,
             step_mode: VertexStepMode::Instance,
-            attributes: &vertex_attr_array![1 => Uint32x2],
+            attributes: &vertex_attr_array![1 => Sint32x2],
+        }
+    }
+}
+
+/// Whatever the simulation says about a square,
+/// goes in its own instance buffer since it changes way more often
+/// than the positions
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct SquareState {
+    // The lowest byte is the state of the cell, 0 off, 1 on and from 2 up dying.
+    // The rest is how old it is when coloring by age: generations survived when on,
+    // generations since dying plus one when not (zero being never on or dead for too long)
+    pub state: u32,
+}
+
+/// The most an age can be while still fitting above the cell's state
+const MAX_AGE: u32 = u32::MAX >> 8;
+
+impl SquareState {
+    pub const OFF: Self = Self { state: 0 };
+    pub const ON: Self = Self { state: 1 };
+
+    pub fn new(cell_state: u8) -> Self {
+        Self {
+            state: cell_state as u32,
+        }
+    }
+
+    /// The age goes along with whatever state the cell is in
+    pub fn with_age(self, age: Age) -> Self {
+        let age = match age {
+            Age::Alive(age) => age.min(MAX_AGE),
+            Age::Dead(dead) => dead.saturating_add(1).min(MAX_AGE),
+        };
+        Self {
+            state: self.state & 0xFF | age << 8,
+        }
+    }
+}
+
+impl SquareState {
+    pub fn description() -> VertexBufferLayout<'static> {
+        VertexBufferLayout {
+            array_stride: size_of::<SquareState>() as BufferAddress,
+            step_mode: VertexStepMode::Instance,
+            attributes: &vertex_attr_array![2 => Uint32],
         }
     }
 }
@@ -83,3 +190,34 @@ impl Instance {
 pub struct GridZoom {
     pub z: f32,
 }
+
+// Alignment for wgpu:
+// Spacing 8 + lattice 4 + partition 4 = 16
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct LatticeInfo {
+    /// How far apart the columns and rows are, before zooming
+    pub spacing: [f32; 2],
+    /// 0 for squares, 1 for hexagons and 2 for triangles
+    pub lattice: u32,
+    /// 0 to leave the squares where they are, otherwise the squares of every 2x2 block
+    /// of block rules are drawn together, 1 for blocks starting at even columns
+    /// and rows and 2 for blocks starting at odd ones
+    pub partition: u32,
+}
+
+pub const LATTICE_PARTITION_OFFSET: BufferAddress = 12;
+
+// Alignment for wgpu:
+// Columns 4 + rows 4 + birth 4 + survival 4 + topology 4 + states 4 + padding 8 = 32
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct StepInfo {
+    pub columns: i32,
+    pub rows: i32,
+    pub birth: u32,
+    pub survival: u32,
+    pub topology: u32,
+    pub states: u32,
+    pub padding: [u32; 2],
+}
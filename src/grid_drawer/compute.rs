@@ -0,0 +1,375 @@
+use wgpu::{
+    util::{BufferInitDescriptor, DeviceExt},
+    *,
+};
+
+use gol::*;
+
+use std::rc::Rc;
+
+use super::buffers::{SquareState, StepInfo};
+
+/// Squares per workgroup on each axis, has to match the compute shader
+const WORKGROUP_SIZE: u32 = 8;
+
+/// Steps a grid with borders on the GPU
+///
+/// The squares go back and forth between two storage buffers, one `u32` per square
+/// row by row like [`SquareState`], so whichever one has the latest generation
+/// can be drawn straight away without ever going through the CPU
+#[derive(Debug)]
+pub struct GpuStepper {
+    device: Rc<Device>,
+    queue: Rc<Queue>,
+    compute_pipeline: ComputePipeline,
+    step_info_buf: Buffer,
+    /// The squares, the latest generation being in `cell_bufs[current]`
+    cell_bufs: [Buffer; 2],
+    /// `bind_groups[i]` reads from `cell_bufs[i]` and writes to the other one
+    bind_groups: [BindGroup; 2],
+    current: usize,
+    columns: usize,
+    rows: usize,
+}
+
+impl GpuStepper {
+    /// Starts off with the squares, rule and topology of `life`
+    pub fn new(device: Rc<Device>, queue: Rc<Queue>, life: &Life) -> Self {
+        let shader = device.create_shader_module(&include_wgsl!("../../step_shaders.wgsl"));
+
+        let storage_entry = |binding, read_only| BindGroupLayoutEntry {
+            binding,
+            visibility: ShaderStages::COMPUTE,
+            ty: BindingType::Buffer {
+                ty: BufferBindingType::Storage { read_only },
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+
+        let bind_group_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("gpu_stepper_bind_group_layout"),
+            entries: &[
+                // current
+                storage_entry(0, true),
+                // next
+                storage_entry(1, false),
+                BindGroupLayoutEntry {
+                    // step_info
+                    binding: 2,
+                    visibility: ShaderStages::COMPUTE,
+                    ty: BindingType::Buffer {
+                        ty: BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+        });
+
+        let compute_pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("gpu_stepper_compute_pipeline_layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let compute_pipeline = device.create_compute_pipeline(&ComputePipelineDescriptor {
+            label: Some("gpu_stepper_compute_pipeline"),
+            layout: Some(&compute_pipeline_layout),
+            module: &shader,
+            entry_point: "cs_main",
+        });
+
+        let step_info_buf = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("gpu_stepper_step_info_buffer"),
+            contents: bytemuck::bytes_of(&step_info(life)),
+            usage: BufferUsages::COPY_DST | BufferUsages::UNIFORM,
+        });
+
+        let states = states(life);
+        let create_cell_buf = || {
+            device.create_buffer_init(&BufferInitDescriptor {
+                label: Some("gpu_stepper_cell_buffer"),
+                contents: bytemuck::cast_slice(&states),
+                usage: BufferUsages::STORAGE
+                    | BufferUsages::VERTEX
+                    | BufferUsages::COPY_DST
+                    | BufferUsages::COPY_SRC,
+            })
+        };
+        let cell_bufs = [create_cell_buf(), create_cell_buf()];
+
+        let create_bind_group = |from: usize| {
+            device.create_bind_group(&BindGroupDescriptor {
+                label: Some("gpu_stepper_bind_group"),
+                layout: &bind_group_layout,
+                entries: &[
+                    BindGroupEntry {
+                        binding: 0,
+                        resource: cell_bufs[from].as_entire_binding(),
+                    },
+                    BindGroupEntry {
+                        binding: 1,
+                        resource: cell_bufs[1 - from].as_entire_binding(),
+                    },
+                    BindGroupEntry {
+                        binding: 2,
+                        resource: step_info_buf.as_entire_binding(),
+                    },
+                ],
+            })
+        };
+        let bind_groups = [create_bind_group(0), create_bind_group(1)];
+
+        Self {
+            device,
+            queue,
+            compute_pipeline,
+            step_info_buf,
+            cell_bufs,
+            bind_groups,
+            current: 0,
+            columns: life.columns(),
+            rows: life.rows(),
+        }
+    }
+
+    /// Whether it can take the squares of `life` without making new buffers
+    pub fn fits(&self, life: &Life) -> bool {
+        [self.columns, self.rows] == [life.columns(), life.rows()]
+    }
+
+    /// Replaces everything with what is in `life`, which has to [fit](GpuStepper::fits)
+    pub fn upload(&mut self, life: &Life) {
+        debug_assert!(self.fits(life));
+
+        self.queue
+            .write_buffer(&self.step_info_buf, 0, bytemuck::bytes_of(&step_info(life)));
+        self.queue.write_buffer(
+            &self.cell_bufs[self.current],
+            0,
+            bytemuck::cast_slice(&states(life)),
+        );
+    }
+
+    /// All of the generations are sent in one go, nothing is waited on
+    pub fn step_n(&mut self, n: u64) {
+        if n == 0 || self.columns == 0 || self.rows == 0 {
+            return;
+        }
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&CommandEncoderDescriptor {
+                label: Some("gpu_stepper_compute_encoder"),
+            });
+
+        {
+            let mut compute_pass = encoder.begin_compute_pass(&ComputePassDescriptor {
+                label: Some("gpu_stepper_compute_pass"),
+            });
+            compute_pass.set_pipeline(&self.compute_pipeline);
+
+            let workgroups = |squares: usize| (squares as u32).div_ceil(WORKGROUP_SIZE);
+            for _ in 0..n {
+                compute_pass.set_bind_group(0, &self.bind_groups[self.current], &[]);
+                compute_pass.dispatch(workgroups(self.columns), workgroups(self.rows), 1);
+                self.current = 1 - self.current;
+            }
+        }
+
+        self.queue.submit(std::iter::once(encoder.finish()));
+    }
+
+    /// The latest generation, laid out like the buffer of [`SquareState`]s
+    pub fn state_buf(&self) -> &Buffer {
+        &self.cell_bufs[self.current]
+    }
+
+    /// Brings the squares back from the GPU into `life`, waiting for every step sent
+    pub fn download(&self, life: &mut Life) {
+        debug_assert!(self.fits(life));
+
+        let size = (self.columns * self.rows * std::mem::size_of::<u32>()) as BufferAddress;
+        if size == 0 {
+            return;
+        }
+
+        let staging_buf = self.device.create_buffer(&BufferDescriptor {
+            label: Some("gpu_stepper_staging_buffer"),
+            size,
+            usage: BufferUsages::MAP_READ | BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&CommandEncoderDescriptor {
+                label: Some("gpu_stepper_download_encoder"),
+            });
+        encoder.copy_buffer_to_buffer(&self.cell_bufs[self.current], 0, &staging_buf, 0, size);
+        self.queue.submit(std::iter::once(encoder.finish()));
+
+        let slice = staging_buf.slice(..);
+        let mapping = slice.map_async(MapMode::Read);
+        self.device.poll(Maintain::Wait);
+        pollster::block_on(mapping).expect("Fatal error: failed to read the squares back");
+
+        {
+            let mapped = slice.get_mapped_range();
+            let states: &[u32] = bytemuck::cast_slice(&mapped);
+            for (i, state) in states.iter().enumerate() {
+                life.set_state(i % self.columns, i / self.columns, *state as u8);
+            }
+        }
+        staging_buf.unmap();
+    }
+}
+
+fn step_info(life: &Life) -> StepInfo {
+    let rule = life.rule();
+
+    StepInfo {
+        columns: life.columns() as i32,
+        rows: life.rows() as i32,
+        birth: rule.birth_mask() as u32,
+        survival: rule.survival_mask() as u32,
+        topology: Topology::ALL
+            .iter()
+            .position(|topology| *topology == life.topology())
+            .unwrap() as u32,
+        states: rule.states() as u32,
+        padding: [0; 2],
+    }
+}
+
+/// Buffers can't be empty, so an empty grid gets a single dead square
+fn states(life: &Life) -> Vec<SquareState> {
+    let mut states: Vec<_> = life
+        .iter_state_rows()
+        .flatten()
+        .map(SquareState::new)
+        .collect();
+
+    if states.is_empty() {
+        states.push(SquareState::OFF);
+    }
+    states
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Any adapter will do, the software one included,
+    /// `None` on machines that have none at all
+    fn device_and_queue() -> Option<(Rc<Device>, Rc<Queue>)> {
+        let instance = Instance::new(Backends::all());
+
+        let adapter = [true, false]
+            .into_iter()
+            .find_map(|force_fallback_adapter| {
+                pollster::block_on(instance.request_adapter(&RequestAdapterOptions {
+                    power_preference: PowerPreference::default(),
+                    force_fallback_adapter,
+                    compatible_surface: None,
+                }))
+            })?;
+
+        let (device, queue) = pollster::block_on(adapter.request_device(
+            &DeviceDescriptor {
+                label: None,
+                features: Features::empty(),
+                limits: Limits::downlevel_defaults(),
+            },
+            None,
+        ))
+        .ok()?;
+
+        Some((Rc::new(device), Rc::new(queue)))
+    }
+
+    fn soup_life(columns: usize, rows: usize, rule: Rule, topology: Topology) -> Life {
+        let mut life = Life::new(columns, rows);
+        life.set_rule(rule).unwrap();
+        life.set_topology(topology);
+
+        let soup = Soup {
+            seed: 7,
+            density: 0.4,
+            symmetry: Symmetry::C1,
+        };
+        for [x, y] in soup.cells([0, 0, columns as i64 - 1, rows as i64 - 1]) {
+            life.set(x as usize, y as usize, true);
+        }
+
+        life
+    }
+
+    #[test]
+    fn shaders_are_valid() {
+        let source = include_str!("../../step_shaders.wgsl");
+        let module = naga::front::wgsl::parse_str(source)
+            .unwrap_or_else(|error| panic!("{}", error.emit_to_string(source)));
+        naga::valid::Validator::new(
+            naga::valid::ValidationFlags::all(),
+            naga::valid::Capabilities::empty(),
+        )
+        .validate(&module)
+        .unwrap();
+    }
+
+    #[test]
+    #[ignore = "needs an adapter, even a software one"]
+    fn matches_the_cpu() {
+        let (device, queue) = device_and_queue().expect("no adapter, not even a software one");
+
+        let rules = [
+            Rule::CONWAY,
+            "B36/S23".parse().unwrap(),
+            "B0/S8".parse().unwrap(),
+            "B2/S345/C4".parse().unwrap(),
+        ];
+        for rule in rules {
+            for topology in Topology::ALL {
+                // Not a multiple of the workgroup size on purpose
+                let mut life = soup_life(37, 21, rule.clone(), topology);
+                let mut gpu = GpuStepper::new(Rc::clone(&device), Rc::clone(&queue), &life);
+
+                gpu.step_n(30);
+                life.step_n(30);
+
+                let mut from_gpu = soup_life(37, 21, rule.clone(), topology);
+                gpu.download(&mut from_gpu);
+                assert!(
+                    from_gpu.cells() == life.cells() && from_gpu.dying() == life.dying(),
+                    "{} on {} went differently",
+                    rule,
+                    topology
+                );
+            }
+        }
+    }
+
+    #[test]
+    #[ignore = "needs an adapter, even a software one"]
+    fn upload_replaces_everything() {
+        let (device, queue) = device_and_queue().expect("no adapter, not even a software one");
+
+        let mut life = soup_life(10, 10, Rule::CONWAY, Topology::Dead);
+        let mut gpu = GpuStepper::new(device, queue, &life);
+        gpu.step_n(5);
+
+        life = soup_life(10, 10, "B36/S23".parse().unwrap(), Topology::Torus);
+        assert!(gpu.fits(&life));
+        gpu.upload(&life);
+        gpu.step_n(3);
+        life.step_n(3);
+
+        let mut from_gpu = Life::new(10, 10);
+        gpu.download(&mut from_gpu);
+        assert!(from_gpu.cells() == life.cells());
+    }
+}
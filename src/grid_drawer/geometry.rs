@@ -0,0 +1,136 @@
+/*!
+Where the cells of every lattice go and which one is under a point
+
+Everything here is before zooming and squishing to the window's aspect ratio,
+with the top left cell at 0, 0 and y going up like in wgpu
+*/
+
+use gol::Lattice;
+
+use super::buffers::*;
+
+/// Distance between the rows of hexagons and triangles, √3 / 2 of their width
+const ROW_HEIGHT: f32 = 0.08660254;
+
+/// The vertices and indices of a single cell
+pub fn shape(lattice: Lattice) -> (&'static [Vertex], &'static [u16]) {
+    match lattice {
+        Lattice::Square => (&DEFAULT_SQUARE_VERTICES, &DEFAULT_SQUARE_INDICES),
+        Lattice::Hexagonal => (&HEXAGON_VERTICES, &HEXAGON_INDICES),
+        Lattice::Triangular => (&TRIANGLE_VERTICES, &TRIANGLE_INDICES),
+    }
+}
+
+/// How far apart the columns and rows are
+pub fn spacing(lattice: Lattice) -> [f32; 2] {
+    match lattice {
+        Lattice::Square => [0.1, 0.1],
+        Lattice::Hexagonal => [0.1, ROW_HEIGHT],
+        // Every other triangle is turned around, so they overlap half way
+        Lattice::Triangular => [0.05, ROW_HEIGHT],
+    }
+}
+
+/// What the shader knows the lattice as
+pub fn shader_index(lattice: Lattice) -> u32 {
+    match lattice {
+        Lattice::Square => 0,
+        Lattice::Hexagonal => 1,
+        Lattice::Triangular => 2,
+    }
+}
+
+/// The middle of the cell at `x`, `y`, for triangles halfway between their top and bottom
+pub fn center(lattice: Lattice, [x, y]: [i64; 2]) -> [f64; 2] {
+    let [column_width, row_height] = spacing(lattice).map(|spacing| spacing as f64);
+    // Odd rows of hexagons are shifted half a cell to the right
+    let shift = match lattice {
+        Lattice::Hexagonal if y.rem_euclid(2) == 1 => 0.5,
+        _ => 0.0,
+    };
+
+    [(x as f64 + shift) * column_width, -y as f64 * row_height]
+}
+
+/// Which cell `point` is in, anything in the gaps between them
+/// going to the closest one
+pub fn cell_at(lattice: Lattice, [x, y]: [f64; 2]) -> [i64; 2] {
+    let [column_width, row_height] = spacing(lattice).map(|spacing| spacing as f64);
+    let row = (-y / row_height + 0.5).floor() as i64;
+
+    match lattice {
+        Lattice::Square => [(x / column_width + 0.5).floor() as i64, row],
+        // The closest middle out of the rows around
+        Lattice::Hexagonal => (row - 1..=row + 1)
+            .map(|row| {
+                let shift = row.rem_euclid(2) as f64 * 0.5;
+                let column = (x / column_width - shift).round() as i64;
+                [column, row]
+            })
+            .min_by(|a, b| {
+                let distance = |cell| {
+                    let [center_x, center_y] = center(lattice, cell);
+                    (x - center_x).powi(2) + (y - center_y).powi(2)
+                };
+                distance(*a).total_cmp(&distance(*b))
+            })
+            .unwrap(),
+        Lattice::Triangular => {
+            let column = (x / column_width).round() as i64;
+            // From -0.5 at the bottom of the row to 0.5 at its top
+            let height = (y + row as f64 * row_height) / row_height;
+
+            let inside = |column: i64| {
+                let widening = if Lattice::points_up(column, row) {
+                    0.5 - height
+                } else {
+                    0.5 + height
+                };
+                (x - column as f64 * column_width).abs() <= column_width * widening
+            };
+            let column = [column, column - 1, column + 1]
+                .into_iter()
+                .find(|column| inside(*column))
+                .unwrap_or(column);
+            [column, row]
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn centers_are_in_their_cells() {
+        for lattice in Lattice::ALL {
+            for cell in [[0, 0], [1, 0], [0, 1], [5, 3], [-2, -7], [4, 4]] {
+                assert_eq!(
+                    cell_at(lattice, center(lattice, cell)),
+                    cell,
+                    "{:?}",
+                    lattice
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn hexagon_edges() {
+        // Right below the bottom of (0, 0) but to its left is (-1, 1),
+        // and to its right is (0, 1), as odd rows are shifted to the right
+        let below = -ROW_HEIGHT as f64 / 2.0 - 0.02;
+        assert_eq!(cell_at(Lattice::Hexagonal, [-0.02, below]), [-1, 1]);
+        assert_eq!(cell_at(Lattice::Hexagonal, [0.02, below]), [0, 1]);
+    }
+
+    #[test]
+    fn triangle_edges() {
+        // (0, 0) points up, so near the bottom of the row it is the wide part
+        // and near the top of it the slopes leave room for (1, 0) and (-1, 0)
+        let (bottom, top) = (-0.04, 0.04);
+        assert_eq!(cell_at(Lattice::Triangular, [0.04, bottom]), [0, 0]);
+        assert_eq!(cell_at(Lattice::Triangular, [0.04, top]), [1, 0]);
+        assert_eq!(cell_at(Lattice::Triangular, [-0.04, top]), [-1, 0]);
+    }
+}
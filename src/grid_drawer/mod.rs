@@ -7,14 +7,18 @@ use gol::*;
 
 mod buffers;
 use buffers::*;
+
+mod compute;
+use compute::*;
+
+mod geometry;
 use winit::dpi::PhysicalPosition;
 
+use std::collections::BTreeMap;
 use std::rc::Rc;
 
 use crate::settings::Settings;
 
-const DEFAULT_GRID_SIZE: u16 = 5; // 5 x 5
-
 #[derive(Debug)]
 pub struct GridDrawer {
     // The wgpu stuff
@@ -22,23 +26,46 @@ pub struct GridDrawer {
     queue: Rc<Queue>,
     render_pipeline: RenderPipeline,
     bind_group: BindGroup,
-    // Crucial buffers
+    // Crucial buffers, the shape of a single cell
     sqvert_buf: Buffer,
     sqind_buf: Buffer,
-    // Vector of positions (x, y)
+    index_count: u32,
+    // Vector of positions (x, y),
+    // every square of the grid or only the live ones in an infinite plane
     instances: Vec<buffers::Instance>,
     instance_buf: Buffer,
+    // What the simulation says about every instance, same order
+    states: Vec<SquareState>,
+    state_buf: Buffer,
     // Uniform buffers
     sqcolors_buf: Buffer,
     sqinfo: SquareInfo,
     sqinfo_buf: Buffer,
     grid_zoom: GridZoom,
     grid_zoom_buf: Buffer,
+    lattice_info_buf: Buffer,
+    palette_buf: Buffer,
+    // Stepping on the GPU
+    gpu_stepper: Option<GpuStepper>,
+    /// Whether the GPU has later generations than the simulation,
+    /// in which case the squares are drawn straight from it
+    gpu_ahead: bool,
     // Miscellanenous
-    /// *NOTE*: This is before taking the zoom into account
-    sq_width: f32,
-    /// *NOTE*: This is before taking the zoom into account
-    sq_height: f32,
+    /// The columns and rows the instances were made for, `None` for an infinite plane
+    grid_size: Option<[usize; 2]>,
+    /// The version of the settings the squares were last synced with
+    synced_version: Option<u64>,
+    /// The rule the squares are colored for, which says how many states there are
+    /// and, for rule tables, what color they are
+    rule: Rule,
+    /// What the cells are drawn as, an infinite plane is always squares
+    lattice: Lattice,
+    /// Whether to draw the blocks of block rules apart
+    show_blocks: bool,
+    /// What the shader knows the partition of the blocks as
+    partition: u32,
+    /// How much the x and y axes are squished to make up for the window not being square
+    aspect: [f32; 2],
 }
 
 impl GridDrawer {
@@ -49,17 +76,14 @@ impl GridDrawer {
             .device
             .create_shader_module(&include_wgsl!("../../grid_shaders.wgsl"));
 
-        let sqcolor_off = settings.sqcolor_off().to_f32();
-        let sqcolor_on = settings.sqcolor_on().to_f32();
-
         let sqcolors = SquareColors {
-            color_off: [
-                sqcolor_off[0],
-                sqcolor_off[1],
-                sqcolor_off[2],
-                sqcolor_off[3],
-            ],
-            color_on: [sqcolor_on[0], sqcolor_on[1], sqcolor_on[2], sqcolor_on[3]],
+            color_off: settings.sqcolor_off().to_f32(),
+            color_on: settings.sqcolor_on().to_f32(),
+            color_old: settings.sqcolor_old().to_f32(),
+            color_dying: settings.sqcolor_dying().to_f32(),
+            age_span: age_span(settings),
+            states: settings.squares().engine().rule().states() as f32,
+            padding: [0.0; 2],
         };
 
         let sqcolors_buf = wgpu_state.device.create_buffer_init(&BufferInitDescriptor {
@@ -86,6 +110,25 @@ impl GridDrawer {
             usage: BufferUsages::COPY_DST | BufferUsages::UNIFORM,
         });
 
+        let lattice = match settings.squares() {
+            Universe::Bounded(life) => life.lattice(),
+            Universe::Infinite(_) => Lattice::Square,
+        };
+        let aspect = [1.0, 1.0];
+
+        let lattice_info_buf = wgpu_state.device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("lattice_information_buffer"),
+            contents: bytemuck::cast_slice(&[lattice_info(lattice, aspect, 0)]),
+            usage: BufferUsages::COPY_DST | BufferUsages::UNIFORM,
+        });
+
+        let rule = settings.squares().engine().rule();
+        let palette_buf = wgpu_state.device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("palette_buffer"),
+            contents: bytemuck::cast_slice(&palette(&rule)),
+            usage: BufferUsages::COPY_DST | BufferUsages::UNIFORM,
+        });
+
         // --BIND GROUP AND RENDER PIPELINE-- \\
 
         let bind_group_layout =
@@ -126,6 +169,28 @@ impl GridDrawer {
                             },
                             count: None,
                         },
+                        BindGroupLayoutEntry {
+                            // lattice_info
+                            binding: 3,
+                            visibility: ShaderStages::VERTEX,
+                            ty: BindingType::Buffer {
+                                ty: BufferBindingType::Uniform,
+                                has_dynamic_offset: false,
+                                min_binding_size: None,
+                            },
+                            count: None,
+                        },
+                        BindGroupLayoutEntry {
+                            // palette
+                            binding: 4,
+                            visibility: ShaderStages::FRAGMENT,
+                            ty: BindingType::Buffer {
+                                ty: BufferBindingType::Uniform,
+                                has_dynamic_offset: false,
+                                min_binding_size: None,
+                            },
+                            count: None,
+                        },
                     ],
                 });
 
@@ -147,6 +212,16 @@ impl GridDrawer {
                     binding: 2,
                     resource: grid_zoom_buf.as_entire_binding(),
                 },
+                BindGroupEntry {
+                    // lattice_info
+                    binding: 3,
+                    resource: lattice_info_buf.as_entire_binding(),
+                },
+                BindGroupEntry {
+                    // palette
+                    binding: 4,
+                    resource: palette_buf.as_entire_binding(),
+                },
             ],
         });
 
@@ -167,7 +242,11 @@ impl GridDrawer {
                 vertex: VertexState {
                     module: &shader,
                     entry_point: "vs_main",
-                    buffers: &[Vertex::description(), buffers::Instance::description()],
+                    buffers: &[
+                        Vertex::description(),
+                        buffers::Instance::description(),
+                        SquareState::description(),
+                    ],
                 },
                 primitive: PrimitiveState {
                     topology: PrimitiveTopology::TriangleList,
@@ -198,54 +277,56 @@ impl GridDrawer {
 
         // --OTHER BUFFERS THAT I COULD CREATE NOW-- \\
 
-        let sqvert_buf = wgpu_state.device.create_buffer_init(&BufferInitDescriptor {
-            label: Some("square_vertex_buffer"),
-            contents: bytemuck::cast_slice(&DEFAULT_SQUARE_VERTICES),
-            usage: BufferUsages::VERTEX | BufferUsages::COPY_DST,
-        });
-
-        let sqind_buf = wgpu_state.device.create_buffer_init(&BufferInitDescriptor {
-            label: Some("square_index_buffer"),
-            contents: bytemuck::cast_slice(&DEFAULT_SQUARE_INDICES),
-            usage: BufferUsages::INDEX,
-        });
+        let (sqvert_buf, sqind_buf, index_count) =
+            Self::create_shape_bufs(&wgpu_state.device, lattice, aspect);
 
-        let mut instances = Vec::with_capacity((DEFAULT_GRID_SIZE * DEFAULT_GRID_SIZE) as usize);
+        // Filled in with the first sync
+        let instances = Vec::new();
+        let instance_buf = Self::create_instance_buf(&wgpu_state.device, &instances);
 
-        for row in 0..DEFAULT_GRID_SIZE {
-            for column in 0..DEFAULT_GRID_SIZE {
-                instances.push(buffers::Instance {
-                    pos: [column as u32, row as u32],
-                });
-            }
-        }
+        let states = Vec::new();
+        let state_buf = Self::create_state_buf(&wgpu_state.device, &states);
 
-        let instance_buf = wgpu_state.device.create_buffer_init(&BufferInitDescriptor {
-            label: Some("grid_drawer_instance_buffer"),
-            contents: bytemuck::cast_slice(&instances),
-            usage: BufferUsages::VERTEX,
-        });
-
-        Self {
+        let mut grid = Self {
             device: Rc::clone(&wgpu_state.device),
             queue: Rc::clone(&wgpu_state.queue),
             render_pipeline,
             bind_group,
             sqvert_buf,
             sqind_buf,
+            index_count,
             sqinfo_buf,
             sqinfo,
             instances,
             instance_buf,
+            states,
+            state_buf,
             grid_zoom,
             grid_zoom_buf,
+            lattice_info_buf,
+            palette_buf,
             sqcolors_buf,
-            sq_width: DEFAULT_SQUARE_VERTICES[0].pos[0].abs() * 2.0,
-            sq_height: DEFAULT_SQUARE_VERTICES[0].pos[1].abs() * 2.0,
-        }
+            gpu_stepper: None,
+            gpu_ahead: false,
+            grid_size: None,
+            synced_version: None,
+            rule,
+            lattice,
+            show_blocks: settings.show_blocks(),
+            partition: 0,
+            aspect,
+        };
+
+        grid.sync_squares(settings);
+
+        grid
     }
 
     pub fn draw(&self, surface_texture: &SurfaceTexture) -> Result<(), SurfaceError> {
+        if self.instances.is_empty() {
+            return Ok(());
+        }
+
         let view = surface_texture
             .texture
             .create_view(&TextureViewDescriptor::default());
@@ -273,8 +354,13 @@ impl GridDrawer {
         render_pass.set_bind_group(0, &self.bind_group, &[]);
         render_pass.set_vertex_buffer(0, self.sqvert_buf.slice(..));
         render_pass.set_vertex_buffer(1, self.instance_buf.slice(..));
+        let state_buf = match &self.gpu_stepper {
+            Some(gpu_stepper) if self.gpu_ahead => gpu_stepper.state_buf(),
+            _ => &self.state_buf,
+        };
+        render_pass.set_vertex_buffer(2, state_buf.slice(..));
         render_pass.set_index_buffer(self.sqind_buf.slice(..), IndexFormat::Uint16);
-        render_pass.draw_indexed(0..6, 0, 0..self.instances.len() as u32);
+        render_pass.draw_indexed(0..self.index_count, 0, 0..self.instances.len() as u32);
 
         drop(render_pass);
 
@@ -283,45 +369,111 @@ impl GridDrawer {
         Ok(())
     }
 
-    pub fn square_clicked(
+    /// Steps a grid with borders `n` generations on the GPU, the squares in `settings`
+    /// are left behind until [`GridDrawer::catch_up`] brings them back.
+    /// Returns false if it can't, i.e. for an infinite plane, cells that aren't squares
+    /// or a rule with more than the usual 8 neighbors or that cares where they are
+    pub fn step_on_gpu(&mut self, settings: &mut Settings, n: u64) -> bool {
+        let Universe::Bounded(life) = settings.squares() else {
+            return false;
+        };
+        if life.lattice() != Lattice::Square || !life.rule().is_totalistic() {
+            return false;
+        }
+
+        if n == 0 {
+            return true;
+        }
+
+        let gpu_stepper = match &mut self.gpu_stepper {
+            Some(gpu_stepper) if self.gpu_ahead => gpu_stepper,
+            Some(gpu_stepper) if gpu_stepper.fits(life) => {
+                gpu_stepper.upload(life);
+                gpu_stepper
+            }
+            gpu_stepper => gpu_stepper.insert(GpuStepper::new(
+                Rc::clone(&self.device),
+                Rc::clone(&self.queue),
+                life,
+            )),
+        };
+        gpu_stepper.step_n(n);
+        self.gpu_ahead = true;
+
+        let engine = settings.squares_mut().engine_mut();
+        engine.set_generation(engine.generation() + n);
+
+        true
+    }
+
+    /// Brings the squares stepped on the GPU back into `settings`, if there are any.
+    /// Has to be called before anything looks at or changes the squares
+    pub fn catch_up(&mut self, settings: &mut Settings) {
+        if !std::mem::take(&mut self.gpu_ahead) {
+            return;
+        }
+
+        let (Some(gpu_stepper), Universe::Bounded(old)) = (&self.gpu_stepper, settings.squares())
+        else {
+            return;
+        };
+
+        let mut life = Life::new(old.columns(), old.rows());
+        life.set_rule(old.rule()).unwrap();
+        life.set_topology(old.topology());
+        life.set_lattice(old.lattice()).unwrap();
+        life.set_threads(old.threads());
+        life.set_generation(old.generation());
+        gpu_stepper.download(&mut life);
+
+        settings.jump_to(life);
+    }
+
+    /// Which square would be at `position` if the grid went on forever,
+    /// so it can be outside of the grid and even negative
+    pub fn square_at(
         &self,
-        mut click_position: PhysicalPosition<f64>,
-        settings: &Settings,
+        mut position: PhysicalPosition<f64>,
         window: &winit::window::Window,
-    ) -> Option<[u32; 2]> {
+    ) -> [i64; 2] {
         // Translating from winit's coordinate system
         // to wgpu's coordinate system
 
-        click_position.x -= window.inner_size().width as f64 / 2.0;
-        click_position.y -= window.inner_size().height as f64 / 2.0;
+        position.x -= window.inner_size().width as f64 / 2.0;
+        position.y -= window.inner_size().height as f64 / 2.0;
 
-        click_position.y *= -1.0;
+        position.y *= -1.0;
 
-        click_position.x /= window.inner_size().width as f64 / 2.0;
-        click_position.y /= window.inner_size().height as f64 / 2.0;
+        position.x /= window.inner_size().width as f64 / 2.0;
+        position.y /= window.inner_size().height as f64 / 2.0;
 
-        // Calculating which square was clicked
+        // Calculating which square is there
 
-        click_position.x -= self.sqinfo.translation[0] as f64;
-        click_position.y -= self.sqinfo.translation[1] as f64;
+        position.x -= self.sqinfo.translation[0] as f64;
+        position.y -= self.sqinfo.translation[1] as f64;
 
         let f64_gzoom = self.grid_zoom.z as f64;
-        click_position.x /= self.sq_width as f64 * f64_gzoom;
-        click_position.y /= self.sq_height as f64 * f64_gzoom;
+        position.x /= self.aspect[0] as f64 * f64_gzoom;
+        position.y /= self.aspect[1] as f64 * f64_gzoom;
 
-        let result = [
-            click_position.x.round() as u32,
-            click_position.y.round() as u32,
-        ];
+        geometry::cell_at(self.lattice, [position.x, position.y])
+    }
 
-        println!("{:?}\t{:?}", click_position, result);
+    /// Returns the \[x, y\] of the square at `click_position`
+    /// or `None` if there is no square there
+    pub fn square_clicked(
+        &self,
+        click_position: PhysicalPosition<f64>,
+        settings: &Settings,
+        window: &winit::window::Window,
+    ) -> Option<[i64; 2]> {
+        let [x, y] = self.square_at(click_position, window);
 
-        if result[0] <= settings.squares_x() as u32 - 1
-            && result[1] <= settings.squares_y() as u32 - 1
-            && click_position.x >= -0.5 * f64_gzoom
-            && click_position.y >= -0.5 * f64_gzoom
+        if settings.squares().is_infinite()
+            || (0..settings.squares_x() as i64).contains(&x)
+                && (0..settings.squares_y() as i64).contains(&y)
         {
-            Some(result)
+            Some([x, y])
         } else {
             None
         }
@@ -343,6 +495,46 @@ impl GridDrawer {
         );
     }
 
+    pub fn set_square_color_old(&mut self, color: [f32; 4]) {
+        self.queue.write_buffer(
+            &self.sqcolors_buf,
+            SQCOLOR_OLD_OFFSET,
+            bytemuck::cast_slice(&color),
+        );
+    }
+
+    pub fn set_square_color_dying(&mut self, color: [f32; 4]) {
+        self.queue.write_buffer(
+            &self.sqcolors_buf,
+            SQCOLOR_DYING_OFFSET,
+            bytemuck::cast_slice(&color),
+        );
+    }
+
+    /// Has to be called whenever coloring by age is turned on or off, or its span changes
+    pub fn update_age_coloring(&mut self, settings: &Settings) {
+        self.queue.write_buffer(
+            &self.sqcolors_buf,
+            SQCOLOR_AGE_SPAN_OFFSET,
+            bytemuck::bytes_of(&age_span(settings)),
+        );
+    }
+
+    /// Every color and how they are used at once, for when all the settings are replaced
+    pub fn sync_colors(&mut self, settings: &Settings) {
+        self.set_square_color_off(settings.sqcolor_off().to_f32());
+        self.set_square_color_on(settings.sqcolor_on().to_f32());
+        self.set_square_color_old(settings.sqcolor_old().to_f32());
+        self.set_square_color_dying(settings.sqcolor_dying().to_f32());
+        self.update_age_coloring(settings);
+        self.set_show_blocks(settings.show_blocks());
+    }
+
+    /// The blocks show up on the next sync
+    pub fn set_show_blocks(&mut self, show_blocks: bool) {
+        self.show_blocks = show_blocks;
+    }
+
     pub fn set_grid_translation(&mut self, translation: [f32; 2]) {
         self.sqinfo.translation[0] = translation[0];
         self.sqinfo.translation[1] = translation[1];
@@ -364,6 +556,7 @@ impl GridDrawer {
         );
     }
 
+    #[allow(dead_code)]
     pub fn set_square_scale(&mut self, scale: f32) {
         self.queue.write_buffer(
             &self.sqinfo_buf,
@@ -373,6 +566,7 @@ impl GridDrawer {
         self.sqinfo.scale = scale;
     }
 
+    #[allow(dead_code)]
     pub fn set_square_corner_radius(&mut self, corner_radius: f32) {
         self.queue.write_buffer(
             &self.sqinfo_buf,
@@ -401,56 +595,373 @@ impl GridDrawer {
         self.grid_zoom.z
     }
 
-    fn write_sqbuffer_vertices(&self, offsets: &[BufferAddress], value: f32) {
-        for offset in offsets.iter() {
+    pub fn resize_window(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
+        let aspect_ratio = new_size.width as f32 / new_size.height as f32;
+
+        // Only one of the axes gets squished, the other one
+        // goes back to default in case the window went from wide to tall or vice versa
+        self.aspect = if aspect_ratio > 1.0 {
+            [1.0 / aspect_ratio, 1.0]
+        } else {
+            [1.0, aspect_ratio]
+        };
+
+        self.reshape_cells();
+    }
+
+    /// Makes the shape of the cells and how far apart they are
+    /// match the lattice and the window's aspect ratio again
+    fn reshape_cells(&mut self) {
+        (self.sqvert_buf, self.sqind_buf, self.index_count) =
+            Self::create_shape_bufs(&self.device, self.lattice, self.aspect);
+        self.queue.write_buffer(
+            &self.lattice_info_buf,
+            0,
+            bytemuck::cast_slice(&[lattice_info(self.lattice, self.aspect, self.partition)]),
+        );
+    }
+
+    /// The vertices and indices of a single cell, along with how many indices there are.
+    /// Every lattice has a different number of them, so they are made again when it changes
+    fn create_shape_bufs(
+        device: &Device,
+        lattice: Lattice,
+        aspect: [f32; 2],
+    ) -> (Buffer, Buffer, u32) {
+        let (vertices, indices) = geometry::shape(lattice);
+        let vertices: Vec<_> = vertices
+            .iter()
+            .map(|vertex| Vertex {
+                pos: [vertex.pos[0] * aspect[0], vertex.pos[1] * aspect[1]],
+            })
+            .collect();
+
+        let sqvert_buf = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("square_vertex_buffer"),
+            contents: bytemuck::cast_slice(&vertices),
+            usage: BufferUsages::VERTEX | BufferUsages::COPY_DST,
+        });
+
+        let sqind_buf = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("square_index_buffer"),
+            contents: bytemuck::cast_slice(indices),
+            usage: BufferUsages::INDEX,
+        });
+
+        (sqvert_buf, sqind_buf, indices.len() as u32)
+    }
+
+    // Buffers can't be empty, so an empty slice gets a single zeroed element
+    fn create_instance_buf(device: &Device, instances: &[buffers::Instance]) -> Buffer {
+        device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("grid_drawer_instance_buffer"),
+            contents: if instances.is_empty() {
+                bytemuck::bytes_of(&buffers::Instance { pos: [0, 0] })
+            } else {
+                bytemuck::cast_slice(instances)
+            },
+            usage: BufferUsages::VERTEX | BufferUsages::COPY_DST,
+        })
+    }
+
+    fn create_state_buf(device: &Device, states: &[SquareState]) -> Buffer {
+        device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("grid_drawer_state_buffer"),
+            contents: if states.is_empty() {
+                bytemuck::bytes_of(&SquareState::OFF)
+            } else {
+                bytemuck::cast_slice(states)
+            },
+            usage: BufferUsages::VERTEX | BufferUsages::COPY_DST,
+        })
+    }
+
+    fn set_instances(&mut self, instances: Vec<buffers::Instance>) {
+        if instances.len() != self.instances.len() {
+            self.instances = instances;
+            self.instance_buf = Self::create_instance_buf(&self.device, &self.instances);
+        } else if instances != self.instances {
+            self.instances = instances;
             self.queue
-                .write_buffer(&self.sqvert_buf, *offset, bytemuck::cast_slice(&[value]));
+                .write_buffer(&self.instance_buf, 0, bytemuck::cast_slice(&self.instances));
         }
     }
 
-    pub fn resize_window(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
-        // The offsets to reach the x components of the vertices that are to the left
-        const BUF_LEFT_X_OFFSETS: [BufferAddress; 2] = [0, 8];
-        // Same but right
-        const BUF_RIGHT_X_OFFSETS: [BufferAddress; 2] = [16, 24];
+    fn set_states(&mut self, states: Vec<SquareState>) {
+        if states.len() != self.states.len() {
+            self.states = states;
+            self.state_buf = Self::create_state_buf(&self.device, &self.states);
+        } else if states != self.states {
+            self.states = states;
+            self.queue
+                .write_buffer(&self.state_buf, 0, bytemuck::cast_slice(&self.states));
+        }
+    }
 
-        // Same but the y components
-        const BUF_UPPER_Y_OFFSETS: [BufferAddress; 2] = [4, 28];
-        const BUF_LOWER_Y_OFFSETS: [BufferAddress; 2] = [12, 20];
+    /// Makes the squares on screen match the ones in the simulation,
+    /// the buffers are only written to if anything actually changed
+    ///
+    /// With ages in the settings the squares are colored by how old they are,
+    /// which also draws the ones on an infinite plane that died not too long ago
+    pub fn sync_squares(&mut self, settings: &Settings) {
+        let squares = settings.squares();
+        let lattice = match squares {
+            Universe::Bounded(life) => life.lattice(),
+            Universe::Infinite(_) => Lattice::Square,
+        };
+        if lattice != self.lattice {
+            self.lattice = lattice;
+            self.reshape_cells();
+        }
 
-        const DEFAULT_LEFT: f32 = DEFAULT_SQUARE_VERTICES[0].pos[0];
-        const DEFAULT_RIGHT: f32 = DEFAULT_SQUARE_VERTICES[2].pos[0];
-        const DEFAULT_UPPER: f32 = DEFAULT_SQUARE_VERTICES[0].pos[1];
-        const DEFAULT_LOWER: f32 = DEFAULT_SQUARE_VERTICES[1].pos[1];
+        let rule = squares.engine().rule();
+        if rule != self.rule {
+            self.queue.write_buffer(
+                &self.sqcolors_buf,
+                SQCOLOR_STATES_OFFSET,
+                bytemuck::bytes_of(&(rule.states() as f32)),
+            );
+            self.queue
+                .write_buffer(&self.palette_buf, 0, bytemuck::cast_slice(&palette(&rule)));
+            self.rule = rule;
+        }
 
-        let aspect_ratio = new_size.width as f32 / new_size.height as f32;
+        let partition = match self.rule.block() {
+            Some(_) if self.show_blocks && lattice == Lattice::Square => {
+                BlockRule::offset(squares.engine().generation()) as u32 + 1
+            }
+            _ => 0,
+        };
+        if partition != self.partition {
+            self.partition = partition;
+            self.queue.write_buffer(
+                &self.lattice_info_buf,
+                LATTICE_PARTITION_OFFSET,
+                bytemuck::bytes_of(&partition),
+            );
+        }
 
-        if aspect_ratio > 1.0 {
-            self.write_sqbuffer_vertices(&BUF_LEFT_X_OFFSETS, DEFAULT_LEFT / aspect_ratio);
-            self.write_sqbuffer_vertices(&BUF_RIGHT_X_OFFSETS, DEFAULT_RIGHT / aspect_ratio);
-            self.sq_width = 2.0 * DEFAULT_RIGHT / aspect_ratio;
-        } else {
-            self.write_sqbuffer_vertices(&BUF_UPPER_Y_OFFSETS, DEFAULT_UPPER * aspect_ratio);
-            self.write_sqbuffer_vertices(&BUF_LOWER_Y_OFFSETS, DEFAULT_LOWER * aspect_ratio);
-            self.sq_height = 2.0 * DEFAULT_UPPER / aspect_ratio;
+        // Nothing to do when the squares didn't change since the last time
+        if self.synced_version == Some(settings.version()) {
+            return;
+        }
+        self.synced_version = Some(settings.version());
+        let ages = settings.ages();
+
+        match squares {
+            Universe::Bounded(life) => {
+                let size = [life.columns(), life.rows()];
+
+                if self.grid_size != Some(size) {
+                    self.grid_size = Some(size);
+
+                    let mut instances = Vec::with_capacity(size[0] * size[1]);
+                    for row in 0..size[1] {
+                        for column in 0..size[0] {
+                            instances.push(buffers::Instance {
+                                pos: [column as i32, row as i32],
+                            });
+                        }
+                    }
+                    self.set_instances(instances);
+                }
+
+                // Drawn from the GPU's buffers instead
+                if self.gpu_ahead {
+                    return;
+                }
+
+                let mut states: Vec<_> = life
+                    .iter_state_rows()
+                    .flatten()
+                    .map(SquareState::new)
+                    .collect();
+
+                if let Some(ages) = ages {
+                    for ([x, y], age) in ages.iter() {
+                        if (0..size[0] as i64).contains(&x) && (0..size[1] as i64).contains(&y) {
+                            let state = &mut states[y as usize * size[0] + x as usize];
+                            *state = state.with_age(age);
+                        }
+                    }
+                }
+                self.set_states(states);
+            }
+
+            // Only the live and dying squares get drawn, there are way too many dead ones
+            Universe::Infinite(sparse) => {
+                self.grid_size = None;
+
+                // Sorted so the buffers are left alone when nothing changed
+                let mut squares: BTreeMap<_, _> = sparse
+                    .live_cells()
+                    .into_iter()
+                    .map(|cell| (cell, SquareState::ON))
+                    .chain(
+                        sparse
+                            .dying_cells()
+                            .into_iter()
+                            .map(|(cell, state)| (cell, SquareState::new(state))),
+                    )
+                    .collect();
+
+                if let Some(ages) = ages {
+                    for (cell, age) in ages.iter() {
+                        let state = squares.entry(cell).or_insert(SquareState::OFF);
+                        *state = state.with_age(age);
+                    }
+                }
+
+                let (instances, states) = squares
+                    .into_iter()
+                    .filter_map(|([x, y], state)| {
+                        let instance = buffers::Instance {
+                            pos: [x.try_into().ok()?, y.try_into().ok()?],
+                        };
+                        Some((instance, state))
+                    })
+                    .unzip();
+                self.set_instances(instances);
+                self.set_states(states);
+            }
         }
     }
+}
+
+fn lattice_info(lattice: Lattice, aspect: [f32; 2], partition: u32) -> LatticeInfo {
+    let [column_width, row_height] = geometry::spacing(lattice);
+    LatticeInfo {
+        spacing: [column_width * aspect[0], row_height * aspect[1]],
+        lattice: geometry::shader_index(lattice),
+        partition,
+    }
+}
 
-    pub fn resize_grid(&mut self, x: u32, y: u32) {
-        self.instances.clear();
+/// The colors a rule table gives its states, anything else is left transparent
+fn palette(rule: &Rule) -> Vec<[f32; 4]> {
+    let mut palette = vec![[0.0; 4]; PALETTE_SIZE];
+    let colors = rule.table().map_or(&[][..], |table| table.colors());
 
-        for row in 0..y {
-            for column in 0..x {
-                self.instances
-                    .push(buffers::Instance { pos: [column, row] });
-            }
+    for (entry, color) in palette.iter_mut().zip(colors) {
+        if let Some([red, green, blue]) = color {
+            *entry = [*red, *green, *blue, 255].map(|channel| channel as f32 / 255.0);
         }
+    }
+    palette
+}
 
-        // recreate instance buffer
-        self.instance_buf = self.device.create_buffer_init(&BufferInitDescriptor {
-            label: Some("grid_drawer_instance_buffer"),
-            contents: bytemuck::cast_slice(&self.instances),
-            usage: BufferUsages::VERTEX,
-        });
+/// What the shader fades the squares over, zero when not coloring by age
+fn age_span(settings: &Settings) -> f32 {
+    match settings.ages() {
+        Some(_) => settings.age_span() as f32,
+        None => 0.0,
+    }
+}
+
+/// All the squares a straight line from `from` to `to` goes through,
+/// both ends included, so that dragging quickly does not leave gaps
+pub fn squares_between(lattice: Lattice, from: [i64; 2], to: [i64; 2]) -> Vec<[i64; 2]> {
+    if lattice != Lattice::Square {
+        return cells_between(lattice, from, to);
+    }
+
+    // Bresenham's line algorithm
+    let delta_x = (to[0] - from[0]).abs();
+    let delta_y = -(to[1] - from[1]).abs();
+    let step_x = if from[0] < to[0] { 1 } else { -1 };
+    let step_y = if from[1] < to[1] { 1 } else { -1 };
+
+    let mut error = delta_x + delta_y;
+    let mut current = from;
+    let mut squares = vec![current];
+
+    while current != to {
+        let doubled_error = 2 * error;
+
+        if doubled_error >= delta_y {
+            error += delta_y;
+            current[0] += step_x;
+        }
+        if doubled_error <= delta_x {
+            error += delta_x;
+            current[1] += step_y;
+        }
+
+        squares.push(current);
+    }
+
+    squares
+}
+
+/// Like [`squares_between`] for the other lattices, going from neighbor to neighbor,
+/// always to the one closest to `to` that it didn't go through yet.
+/// It gives up and jumps straight to `to` if that takes too long
+fn cells_between(lattice: Lattice, from: [i64; 2], to: [i64; 2]) -> Vec<[i64; 2]> {
+    let [to_x, to_y] = geometry::center(lattice, to);
+    let distance = |cell| {
+        let [x, y] = geometry::center(lattice, cell);
+        (x - to_x).powi(2) + (y - to_y).powi(2)
+    };
+    let most_steps = 2 * ((to[0] - from[0]).abs() + (to[1] - from[1]).abs()) + 4;
+
+    let mut cells = vec![from];
+    let mut current = from;
+    while current != to {
+        let next = lattice
+            .neighbors(current[0], current[1])
+            .iter()
+            .map(|[offset_x, offset_y]| [current[0] + offset_x, current[1] + offset_y])
+            .filter(|cell| !cells.contains(cell))
+            .min_by(|a, b| distance(*a).total_cmp(&distance(*b)));
+
+        current = match next {
+            Some(next) if (cells.len() as i64) < most_steps => next,
+            _ => to,
+        };
+        cells.push(current);
+    }
+
+    cells
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn line_of_a_single_square() {
+        for lattice in Lattice::ALL {
+            assert_eq!(squares_between(lattice, [3, -2], [3, -2]), vec![[3, -2]]);
+        }
+    }
+
+    #[test]
+    fn line_has_no_gaps() {
+        let squares = squares_between(Lattice::Square, [-3, 1], [4, -2]);
+
+        assert_eq!(squares.first(), Some(&[-3, 1]));
+        assert_eq!(squares.last(), Some(&[4, -2]));
+
+        for pair in squares.windows(2) {
+            assert!((pair[0][0] - pair[1][0]).abs() <= 1);
+            assert!((pair[0][1] - pair[1][1]).abs() <= 1);
+        }
+    }
+
+    #[test]
+    fn other_lines_go_through_neighbors() {
+        for lattice in [Lattice::Hexagonal, Lattice::Triangular] {
+            for (from, to) in [([0, 0], [7, 5]), ([3, 4], [-2, -6]), ([0, 0], [0, 9])] {
+                let cells = squares_between(lattice, from, to);
+
+                assert_eq!(cells.first(), Some(&from));
+                assert_eq!(cells.last(), Some(&to));
+                for pair in cells.windows(2) {
+                    let [x, y] = pair[0];
+                    let offset = [pair[1][0] - x, pair[1][1] - y];
+                    assert!(lattice.neighbors(x, y).contains(&offset), "{:?}", lattice);
+                }
+            }
+        }
     }
 }
@@ -1,4 +1,7 @@
-use gol::WgpuState;
+use gol::{
+    census, Census, Jump, LargerThanLife, Lattice, Neighborhood, ObjectKind, Rule, RuleError,
+    RuleTable, Soup, Symmetry, Topology, Universe, UpdateTimer, WgpuState, MAX_RANGE, RULE_PRESETS,
+};
 
 use imgui::*;
 use imgui_wgpu::*;
@@ -6,7 +9,9 @@ use imgui_winit_support::*;
 
 use wgpu::*;
 
+use std::borrow::Cow;
 use std::rc::Rc;
+use std::time::{SystemTime, UNIX_EPOCH};
 
 use crate::grid_drawer::*;
 use crate::settings::*;
@@ -18,6 +23,64 @@ pub struct Gui {
     platform: WinitPlatform,
     renderer: Renderer,
     last_cursor: Option<MouseCursor>,
+    rule_input: RuleInput,
+    soup_region: SoupRegion,
+    jump_input: JumpInput,
+    file_input: FileInput,
+    /// The last census taken and the generation it was taken at
+    census: Option<(u64, Census)>,
+}
+
+/// What is being typed in as the rulestring
+#[derive(Default)]
+struct RuleInput {
+    text: String,
+    /// The rule that was last put in the text,
+    /// so the text gets updated if the rule changes some other way
+    shown: Option<Rule>,
+    error: Option<RuleError>,
+    /// Where the last Golly `.rule` file came from
+    path: String,
+    /// Anything that went wrong reading or parsing the file
+    load_error: Option<String>,
+}
+
+/// Where the settings are saved to and opened from
+#[derive(Default)]
+struct FileInput {
+    path: String,
+    /// Anything that went wrong the last time, or where it was saved
+    message: Option<String>,
+}
+
+/// How far to jump ahead, either by some generations or up to one
+struct JumpInput {
+    by: i32,
+    to: i32,
+}
+
+impl Default for JumpInput {
+    fn default() -> Self {
+        Self { by: 1000, to: 1000 }
+    }
+}
+
+/// Where the next soup goes
+struct SoupRegion {
+    /// Only for grids, an infinite plane has no whole
+    whole_grid: bool,
+    corner: [i32; 2],
+    size: [i32; 2],
+}
+
+impl Default for SoupRegion {
+    fn default() -> Self {
+        Self {
+            whole_grid: true,
+            corner: [0, 0],
+            size: [16, 16],
+        }
+    }
 }
 
 impl Gui {
@@ -62,6 +125,11 @@ impl Gui {
             platform,
             renderer,
             last_cursor: None,
+            rule_input: RuleInput::default(),
+            soup_region: SoupRegion::default(),
+            jump_input: JumpInput::default(),
+            file_input: FileInput::default(),
+            census: None,
         }
     }
 
@@ -77,7 +145,7 @@ impl Gui {
         let changed = ColorEdit::new("On Squares Color Editing", &mut sqcolor_off)
             .label(false)
             .alpha_bar(true)
-            .build(&ui);
+            .build(ui);
         if changed {
             settings.set_sqcolor_off(RGBA::from_f32(sqcolor_off));
             grid.set_square_color_off(sqcolor_off);
@@ -88,18 +156,79 @@ impl Gui {
         let changed = ColorEdit::new("Off Squares Color Editing", &mut sqcolor_on)
             .label(false)
             .alpha_bar(true)
-            .build(&ui);
+            .build(ui);
         if changed {
             settings.set_sqcolor_on(RGBA::from_f32(sqcolor_on));
             grid.set_square_color_on(sqcolor_on);
             overall_change = true;
         }
 
+        let mut age_coloring = settings.age_coloring();
+        if ui.checkbox("Color By Age", &mut age_coloring) {
+            settings.set_age_coloring(age_coloring);
+            grid.update_age_coloring(settings);
+        }
+        if ui.is_item_hovered() {
+            ui.tooltip_text(
+                "Squares fade from the on color to the old one as they survive, \
+                 and from the dying color to the off one after they die",
+            );
+        }
+
+        if settings.rule().block().is_some() {
+            let mut show_blocks = settings.show_blocks();
+            if ui.checkbox("Show Blocks", &mut show_blocks) {
+                settings.set_show_blocks(show_blocks);
+                grid.set_show_blocks(show_blocks);
+            }
+            if ui.is_item_hovered() {
+                ui.tooltip_text("Draws the 2x2 blocks the next generation is made of together");
+            }
+        }
+
+        if age_coloring {
+            let mut sqcolor_old = settings.sqcolor_old().to_f32();
+            let mut sqcolor_dying = settings.sqcolor_dying().to_f32();
+
+            ui.text("Square Color When Old");
+            let changed = ColorEdit::new("Old Squares Color Editing", &mut sqcolor_old)
+                .label(false)
+                .alpha_bar(true)
+                .build(ui);
+            if changed {
+                settings.set_sqcolor_old(RGBA::from_f32(sqcolor_old));
+                grid.set_square_color_old(sqcolor_old);
+                overall_change = true;
+            }
+
+            ui.text("Square Color When Dying");
+            let changed = ColorEdit::new("Dying Squares Color Editing", &mut sqcolor_dying)
+                .label(false)
+                .alpha_bar(true)
+                .build(ui);
+            if changed {
+                settings.set_sqcolor_dying(RGBA::from_f32(sqcolor_dying));
+                grid.set_square_color_dying(sqcolor_dying);
+                overall_change = true;
+            }
+
+            let mut age_span = settings.age_span() as i32;
+
+            ui.text("Generations To Fade");
+            InputInt::new(ui, "Age Span", &mut age_span)
+                .enter_returns_true(true)
+                .build();
+            if age_span > 0 && age_span as u32 != settings.age_span() {
+                settings.set_age_span(age_span as u32);
+                grid.update_age_coloring(settings);
+            }
+        }
+
         ui.text("Background Color");
         let changed = ColorEdit::new("Background Color Editing", &mut backgnd_clr)
             .label(false)
             .alpha(false)
-            .build(&ui);
+            .build(ui);
         if changed {
             settings.set_background_color(RGBA::from_f32(backgnd_clr));
             overall_change = true;
@@ -108,12 +237,22 @@ impl Gui {
         overall_change
     }
 
-    fn grid_dimensions_widgets(ui: &Ui, grid: &mut GridDrawer, settings: &mut Settings) {
+    fn grid_dimensions_widgets(ui: &Ui, settings: &mut Settings, rule_input: &mut RuleInput) {
+        let mut infinite = settings.squares().is_infinite();
+        if ui.checkbox("Infinite Plane", &mut infinite) {
+            rule_input.error = settings.set_infinite(infinite).err();
+            infinite = settings.squares().is_infinite();
+        }
+
+        if infinite {
+            return;
+        }
+
         let mut columns = settings.squares_x() as i32;
         let mut rows = settings.squares_y() as i32;
 
         ui.text("Grid X Dimension");
-        InputInt::new(&ui, "Cols", &mut columns)
+        InputInt::new(ui, "Cols", &mut columns)
             .enter_returns_true(true)
             .build();
         if columns <= 4 {
@@ -121,15 +260,595 @@ impl Gui {
         }
 
         ui.text("Grid Y Dimension");
-        InputInt::new(&ui, "Rows", &mut rows)
+        InputInt::new(ui, "Rows", &mut rows)
             .enter_returns_true(true)
             .build();
         if rows <= 4 {
             rows = 5
         }
 
-        grid.resize_grid(columns as u32, rows as u32);
-        settings.resize_grid(columns as u16, rows as u16);
+        if columns as u16 != settings.squares_x() || rows as u16 != settings.squares_y() {
+            rule_input.error = settings.resize_grid(columns as u16, rows as u16).err();
+        }
+
+        let mut topology = Topology::ALL
+            .iter()
+            .position(|topology| *topology == settings.topology())
+            .unwrap_or_default();
+        ui.text("Edges");
+        let chosen = ui.combo("Topology", &mut topology, &Topology::ALL, |topology| {
+            Cow::Borrowed(topology.label())
+        });
+        if chosen {
+            rule_input.error = settings.set_topology(Topology::ALL[topology]).err();
+        }
+
+        let mut lattice = Lattice::ALL
+            .iter()
+            .position(|lattice| *lattice == settings.lattice())
+            .unwrap_or_default();
+        ui.text("Cells");
+        let chosen = ui.combo("Lattice", &mut lattice, &Lattice::ALL, |lattice| {
+            Cow::Borrowed(lattice.label())
+        });
+        if chosen {
+            rule_input.error = settings.set_lattice(Lattice::ALL[lattice]).err();
+        }
+    }
+
+    fn rule_widgets(ui: &Ui, settings: &mut Settings, rule_input: &mut RuleInput) {
+        let rule = settings.rule();
+        if rule_input.shown.as_ref() != Some(&rule) {
+            rule_input.text = rule.to_string();
+            rule_input.shown = Some(rule.clone());
+        }
+
+        ui.text("Rule");
+        let entered = ui
+            .input_text("B/S", &mut rule_input.text)
+            .enter_returns_true(true)
+            .build();
+        if ui.is_item_hovered() {
+            ui.tooltip_text(
+                "Like B3/S23, B2-a/S12 with the shapes the neighbors make,\n\
+                 B2/S/C3 for rules with dying states, B3/S2:99,3:99@42 for chances\n\
+                 in percent and a seed, or MS,D followed by what each of the\n\
+                 16 blocks turns into for block rules",
+            );
+        }
+        if entered {
+            rule_input.error = rule_input
+                .text
+                .parse()
+                .and_then(|rule| settings.set_rule(rule))
+                .err();
+            // Back to what is actually being used, e.g. 23/3 becomes B3/S23
+            rule_input.shown = None;
+        }
+
+        let mut preset = RULE_PRESETS
+            .iter()
+            .position(|(_, preset)| *preset == rule)
+            .unwrap_or(usize::MAX);
+        let chosen = ui.combo("Presets", &mut preset, &RULE_PRESETS, |(name, _)| {
+            Cow::Borrowed(name)
+        });
+        if chosen {
+            rule_input.error = settings.set_rule(RULE_PRESETS[preset].1.clone()).err();
+        }
+
+        if let Some(larger) = rule.larger_than_life() {
+            Self::neighborhood_widgets(ui, settings, rule_input, &rule, larger);
+        }
+
+        if let Some(stochastic) = rule.stochastic() {
+            let mut seed = stochastic.seed().to_string();
+            let entered = ui
+                .input_text("Rule Seed", &mut seed)
+                .chars_decimal(true)
+                .enter_returns_true(true)
+                .build();
+            if ui.is_item_hovered() {
+                ui.tooltip_text(
+                    "Runs with the same seed take the same chances and turn out the same",
+                );
+            }
+            if let (true, Ok(seed)) = (entered, seed.parse()) {
+                let reseeded =
+                    Rule::from_stochastic(stochastic.with_seed(seed)).with_states(rule.states());
+                rule_input.error = settings.set_rule(reseeded).err();
+            }
+        }
+
+        ui.input_text("Rule File", &mut rule_input.path).build();
+        if ui.is_item_hovered() {
+            ui.tooltip_text("A Golly .rule file with a @TABLE, e.g. WireWorld.rule");
+        }
+        if ui.button("Load") {
+            rule_input.load_error = None;
+            rule_input.error = None;
+            match std::fs::read_to_string(rule_input.path.trim()) {
+                Err(error) => rule_input.load_error = Some(error.to_string()),
+                Ok(source) => match RuleTable::parse(&source) {
+                    Err(error) => rule_input.load_error = Some(error.to_string()),
+                    Ok(table) => {
+                        rule_input.error = settings.set_rule(Rule::from_table(table)).err();
+                    }
+                },
+            }
+        }
+
+        if let Some(error) = &rule_input.error {
+            ui.text_colored([1.0, 0.4, 0.4, 1.0], error.to_string());
+        }
+        if let Some(error) = &rule_input.load_error {
+            ui.text_colored([1.0, 0.4, 0.4, 1.0], error);
+        }
+    }
+
+    /// Saving everything to a .gol file and opening one back
+    fn file_widgets(
+        ui: &Ui,
+        grid: &mut GridDrawer,
+        settings: &mut Settings,
+        timer: &mut UpdateTimer,
+        file_input: &mut FileInput,
+    ) {
+        ui.input_text("File", &mut file_input.path).build();
+        if ui.is_item_hovered() {
+            ui.tooltip_text("A .gol file with the grid, the rule and the colors");
+        }
+
+        let path = file_input.path.trim();
+        if ui.button("Save") {
+            grid.catch_up(settings);
+            file_input.message = Some(match settings.write_in_file(path) {
+                Ok(()) => format!("Saved to {}", path),
+                Err(error) => error.to_string(),
+            });
+        }
+
+        ui.same_line();
+        if ui.button("Open") {
+            match Settings::read_from_file(path) {
+                Ok(opened) => {
+                    grid.catch_up(settings);
+                    *settings = opened;
+                    grid.sync_colors(settings);
+                    timer.set_jump(None);
+                    timer.set_paused(true);
+                    file_input.message = None;
+                }
+                Err(error) => file_input.message = Some(error.to_string()),
+            }
+        }
+
+        if let Some(message) = &file_input.message {
+            ui.text_wrapped(message);
+        }
+    }
+
+    /// For Larger than Life rules, custom neighborhoods are drawn as a grid of checkboxes
+    fn neighborhood_widgets(
+        ui: &Ui,
+        settings: &mut Settings,
+        rule_input: &mut RuleInput,
+        rule: &Rule,
+        larger: LargerThanLife,
+    ) {
+        let mut changed = larger;
+
+        let kinds = ["Moore", "von Neumann", "Circular", "Custom"];
+        let mut kind = match larger.neighborhood {
+            Neighborhood::Moore => 0,
+            Neighborhood::VonNeumann => 1,
+            Neighborhood::Circular => 2,
+            Neighborhood::Custom(_) => 3,
+        };
+        if ui.combo_simple_string("Neighborhood", &mut kind, &kinds) {
+            changed.neighborhood = match kind {
+                0 => Neighborhood::Moore,
+                1 => Neighborhood::VonNeumann,
+                2 => Neighborhood::Circular,
+                _ => larger
+                    .neighborhood
+                    .to_custom(larger.range, larger.range)
+                    .unwrap_or(larger.neighborhood),
+            };
+        }
+
+        let mut range = larger.range as i32;
+        InputInt::new(ui, "Range", &mut range)
+            .enter_returns_true(true)
+            .build();
+        if range != larger.range as i32 {
+            changed.range = range.clamp(0, MAX_RANGE as i32) as u16;
+            // Whatever was outside of the old range starts off not counting
+            if let Neighborhood::Custom(_) = larger.neighborhood {
+                changed.neighborhood = larger
+                    .neighborhood
+                    .to_custom(larger.range, changed.range)
+                    .unwrap_or(larger.neighborhood);
+            }
+        }
+
+        if let Neighborhood::Custom(_) = changed.neighborhood {
+            let range = changed.range as i64;
+            for y in -range..=range {
+                for x in -range..=range {
+                    if x > -range {
+                        ui.same_line();
+                    }
+
+                    // The middle is up to M in the rulestring
+                    let mut counts = if [x, y] == [0, 0] {
+                        larger.middle
+                    } else {
+                        changed.neighborhood.contains(changed.range, x, y)
+                    };
+                    if ui.checkbox(format!("##neighbor{},{}", x, y), &mut counts) {
+                        if [x, y] == [0, 0] {
+                            changed.middle = counts;
+                        } else {
+                            changed.neighborhood.toggle(changed.range, x, y);
+                        }
+                    }
+                }
+            }
+        }
+
+        if changed != larger {
+            rule_input.error = changed
+                .validate()
+                .and_then(|()| {
+                    settings
+                        .set_rule(Rule::from_larger_than_life(changed).with_states(rule.states()))
+                })
+                .err();
+        }
+    }
+
+    fn simulation_widgets(
+        ui: &Ui,
+        settings: &mut Settings,
+        timer: &mut UpdateTimer,
+        jump_input: &mut JumpInput,
+    ) {
+        ui.text(format!("Generation {}", settings.generation()));
+
+        let play_label = if timer.paused() { "Play" } else { "Pause" };
+        if ui.button(play_label) {
+            timer.set_paused(!timer.paused());
+        }
+
+        ui.same_line();
+        if ui.button("Step") {
+            settings.step_n(1);
+        }
+
+        ui.same_line();
+        if ui.button("Clear") {
+            settings.clear_squares();
+        }
+
+        let mut updates_sec = settings.updates_sec();
+
+        ui.text("Updates Per Second");
+        InputFloat::new(ui, "Updates/s", &mut updates_sec)
+            .enter_returns_true(true)
+            .build();
+        if updates_sec > 0.0 && updates_sec != settings.updates_sec() {
+            settings.set_updates_sec(updates_sec);
+        }
+
+        if !settings.squares().is_infinite() {
+            let mut gpu_stepping = settings.gpu_stepping();
+            if ui.checkbox("Step On The GPU", &mut gpu_stepping) {
+                settings.set_gpu_stepping(gpu_stepping);
+            }
+            if ui.is_item_hovered() {
+                ui.tooltip_text(
+                    "Faster for big grids, but the history, cycles, \
+                     statistics and ages only see where it stopped",
+                );
+            }
+        }
+
+        // Only grids with borders are stepped in parallel
+        if let Universe::Bounded(life) = settings.squares_mut() {
+            let mut threads = life.threads() as i32;
+
+            ui.text("Threads");
+            InputInt::new(ui, "Threads", &mut threads)
+                .enter_returns_true(true)
+                .build();
+            if threads >= 1 && threads as usize != life.threads() {
+                life.set_threads(threads as usize);
+            }
+        }
+
+        Self::jump_widgets(ui, settings, timer, jump_input);
+    }
+
+    /// Computing a lot of generations at once without drawing any of them
+    fn jump_widgets(
+        ui: &Ui,
+        settings: &mut Settings,
+        timer: &mut UpdateTimer,
+        jump_input: &mut JumpInput,
+    ) {
+        let generation = settings.generation();
+
+        if let Some(jump) = timer.jump() {
+            ProgressBar::new(jump.progress(generation))
+                .overlay_text(format!("{} / {}", generation, jump.target()))
+                .build(ui);
+            if ui.button("Cancel") {
+                settings.finish_jump();
+                timer.set_jump(None);
+            }
+            return;
+        }
+
+        ui.text("Jump Ahead");
+        InputInt::new(ui, "Generations", &mut jump_input.by).build();
+        jump_input.by = jump_input.by.max(1);
+        if ui.button("Advance") {
+            timer.set_jump(Some(Jump::by(generation, jump_input.by as u64)));
+        }
+
+        InputInt::new(ui, "Generation", &mut jump_input.to).build();
+        jump_input.to = jump_input.to.max(0);
+        if ui.button("Run Until") {
+            timer.set_jump(Some(Jump::to(generation, jump_input.to as u64)));
+        }
+        if ui.is_item_hovered() {
+            ui.tooltip_text("Nothing is drawn until it gets there or is canceled");
+        }
+    }
+
+    fn soup_widgets(ui: &Ui, settings: &mut Settings, region: &mut SoupRegion) {
+        let mut soup = settings.soup();
+
+        ui.text("Random Soup");
+
+        let mut seed = soup.seed.to_string();
+        if ui.input_text("Seed", &mut seed).chars_decimal(true).build() {
+            if let Ok(seed) = seed.parse() {
+                soup.seed = seed;
+            }
+        }
+
+        let mut density = soup.density as f32;
+        if Slider::new("Density", 0.0, 1.0).build(ui, &mut density) {
+            soup.density = density as f64;
+        }
+
+        let mut symmetry = Symmetry::ALL
+            .iter()
+            .position(|symmetry| *symmetry == soup.symmetry)
+            .unwrap_or(0);
+        if ui.combo("Symmetry", &mut symmetry, &Symmetry::ALL, |symmetry| {
+            Cow::Owned(symmetry.to_string())
+        }) {
+            soup.symmetry = Symmetry::ALL[symmetry];
+        }
+
+        settings.set_soup(soup);
+
+        let bounded = !settings.squares().is_infinite();
+        if bounded {
+            ui.checkbox("Whole Grid", &mut region.whole_grid);
+        }
+        if !bounded || !region.whole_grid {
+            ui.input_int2("Corner", &mut region.corner).build();
+            ui.input_int2("Size", &mut region.size).build();
+        }
+
+        let fill = ui.button("Fill");
+        ui.same_line();
+        let reseed = ui.button("New Seed");
+        if reseed {
+            let now = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default();
+            settings.set_soup(Soup {
+                seed: now.as_nanos() as u64,
+                ..soup
+            });
+        }
+
+        if fill || reseed {
+            let [min_x, min_y, max_x, max_y] = if bounded && region.whole_grid {
+                [
+                    0,
+                    0,
+                    settings.squares_x() as i32 - 1,
+                    settings.squares_y() as i32 - 1,
+                ]
+            } else {
+                [
+                    region.corner[0],
+                    region.corner[1],
+                    region.corner[0] + region.size[0] - 1,
+                    region.corner[1] + region.size[1] - 1,
+                ]
+            };
+            settings.fill_soup([min_x as i64, min_y as i64, max_x as i64, max_y as i64]);
+        }
+    }
+
+    fn history_widgets(ui: &Ui, settings: &mut Settings, timer: &mut UpdateTimer) {
+        let history = settings.history();
+
+        ui.text("History");
+        match history.oldest_generation() {
+            Some(oldest) => ui.text(format!(
+                "Back to generation {} ({} KiB)",
+                oldest,
+                history.memory() / 1024
+            )),
+            None => ui.text("Nothing to go back to"),
+        }
+
+        // Going back while playing would step right back forward
+        if ui.button("<< Back") && settings.step_back() {
+            timer.set_paused(true);
+        }
+
+        ui.same_line();
+        if ui.button("Forward >>") {
+            settings.step_forward();
+        }
+
+        let mut generation = settings.squares().engine().generation() as i32;
+        let rewind = InputInt::new(ui, "Go To", &mut generation)
+            .enter_returns_true(true)
+            .build();
+        if rewind && generation >= 0 {
+            settings.rewind_to(generation as u64);
+            timer.set_paused(true);
+        }
+
+        let mut limit_mib = (settings.history().limit() >> 20) as i32;
+        ui.text("History Memory Limit");
+        InputInt::new(ui, "MiB", &mut limit_mib)
+            .enter_returns_true(true)
+            .build();
+        if limit_mib >= 0 && limit_mib as usize != settings.history().limit() >> 20 {
+            settings.set_history_limit((limit_mib as usize) << 20);
+        }
+    }
+
+    fn cycle_widgets(ui: &Ui, settings: &mut Settings) {
+        let mut detect = settings.detect_cycles();
+        if ui.checkbox("Detect Cycles", &mut detect) {
+            settings.set_detect_cycles(detect);
+        }
+
+        if !detect {
+            return;
+        }
+
+        let mut stop = settings.stop_on_cycle();
+        if ui.checkbox("Stop When Found", &mut stop) {
+            settings.set_stop_on_cycle(stop);
+        }
+
+        let population = settings.squares().engine().population();
+        match settings.cycle() {
+            None => ui.text("No cycle yet"),
+            Some(cycle) if population == 0 => {
+                ui.text(format!("Died out at generation {}", cycle.start))
+            }
+            Some(cycle) if cycle.is_still() => {
+                ui.text(format!("Still since generation {}", cycle.start))
+            }
+            Some(cycle) if cycle.is_moving() => ui.text_wrapped(format!(
+                "Moving by ({}, {}) every {} generations since generation {}",
+                cycle.displacement[0], cycle.displacement[1], cycle.period, cycle.start
+            )),
+            Some(cycle) => ui.text_wrapped(format!(
+                "Period {} since generation {}",
+                cycle.period, cycle.start
+            )),
+        }
+    }
+
+    fn statistics_widgets(ui: &Ui, settings: &Settings) {
+        let engine = settings.squares().engine();
+        let stats = settings.stats();
+
+        ui.text("Statistics");
+        ui.text(format!("Population {}", engine.population()));
+
+        if let Some(latest) = stats
+            .latest()
+            .filter(|latest| latest.generation == engine.generation())
+        {
+            if let (Some(births), Some(deaths)) = (latest.births, latest.deaths) {
+                ui.text(format!("Births {} / Deaths {}", births, deaths));
+            }
+        }
+
+        match engine.bounding_box() {
+            Some([min_x, min_y, max_x, max_y]) => ui.text(format!(
+                "Bounding box {}x{}",
+                max_x - min_x + 1,
+                max_y - min_y + 1
+            )),
+            None => ui.text("Bounding box empty"),
+        }
+
+        if let Some(peak) = stats.peak_population() {
+            ui.text(format!(
+                "Peak {} at generation {}",
+                peak.population, peak.generation
+            ));
+        }
+
+        let populations: Vec<f32> = stats.iter().map(|stats| stats.population as f32).collect();
+        PlotLines::new(ui, "##Population", &populations)
+            .overlay_text("Population")
+            .scale_min(0.0)
+            .graph_size([0.0, 60.0])
+            .build();
+    }
+
+    fn census_widgets(ui: &Ui, settings: &Settings, taken: &mut Option<(u64, Census)>) {
+        // Objects are told apart by the 8 squares around their cells
+        if settings.lattice() != Lattice::Square {
+            return;
+        }
+
+        let engine = settings.squares().engine();
+
+        if ui.button("Take Census") {
+            *taken = Some((engine.generation(), census(engine)));
+        }
+
+        let Some((generation, census)) = taken else {
+            return;
+        };
+
+        ui.same_line();
+        if ui.button("Forget") {
+            *taken = None;
+            return;
+        }
+
+        ui.text(format!(
+            "{} objects at generation {}",
+            census.objects(),
+            generation
+        ));
+
+        ui.columns(3, "##Census", true);
+        for header in ["Object", "Kind", "Count"] {
+            ui.text(header);
+            ui.next_column();
+        }
+        ui.separator();
+
+        for entry in &census.entries {
+            ui.text(entry.name.unwrap_or(&entry.code));
+            if entry.name.is_some() && ui.is_item_hovered() {
+                ui.tooltip_text(&entry.code);
+            }
+            ui.next_column();
+
+            match entry.kind {
+                ObjectKind::StillLife => ui.text("still"),
+                ObjectKind::Oscillator { period } => ui.text(format!("p{}", period)),
+                ObjectKind::Spaceship { period } => ui.text(format!("c/{}", period)),
+                ObjectKind::Unclassified => ui.text("?"),
+            }
+            ui.next_column();
+
+            ui.text(entry.count.to_string());
+            ui.next_column();
+        }
+        ui.columns(1, "##Census", false);
     }
 
     /// Returns whether the colors were changed
@@ -139,13 +858,22 @@ impl Gui {
         surface_texture: &SurfaceTexture,
         grid: &mut GridDrawer,
         settings: &mut Settings,
+        timer: &mut UpdateTimer,
     ) -> bool {
         self.platform
-            .prepare_frame(self.context.io_mut(), &window)
+            .prepare_frame(self.context.io_mut(), window)
             .expect("Fatal error: failed to prepare frame");
 
         let ui = self.context.frame();
 
+        // Anything done in the panel has to see the squares as they are,
+        // not as they were before stepping on the GPU
+        let clicked = ui.io().want_capture_mouse && ui.is_mouse_clicked(MouseButton::Left);
+        let entered = ui.io().want_capture_keyboard && ui.is_key_pressed(Key::Enter);
+        if clicked || entered {
+            grid.catch_up(settings);
+        }
+
         let mut colors_changed = false;
 
         {
@@ -164,7 +892,39 @@ impl Gui {
 
                     ui.separator();
 
-                    Self::grid_dimensions_widgets(&ui, grid, settings);
+                    Self::grid_dimensions_widgets(&ui, settings, &mut self.rule_input);
+
+                    ui.separator();
+
+                    Self::rule_widgets(&ui, settings, &mut self.rule_input);
+
+                    ui.separator();
+
+                    Self::simulation_widgets(&ui, settings, timer, &mut self.jump_input);
+
+                    ui.separator();
+
+                    Self::soup_widgets(&ui, settings, &mut self.soup_region);
+
+                    ui.separator();
+
+                    Self::history_widgets(&ui, settings, timer);
+
+                    ui.separator();
+
+                    Self::cycle_widgets(&ui, settings);
+
+                    ui.separator();
+
+                    Self::statistics_widgets(&ui, settings);
+
+                    ui.separator();
+
+                    Self::census_widgets(&ui, settings, &mut self.census);
+
+                    ui.separator();
+
+                    Self::file_widgets(&ui, grid, settings, timer, &mut self.file_input);
 
                     ui.separator();
                 });
@@ -214,6 +974,12 @@ impl Gui {
         colors_changed
     }
 
+    /// Whether the mouse is over any of the GUI
+    /// and so it should not affect the grid
+    pub fn wants_mouse(&self) -> bool {
+        self.context.io().want_capture_mouse
+    }
+
     pub fn platform_event_handling(
         &mut self,
         window: &winit::window::Window,
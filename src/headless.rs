@@ -0,0 +1,103 @@
+/*!
+Running without a window, for machines that have no display
+
+`gol search [options]` runs a soup search and writes a report,
+see [`USAGE`] for the options
+*/
+
+use gol::{search, SearchOptions};
+
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::time::Instant;
+
+pub const USAGE: &str = "\
+Usage: gol search [options]
+
+Runs random soups until they settle and counts the objects they leave behind
+
+Options:
+    --soups N             how many soups to run (1000)
+    --seed N              the seed of the first soup, the rest follow (0)
+    --size N              soups are N by N squares (16)
+    --density D           how likely a square is to be alive, 0 to 1 (0.5)
+    --symmetry S          C1, C2, C4, D2, D4 or D8 (C1)
+    --rule R              the rule in B/S notation (B3/S23)
+    --max-generations N   give up on soups still changing by then (10000)
+    --threads N           how many soups run at once (all of the cores)
+    --output PATH         where the report goes (search-report.txt)";
+
+/// Takes the arguments after `search`
+pub fn run_search(args: &[String]) -> Result<(), String> {
+    let mut options = SearchOptions::default();
+    let mut output = String::from("search-report.txt");
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if arg == "--help" || arg == "-h" {
+            println!("{}", USAGE);
+            return Ok(());
+        }
+
+        let value = args
+            .next()
+            .ok_or_else(|| format!("{} needs a value\n\n{}", arg, USAGE))?;
+        let invalid = || format!("invalid value for {}: {}", arg, value);
+
+        match arg.as_str() {
+            "--soups" => options.soups = value.parse().map_err(|_| invalid())?,
+            "--seed" => options.first_seed = value.parse().map_err(|_| invalid())?,
+            "--size" => {
+                options.size = value
+                    .parse()
+                    .ok()
+                    .filter(|size| *size > 0)
+                    .ok_or_else(invalid)?
+            }
+            "--density" => {
+                options.density = value
+                    .parse()
+                    .ok()
+                    .filter(|density| (0.0..=1.0).contains(density))
+                    .ok_or_else(invalid)?
+            }
+            "--symmetry" => options.symmetry = value.parse().map_err(|_| invalid())?,
+            "--rule" => options.rule = value.parse().map_err(|_| invalid())?,
+            "--max-generations" => {
+                options.max_generations = value.parse().map_err(|_| invalid())?
+            }
+            "--threads" => options.threads = value.parse().map_err(|_| invalid())?,
+            "--output" => output = value.clone(),
+            _ => return Err(format!("unknown option {}\n\n{}", arg, USAGE)),
+        }
+    }
+
+    // Opened first so a bad path doesn't waste a whole night of searching
+    let file = File::create(&output).map_err(|error| format!("{}: {}", output, error))?;
+
+    let start = Instant::now();
+    let total = options.soups;
+    let step = (total / 100).max(1);
+    let report = search(&options, &|done| {
+        if done.is_multiple_of(step) || done == total {
+            eprint!("\r{}/{} soups", done, total);
+        }
+    })
+    .map_err(|error| error.to_string())?;
+    eprintln!(" in {:.1}s", start.elapsed().as_secs_f64());
+
+    let mut writer = BufWriter::new(file);
+    report
+        .write_to(&mut writer)
+        .and_then(|()| writer.flush())
+        .map_err(|error| format!("{}: {}", output, error))?;
+
+    eprintln!(
+        "{} kinds of objects, {} soups never settled, report in {}",
+        report.objects.len(),
+        report.unsettled,
+        output
+    );
+
+    Ok(())
+}
@@ -1,3 +1,6 @@
+mod life;
+pub use life::*;
+
 use wgpu::*;
 
 use winit::event_loop::ControlFlow;
@@ -21,7 +24,7 @@ pub struct WgpuState {
     pub device: Rc<Device>,
     pub queue: Rc<Queue>,
     pub config: SurfaceConfiguration,
-    pub adapter: Rc<Adapter>
+    pub adapter: Rc<Adapter>,
 }
 
 impl WgpuState {
@@ -0,0 +1,210 @@
+/*!
+How long every cell has been alive, or how long ago it died
+
+Works with any [`Engine`] by comparing the live cells of one generation with
+the ones of the generation before, so it has to see every generation
+*/
+
+use std::collections::HashMap;
+
+use super::Engine;
+
+/// How many generations a dead cell is remembered for by default
+pub const DEFAULT_AGE_SPAN: u32 = 100;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Age {
+    /// How many generations it has survived, zero right after being born
+    Alive(u32),
+    /// How many generations ago it died, zero right after dying
+    Dead(u32),
+}
+
+#[derive(Debug, Clone)]
+pub struct CellAges {
+    /// Every live cell and the cells that died not too long ago
+    ages: HashMap<[i64; 2], Age>,
+    last_generation: Option<u64>,
+    /// Dead cells are forgotten once they've been dead for this many generations
+    dead_limit: u32,
+}
+
+impl Default for CellAges {
+    fn default() -> Self {
+        Self::new(DEFAULT_AGE_SPAN)
+    }
+}
+
+impl CellAges {
+    pub fn new(dead_limit: u32) -> Self {
+        Self {
+            ages: HashMap::new(),
+            last_generation: None,
+            dead_limit,
+        }
+    }
+
+    pub fn dead_limit(&self) -> u32 {
+        self.dead_limit
+    }
+
+    pub fn set_dead_limit(&mut self, dead_limit: u32) {
+        self.dead_limit = dead_limit;
+        self.ages
+            .retain(|_, age| !matches!(age, Age::Dead(dead) if *dead >= dead_limit));
+    }
+
+    /// `None` for cells that were never alive or died too long ago
+    pub fn age(&self, x: i64, y: i64) -> Option<Age> {
+        self.ages.get(&[x, y]).copied()
+    }
+
+    /// In no particular order
+    pub fn iter(&self) -> impl Iterator<Item = ([i64; 2], Age)> + '_ {
+        self.ages.iter().map(|(cell, age)| (*cell, *age))
+    }
+
+    pub fn clear(&mut self) {
+        self.ages.clear();
+        self.last_generation = None;
+    }
+
+    /// Takes in the current generation. Coming right after the last one ages everything,
+    /// the same one again only takes in the cells that were edited,
+    /// and anything else starts over with every live cell just born
+    pub fn observe(&mut self, engine: &dyn Engine) {
+        let generation = engine.generation();
+        let live = engine.live_cells();
+
+        match self.last_generation {
+            Some(last) if generation == last + 1 => self.stepped(live),
+            Some(last) if generation == last => self.edited(live),
+            _ => {
+                self.ages = live.into_iter().map(|cell| (cell, Age::Alive(0))).collect();
+            }
+        }
+
+        self.last_generation = Some(generation);
+    }
+
+    fn stepped(&mut self, live: Vec<[i64; 2]>) {
+        let mut next = HashMap::with_capacity(self.ages.len());
+
+        for cell in live {
+            let age = match self.ages.get(&cell) {
+                Some(Age::Alive(age)) => Age::Alive(age.saturating_add(1)),
+                _ => Age::Alive(0),
+            };
+            next.insert(cell, age);
+        }
+
+        for (cell, age) in &self.ages {
+            if next.contains_key(cell) {
+                continue;
+            }
+
+            let dead = match age {
+                Age::Alive(_) => 0,
+                Age::Dead(dead) => dead.saturating_add(1),
+            };
+            if dead < self.dead_limit {
+                next.insert(*cell, Age::Dead(dead));
+            }
+        }
+
+        self.ages = next;
+    }
+
+    fn edited(&mut self, live: Vec<[i64; 2]>) {
+        let mut next = HashMap::with_capacity(self.ages.len());
+
+        for cell in live {
+            let age = match self.ages.get(&cell) {
+                Some(Age::Alive(age)) => Age::Alive(*age),
+                _ => Age::Alive(0),
+            };
+            next.insert(cell, age);
+        }
+
+        for (cell, age) in &self.ages {
+            match age {
+                _ if next.contains_key(cell) => {}
+                Age::Alive(_) if self.dead_limit > 0 => {
+                    next.insert(*cell, Age::Dead(0));
+                }
+                Age::Alive(_) => {}
+                Age::Dead(dead) => {
+                    next.insert(*cell, Age::Dead(*dead));
+                }
+            }
+        }
+
+        self.ages = next;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SparseLife;
+
+    #[test]
+    fn blinker_ages() {
+        let mut sparse = SparseLife::from_cells([[-1, 0], [0, 0], [1, 0]], 0);
+        let mut ages = CellAges::new(3);
+
+        ages.observe(&sparse);
+        for _ in 0..2 {
+            sparse.step();
+            ages.observe(&sparse);
+        }
+
+        // The middle one never dies, the others keep dying and being born again
+        assert_eq!(ages.age(0, 0), Some(Age::Alive(2)));
+        assert_eq!(ages.age(1, 0), Some(Age::Alive(0)));
+        assert_eq!(ages.age(0, 1), Some(Age::Dead(0)));
+        assert_eq!(ages.age(5, 5), None);
+    }
+
+    #[test]
+    fn dead_cells_are_forgotten() {
+        let mut sparse = SparseLife::from_cells([[0, 0]], 0);
+        let mut ages = CellAges::new(2);
+
+        ages.observe(&sparse);
+        sparse.step();
+        ages.observe(&sparse);
+        assert_eq!(ages.age(0, 0), Some(Age::Dead(0)));
+
+        sparse.step();
+        ages.observe(&sparse);
+        assert_eq!(ages.age(0, 0), Some(Age::Dead(1)));
+
+        sparse.step();
+        ages.observe(&sparse);
+        assert_eq!(ages.age(0, 0), None);
+    }
+
+    #[test]
+    fn edits_and_jumps() {
+        let mut sparse = SparseLife::from_cells([[0, 0], [1, 0], [0, 1], [1, 1]], 0);
+        let mut ages = CellAges::default();
+
+        ages.observe(&sparse);
+        sparse.step_n(1);
+        ages.observe(&sparse);
+
+        sparse.set_cell(1, 1, false);
+        sparse.set_cell(5, 5, true);
+        ages.observe(&sparse);
+        assert_eq!(ages.age(0, 0), Some(Age::Alive(1)));
+        assert_eq!(ages.age(1, 1), Some(Age::Dead(0)));
+        assert_eq!(ages.age(5, 5), Some(Age::Alive(0)));
+
+        // Going back starts over
+        sparse.set_generation(0);
+        ages.observe(&sparse);
+        assert_eq!(ages.age(0, 0), Some(Age::Alive(0)));
+        assert_eq!(ages.age(1, 1), None);
+    }
+}
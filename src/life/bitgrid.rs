@@ -0,0 +1,803 @@
+/*!
+A grid of cells using a single bit per cell
+
+Every row starts at a new `u64` word and the bits
+go from the least significant one to the most significant one,
+i.e. the first column of a row is the lowest bit of its first word.
+The bits that are left over in the last word of a row are always zero
+
+Stepping works on whole words at a time (SWAR), the neighbor counts
+of all the 64 cells in a word are added up in parallel as 4 bit planes.
+What is past the edges is worked out once per step from the [`Topology`],
+as a row above the grid, a row below it and a cell on each side of every row
+*/
+
+use super::{Rule, Topology};
+
+pub const WORD_BITS: usize = u64::BITS as usize;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BitGrid {
+    columns: usize,
+    rows: usize,
+    words_per_row: usize,
+    words: Vec<u64>,
+}
+
+impl BitGrid {
+    pub fn new(columns: usize, rows: usize) -> Self {
+        let words_per_row = columns.div_ceil(WORD_BITS);
+
+        Self {
+            columns,
+            rows,
+            words_per_row,
+            words: vec![0; words_per_row * rows],
+        }
+    }
+
+    pub fn columns(&self) -> usize {
+        self.columns
+    }
+
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    pub fn words_per_row(&self) -> usize {
+        self.words_per_row
+    }
+
+    /// The index of the word that has the cell and the mask to get it
+    fn locate(&self, column: usize, row: usize) -> (usize, u64) {
+        assert!(
+            column < self.columns && row < self.rows,
+            "cell ({}, {}) is outside of a {}x{} grid",
+            column,
+            row,
+            self.columns,
+            self.rows
+        );
+
+        (
+            row * self.words_per_row + column / WORD_BITS,
+            1 << (column % WORD_BITS),
+        )
+    }
+
+    pub fn get(&self, column: usize, row: usize) -> bool {
+        let (index, mask) = self.locate(column, row);
+        self.words[index] & mask != 0
+    }
+
+    pub fn set(&mut self, column: usize, row: usize, alive: bool) {
+        let (index, mask) = self.locate(column, row);
+        if alive {
+            self.words[index] |= mask;
+        } else {
+            self.words[index] &= !mask;
+        }
+    }
+
+    pub fn toggle(&mut self, column: usize, row: usize) {
+        let (index, mask) = self.locate(column, row);
+        self.words[index] ^= mask;
+    }
+
+    pub fn clear(&mut self) {
+        self.words.fill(0);
+    }
+
+    /// Changes the dimensions of the grid,
+    /// the cells that still fit in it are kept as they were
+    pub fn resize(&mut self, columns: usize, rows: usize) {
+        let mut resized = Self::new(columns, rows);
+
+        let kept_words = self.words_per_row.min(resized.words_per_row);
+        for row in 0..self.rows.min(rows) {
+            resized.row_mut(row)[..kept_words].copy_from_slice(&self.row(row)[..kept_words]);
+        }
+        resized.clear_padding();
+
+        *self = resized;
+    }
+
+    /// Zeroes the bits after the last column in every row
+    fn clear_padding(&mut self) {
+        let mask = self.last_word_mask();
+        for row in self.words.chunks_exact_mut(self.words_per_row) {
+            if let Some(last) = row.last_mut() {
+                *last &= mask;
+            }
+        }
+    }
+
+    /// The bits that are actual cells in the last word of a row
+    pub fn last_word_mask(&self) -> u64 {
+        last_word_mask(self.columns)
+    }
+
+    pub fn row(&self, row: usize) -> &[u64] {
+        &self.words[row * self.words_per_row..(row + 1) * self.words_per_row]
+    }
+
+    pub fn row_mut(&mut self, row: usize) -> &mut [u64] {
+        &mut self.words[row * self.words_per_row..(row + 1) * self.words_per_row]
+    }
+
+    /// The cells of a row from left to right
+    pub fn iter_row(&self, row: usize) -> impl Iterator<Item = bool> + '_ {
+        let words = self.row(row);
+        (0..self.columns)
+            .map(move |column| words[column / WORD_BITS] >> (column % WORD_BITS) & 1 != 0)
+    }
+
+    /// Iterates over the rows from top to bottom
+    pub fn iter_rows(&self) -> impl Iterator<Item = impl Iterator<Item = bool> + '_> + '_ {
+        (0..self.rows).map(move |row| self.iter_row(row))
+    }
+
+    /// The column and row of every live cell, row by row
+    pub fn iter_alive(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.words
+            .chunks_exact(self.words_per_row.max(1))
+            .enumerate()
+            .flat_map(|(row, words)| {
+                words.iter().enumerate().flat_map(move |(index, word)| {
+                    SetBits(*word).map(move |bit| (index * WORD_BITS + bit, row))
+                })
+            })
+    }
+
+    /// The column and row of every cell that is alive in only one of the grids,
+    /// row by row. They have to be the same size
+    pub fn iter_different<'a>(
+        &'a self,
+        other: &'a Self,
+    ) -> impl Iterator<Item = (usize, usize)> + 'a {
+        assert_eq!([self.columns, self.rows], [other.columns, other.rows]);
+        let words_per_row = self.words_per_row.max(1);
+
+        self.words
+            .iter()
+            .zip(&other.words)
+            .enumerate()
+            .flat_map(move |(index, (a, b))| {
+                let (row, word) = (index / words_per_row, index % words_per_row);
+                SetBits(a ^ b).map(move |bit| (word * WORD_BITS + bit, row))
+            })
+    }
+
+    pub fn population(&self) -> usize {
+        self.words
+            .iter()
+            .map(|word| word.count_ones() as usize)
+            .sum()
+    }
+
+    /// The smallest rectangle with every live cell as `[min_column, min_row, max_column, max_row]`,
+    /// all inclusive, worked out a word at a time
+    pub fn bounding_box(&self) -> Option<[usize; 4]> {
+        let mut bounds: Option<[usize; 4]> = None;
+
+        for row in 0..self.rows {
+            for (index, word) in self.row(row).iter().enumerate() {
+                if *word == 0 {
+                    continue;
+                }
+
+                let first = index * WORD_BITS + word.trailing_zeros() as usize;
+                let last = index * WORD_BITS + (WORD_BITS - 1 - word.leading_zeros() as usize);
+
+                bounds = Some(match bounds {
+                    None => [first, row, last, row],
+                    Some([min_column, min_row, max_column, _]) => {
+                        [min_column.min(first), min_row, max_column.max(last), row]
+                    }
+                });
+            }
+        }
+
+        bounds
+    }
+
+    /// Computes the next generation in place,
+    /// only two rows worth of the previous generation are kept around while doing it.
+    /// Returns how many cells were born and how many died
+    pub fn step(&mut self, rule: &Rule, topology: Topology) -> [u64; 2] {
+        self.step_bands(1, rule, topology, None)
+    }
+
+    /// Same as [`BitGrid::step`] but the grid is split into horizontal bands
+    /// of rows and each band is stepped in its own thread
+    ///
+    /// The rows right outside of every band are copied before any thread starts,
+    /// that way a band never sees the already stepped rows of its neighbors
+    /// and the result is exactly the same as stepping in a single thread
+    pub fn step_parallel(&mut self, threads: usize, rule: &Rule, topology: Topology) -> [u64; 2] {
+        self.step_bands(threads, rule, topology, None)
+    }
+
+    /// Same as [`BitGrid::step_parallel`] but the cells that died are also set in `died`,
+    /// which is resized to fit if it has to and has every other cell cleared
+    pub fn step_parallel_deaths(
+        &mut self,
+        threads: usize,
+        rule: &Rule,
+        topology: Topology,
+        died: &mut BitGrid,
+    ) -> [u64; 2] {
+        if (died.columns, died.rows) != (self.columns, self.rows) {
+            *died = BitGrid::new(self.columns, self.rows);
+        }
+        self.step_bands(threads, rule, topology, Some(&mut died.words))
+    }
+
+    fn step_bands(
+        &mut self,
+        threads: usize,
+        rule: &Rule,
+        topology: Topology,
+        died: Option<&mut [u64]>,
+    ) -> [u64; 2] {
+        let bands = threads.min(self.rows / MIN_BAND_ROWS);
+        let halo = self.halo(topology);
+
+        if bands <= 1 {
+            return step_band(
+                &mut self.words,
+                &halo.above,
+                &halo.below,
+                &halo.edges,
+                self.columns,
+                rule,
+                died,
+            );
+        }
+
+        let words_per_row = self.words_per_row;
+        let columns = self.columns;
+        let band_rows = self.rows.div_ceil(bands);
+
+        // The row above and the row below every band, before stepping
+        let borders: Vec<(Vec<u64>, Vec<u64>)> = (0..self.rows)
+            .step_by(band_rows)
+            .map(|first_row| {
+                let last_row = (first_row + band_rows).min(self.rows) - 1;
+
+                let above = if first_row > 0 {
+                    self.row(first_row - 1).to_vec()
+                } else {
+                    halo.above.clone()
+                };
+                let below = if last_row + 1 < self.rows {
+                    self.row(last_row + 1).to_vec()
+                } else {
+                    halo.below.clone()
+                };
+
+                (above, below)
+            })
+            .collect();
+
+        let died_bands = died
+            .into_iter()
+            .flat_map(|died| died.chunks_mut(band_rows * words_per_row).map(Some))
+            .chain(std::iter::repeat_with(|| None));
+
+        std::thread::scope(|scope| {
+            let handles: Vec<_> = self
+                .words
+                .chunks_mut(band_rows * words_per_row)
+                .zip(borders.iter())
+                .zip(died_bands)
+                .enumerate()
+                .map(|(index, ((band, (above, below)), died))| {
+                    // The band's rows plus the ones above and below it
+                    let first_row = index * band_rows;
+                    let edges = &halo.edges[first_row..first_row + band.len() / words_per_row + 2];
+
+                    scope.spawn(move || step_band(band, above, below, edges, columns, rule, died))
+                })
+                .collect();
+
+            handles
+                .into_iter()
+                .fold([0, 0], |[births, deaths], handle| {
+                    let [band_births, band_deaths] = handle.join().unwrap();
+                    [births + band_births, deaths + band_deaths]
+                })
+        })
+    }
+
+    /// The cells right outside of the grid before stepping
+    fn halo(&self, topology: Topology) -> Halo {
+        let alive = |x: i64, y: i64| {
+            topology
+                .locate(x, y, self.columns, self.rows)
+                .is_some_and(|(column, row)| self.get(column, row))
+        };
+
+        let outside_row = |y: i64| {
+            let mut row = vec![0; self.words_per_row];
+            if topology != Topology::Dead {
+                for column in 0..self.columns {
+                    if alive(column as i64, y) {
+                        row[column / WORD_BITS] |= 1 << (column % WORD_BITS);
+                    }
+                }
+            }
+            row
+        };
+
+        let edges = if topology == Topology::Dead {
+            vec![[false; 2]; self.rows + 2]
+        } else {
+            (-1..=self.rows as i64)
+                .map(|y| [alive(-1, y), alive(self.columns as i64, y)])
+                .collect()
+        };
+
+        Halo {
+            above: outside_row(-1),
+            below: outside_row(self.rows as i64),
+            edges,
+        }
+    }
+}
+
+/// The cells right past the left and right ends of a row
+type Edges = [bool; 2];
+
+/// Everything right outside of a grid
+struct Halo {
+    above: Vec<u64>,
+    below: Vec<u64>,
+    /// From the row above the grid down to the row below it
+    edges: Vec<Edges>,
+}
+
+/// The positions of the ones in a word, lowest first
+struct SetBits(u64);
+
+impl Iterator for SetBits {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        if self.0 == 0 {
+            return None;
+        }
+
+        let bit = self.0.trailing_zeros() as usize;
+        self.0 &= self.0 - 1;
+        Some(bit)
+    }
+}
+
+/// Bands with fewer rows than this are not worth a thread of their own
+const MIN_BAND_ROWS: usize = 16;
+
+/// Steps consecutive rows in place,
+/// `above` and `below` being the rows right outside of them before stepping
+/// and `edges` the cells on either side of all of those rows.
+/// The cells that died are written in `died` if there is one, laid out like `words`.
+/// Returns how many cells were born and how many died
+fn step_band(
+    words: &mut [u64],
+    above: &[u64],
+    below: &[u64],
+    edges: &[Edges],
+    columns: usize,
+    rule: &Rule,
+    mut died: Option<&mut [u64]>,
+) -> [u64; 2] {
+    let words_per_row = above.len();
+    if words_per_row == 0 || words.is_empty() {
+        return [0, 0];
+    }
+    let rows = words.len() / words_per_row;
+    let last_mask = last_word_mask(columns);
+
+    // Rows with an extra word on each side to fit the edges,
+    // rolling down as the band gets stepped
+    let mut above = extend_row(above, edges[0], columns);
+    let mut current = extend_row(&words[..words_per_row], edges[1], columns);
+    let (mut births, mut deaths) = (0, 0);
+
+    for row in 0..rows {
+        let next = (row + 1) * words_per_row;
+        let below = extend_row(
+            words.get(next..next + words_per_row).unwrap_or(below),
+            edges[row + 2],
+            columns,
+        );
+
+        let out = &mut words[row * words_per_row..(row + 1) * words_per_row];
+        step_row(&above, &current, &below, out, last_mask, rule);
+
+        for (word, (next, before)) in out.iter().zip(&current[1..]).enumerate() {
+            // The cell right after the last column is only there for its neighbors
+            let before = if word + 1 == words_per_row {
+                before & last_mask
+            } else {
+                *before
+            };
+
+            births += (next & !before).count_ones() as u64;
+            deaths += (!next & before).count_ones() as u64;
+            if let Some(died) = died.as_deref_mut() {
+                died[row * words_per_row + word] = !next & before;
+            }
+        }
+
+        above = std::mem::replace(&mut current, below);
+    }
+
+    [births, deaths]
+}
+
+/// A copy of the row with a word in front and one at the back,
+/// the cell to the left of the first column being the highest bit of the first word
+/// and the one to the right of the last column right after it
+fn extend_row(row: &[u64], [west, east]: Edges, columns: usize) -> Vec<u64> {
+    let mut extended = Vec::with_capacity(row.len() + 2);
+    extended.push((west as u64) << (WORD_BITS - 1));
+    extended.extend_from_slice(row);
+    extended.push(0);
+
+    extended[1 + columns / WORD_BITS] |= (east as u64) << (columns % WORD_BITS);
+
+    extended
+}
+
+fn last_word_mask(columns: usize) -> u64 {
+    match columns % WORD_BITS {
+        0 => u64::MAX,
+        used => (1 << used) - 1,
+    }
+}
+
+/// The sum and carry of adding three bits, for 64 lanes at once
+#[inline(always)]
+fn full_add(a: u64, b: u64, c: u64) -> (u64, u64) {
+    let partial = a ^ b;
+    (partial ^ c, (a & b) | (c & partial))
+}
+
+#[inline(always)]
+fn half_add(a: u64, b: u64) -> (u64, u64) {
+    (a ^ b, a & b)
+}
+
+/// Every cell gets the cell to its left, a.k.a. the whole row shifted to the right
+#[inline(always)]
+fn west(row: &[u64], word: usize) -> u64 {
+    let carry = if word > 0 { row[word - 1] >> 63 } else { 0 };
+    (row[word] << 1) | carry
+}
+
+/// Every cell gets the cell to its right
+#[inline(always)]
+fn east(row: &[u64], word: usize) -> u64 {
+    let carry = row.get(word + 1).map_or(0, |next| next << 63);
+    (row[word] >> 1) | carry
+}
+
+/// The bit planes of the live neighbor count of every cell in a word,
+/// from the least significant to the most significant
+#[inline(always)]
+pub(super) fn neighbor_count(
+    above: &[u64],
+    current: &[u64],
+    below: &[u64],
+    word: usize,
+) -> [u64; 4] {
+    let (ones_above, twos_above) = full_add(west(above, word), above[word], east(above, word));
+    let (ones_below, twos_below) = full_add(west(below, word), below[word], east(below, word));
+    let (ones_side, twos_side) = half_add(west(current, word), east(current, word));
+
+    let (ones, twos_carry) = full_add(ones_above, ones_below, ones_side);
+    let (twos_partial, fours_a) = full_add(twos_above, twos_below, twos_side);
+    let (twos, fours_b) = half_add(twos_partial, twos_carry);
+
+    [ones, twos, fours_a ^ fours_b, fours_a & fours_b]
+}
+
+/// The rule applied to a whole word given the neighbor count bit planes
+#[inline(always)]
+pub(super) fn next_word(planes: [u64; 4], current: u64, rule: &Rule) -> u64 {
+    if *rule == Rule::CONWAY {
+        let [ones, twos, fours, eights] = planes;
+
+        // two or three neighbors
+        let two_or_three = twos & !fours & !eights;
+
+        return two_or_three & (ones | current);
+    }
+
+    let mut born = 0;
+    let mut survives = 0;
+
+    for neighbors in 0..=8 {
+        let (birth, survival) = (rule.born(neighbors), rule.survives(neighbors));
+        if !birth && !survival {
+            continue;
+        }
+
+        // The cells whose count matches `neighbors` bit by bit
+        let matching = planes
+            .iter()
+            .enumerate()
+            .fold(u64::MAX, |matching, (bit, plane)| {
+                matching
+                    & if neighbors >> bit & 1 == 1 {
+                        *plane
+                    } else {
+                        !plane
+                    }
+            });
+
+        if birth {
+            born |= matching;
+        }
+        if survival {
+            survives |= matching;
+        }
+    }
+
+    (!current & born) | (current & survives)
+}
+
+/// Writes the next generation of `current` into `out`,
+/// `above` and `below` being the rows around it before stepping,
+/// all three of them extended with [`extend_row`]
+fn step_row(
+    above: &[u64],
+    current: &[u64],
+    below: &[u64],
+    out: &mut [u64],
+    last_mask: u64,
+    rule: &Rule,
+) {
+    for (word, out) in out.iter_mut().enumerate() {
+        let planes = neighbor_count(above, current, below, word + 1);
+        *out = next_word(planes, current[word + 1], rule);
+    }
+
+    if let Some(last) = out.last_mut() {
+        *last &= last_mask;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::life::tests::soup;
+
+    fn random_grid(columns: usize, rows: usize, seed: u64) -> BitGrid {
+        let mut grid = BitGrid::new(columns, rows);
+        let region = [0, 0, columns as i64 - 1, rows as i64 - 1];
+        for [x, y] in soup(region, 1.0 / 3.0, seed) {
+            grid.set(x as usize, y as usize, true);
+        }
+        grid
+    }
+
+    /// Straightforward cell by cell stepping to check against
+    fn naive_step(grid: &BitGrid, rule: &Rule, topology: Topology) -> BitGrid {
+        let mut next = BitGrid::new(grid.columns(), grid.rows());
+        for row in 0..grid.rows() {
+            for column in 0..grid.columns() {
+                let mut neighbors = 0;
+                for offset_y in -1..=1 {
+                    for offset_x in -1..=1 {
+                        let (x, y) = (column as i64 + offset_x, row as i64 + offset_y);
+                        let alive = topology
+                            .locate(x, y, grid.columns(), grid.rows())
+                            .is_some_and(|(x, y)| grid.get(x, y));
+
+                        if (offset_x, offset_y) != (0, 0) && alive {
+                            neighbors += 1;
+                        }
+                    }
+                }
+                next.set(column, row, rule.next(grid.get(column, row), neighbors));
+            }
+        }
+        next
+    }
+
+    #[test]
+    fn get_set_toggle() {
+        let mut grid = BitGrid::new(130, 3);
+
+        grid.set(129, 2, true);
+        grid.set(64, 0, true);
+        grid.toggle(63, 1);
+        grid.toggle(64, 0);
+
+        assert!(grid.get(129, 2));
+        assert!(grid.get(63, 1));
+        assert!(!grid.get(64, 0));
+        assert_eq!(grid.population(), 2);
+        assert_eq!(grid.words_per_row(), 3);
+    }
+
+    #[test]
+    fn uses_a_bit_per_cell() {
+        let grid = BitGrid::new(1000, 1000);
+        assert_eq!(grid.words.len() * std::mem::size_of::<u64>(), 16 * 1000 * 8);
+    }
+
+    #[test]
+    fn iter_rows_matches_get() {
+        let grid = random_grid(70, 4, 0x5EED);
+
+        for (row, cells) in grid.iter_rows().enumerate() {
+            for (column, alive) in cells.enumerate() {
+                assert_eq!(alive, grid.get(column, row));
+            }
+        }
+    }
+
+    #[test]
+    fn iter_alive_matches_get() {
+        let grid = random_grid(130, 5, 7);
+
+        let alive: Vec<_> = grid.iter_alive().collect();
+        let expected: Vec<_> = (0..5)
+            .flat_map(|row| (0..130).map(move |column| (column, row)))
+            .filter(|&(column, row)| grid.get(column, row))
+            .collect();
+
+        assert_eq!(alive, expected);
+    }
+
+    #[test]
+    fn bounding_box_matches_iter_alive() {
+        let mut grid = BitGrid::new(200, 30);
+        assert_eq!(grid.bounding_box(), None);
+
+        for (column, row) in [(130, 4), (64, 9), (199, 9), (70, 21)] {
+            grid.set(column, row, true);
+        }
+        assert_eq!(grid.bounding_box(), Some([64, 4, 199, 21]));
+    }
+
+    #[test]
+    fn resize_keeps_cells_and_padding() {
+        let mut grid = random_grid(100, 10, 42);
+        let original = grid.clone();
+
+        grid.resize(70, 12);
+
+        for row in 0..10 {
+            for column in 0..70 {
+                assert_eq!(grid.get(column, row), original.get(column, row));
+            }
+        }
+        assert_eq!(grid.row(0)[1] & !grid.last_word_mask(), 0);
+
+        grid.resize(100, 12);
+        assert!(!grid.get(80, 0));
+    }
+
+    #[test]
+    fn parallel_step_matches_single_thread() {
+        for (columns, rows) in [(10, 10), (100, 33), (130, 64), (257, 200)] {
+            let mut single = random_grid(columns, rows, (columns + rows * 7) as u64);
+
+            for threads in [1, 2, 3, 4, 7, 16] {
+                let mut parallel = single.clone();
+
+                for _ in 0..5 {
+                    let before = single.population() as u64;
+                    let [births, deaths] = single.step(&Rule::CONWAY, Topology::Dead);
+                    assert_eq!(before + births - deaths, single.population() as u64);
+
+                    let changes = parallel.step_parallel(threads, &Rule::CONWAY, Topology::Dead);
+                    assert_eq!(parallel, single, "{}x{} {} threads", columns, rows, threads);
+                    assert_eq!(changes, [births, deaths]);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn deaths_are_the_cells_that_went_away() {
+        let mut grid = random_grid(257, 200, 3);
+        let mut died = BitGrid::new(0, 0);
+
+        for threads in [1, 4, 7] {
+            let before = grid.clone();
+            let [_, deaths] =
+                grid.step_parallel_deaths(threads, &Rule::CONWAY, Topology::Torus, &mut died);
+
+            let expected: Vec<_> = before
+                .iter_alive()
+                .filter(|&(column, row)| !grid.get(column, row))
+                .collect();
+            assert_eq!(died.iter_alive().collect::<Vec<_>>(), expected);
+            assert_eq!(deaths, expected.len() as u64);
+        }
+    }
+
+    #[test]
+    fn swar_step_matches_naive_step() {
+        for (columns, rows) in [(1, 1), (5, 5), (63, 7), (64, 64), (65, 3), (200, 50)] {
+            let mut grid = random_grid(columns, rows, (columns * 31 + rows) as u64);
+
+            for _ in 0..8 {
+                let expected = naive_step(&grid, &Rule::CONWAY, Topology::Dead);
+                grid.step(&Rule::CONWAY, Topology::Dead);
+                assert_eq!(grid, expected, "{}x{}", columns, rows);
+            }
+        }
+    }
+
+    #[test]
+    fn other_rules_match_naive_step() {
+        let rules = [
+            "B36/S23",
+            "B3678/S34678",
+            "B2/S",
+            "B0/S8",
+            "B0123478/S01234678",
+        ];
+
+        for rule in rules.map(|rule| rule.parse::<Rule>().unwrap()) {
+            let mut grid = random_grid(100, 40, 99);
+
+            for _ in 0..6 {
+                let expected = naive_step(&grid, &rule, Topology::Dead);
+                grid.step(&rule, Topology::Dead);
+                assert_eq!(grid, expected, "{}", rule);
+            }
+        }
+    }
+
+    #[test]
+    fn topologies_match_naive_step() {
+        let rule: Rule = "B3678/S34678".parse().unwrap();
+
+        for topology in Topology::ALL {
+            for (columns, rows) in [(1, 1), (2, 3), (7, 5), (64, 20), (70, 40)] {
+                let mut grid = random_grid(columns, rows, (columns * 13 + rows) as u64);
+
+                for _ in 0..6 {
+                    let expected = naive_step(&grid, &rule, topology);
+                    grid.step_parallel(3, &rule, topology);
+                    assert_eq!(grid, expected, "{} {}x{}", topology, columns, rows);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn counts_births_and_deaths() {
+        // A blinker going from vertical to horizontal right on the torus' seam
+        let mut grid = BitGrid::new(64, 5);
+        for row in 1..4 {
+            grid.set(0, row, true);
+        }
+
+        assert_eq!(grid.step(&Rule::CONWAY, Topology::Torus), [2, 2]);
+        assert!(grid.get(63, 2) && grid.get(0, 2) && grid.get(1, 2));
+    }
+
+    #[test]
+    fn glider_goes_around_a_torus() {
+        let mut grid = BitGrid::new(8, 8);
+        for (column, row) in [(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)] {
+            grid.set(column, row, true);
+        }
+        let start = grid.clone();
+
+        // A cell diagonally every 4 generations, all the way around
+        for _ in 0..32 {
+            grid.step(&Rule::CONWAY, Topology::Torus);
+        }
+
+        assert_eq!(grid, start);
+    }
+}
@@ -0,0 +1,460 @@
+/*!
+Splitting a universe into objects and telling what every one of them is
+
+Objects are groups of live cells touching each other, diagonals included.
+Every object gets run on its own until it repeats, which tells whether it is a
+still life, an oscillator or a spaceship, and then gets a code that is the same
+no matter its phase, rotation or reflection. The codes follow apgsearch's:
+`xs` for still lifes, `xp` for oscillators and `xq` for spaceships, the population
+or period, and then the cells written in extended Wechsler format
+*/
+
+use std::collections::{HashMap, HashSet};
+use std::sync::OnceLock;
+
+use super::{CycleDetector, Engine, Rule, SparseLife};
+
+/// Objects that don't repeat within this many generations are left unclassified
+pub const MAX_CENSUS_PERIOD: u64 = 256;
+
+/// Some objects of Conway's Life, drawn row by row with `O` for live cells
+const NAMED_OBJECTS: [(&str, &str); 20] = [
+    ("block", "OO/OO"),
+    ("blinker", "OOO"),
+    ("beehive", ".OO./O..O/.OO."),
+    ("loaf", ".OO./O..O/.O.O/..O."),
+    ("boat", "OO./O.O/.O."),
+    ("ship", "OO./O.O/.OO"),
+    ("tub", ".O./O.O/.O."),
+    ("pond", ".OO./O..O/O..O/.OO."),
+    ("long boat", "OO../O.O./.O.O/..O."),
+    ("barge", ".O../O.O./.O.O/..O."),
+    ("mango", ".OO../O..O./.O..O/..OO."),
+    ("eater 1", "OO../O.O./..O./..OO"),
+    ("integral sign", "OO./O.O/..O/..OO"),
+    ("glider", ".O./..O/OOO"),
+    ("toad", ".OOO/OOO."),
+    ("beacon", "OO../OO../..OO/..OO"),
+    ("lightweight spaceship", ".O..O/O..../O...O/OOOO."),
+    (
+        "medium weight spaceship",
+        "...O../.O...O/O...../O....O/OOOOO.",
+    ),
+    (
+        "heavy weight spaceship",
+        "...OO../.O....O/O....../O.....O/OOOOOO.",
+    ),
+    ("pentadecathlon", "..O....O../OO.OOOO.OO/..O....O.."),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum ObjectKind {
+    StillLife,
+    Oscillator {
+        period: u64,
+    },
+    Spaceship {
+        period: u64,
+    },
+    /// Did not repeat on its own, e.g. something still growing
+    /// or two objects close enough to be taken as one
+    Unclassified,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CensusEntry {
+    /// The apgsearch style code, the same for every phase and orientation
+    pub code: String,
+    /// The usual name of the object, only known for some objects in Conway's Life
+    pub name: Option<&'static str>,
+    pub kind: ObjectKind,
+    pub count: usize,
+}
+
+/// Every kind of object found, the most common first
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Census {
+    pub entries: Vec<CensusEntry>,
+}
+
+impl Census {
+    /// How many objects there are in total
+    pub fn objects(&self) -> usize {
+        self.entries.iter().map(|entry| entry.count).sum()
+    }
+
+    pub fn count(&self, code: &str) -> usize {
+        self.entries
+            .iter()
+            .find(|entry| entry.code == code)
+            .map_or(0, |entry| entry.count)
+    }
+}
+
+/// Takes a census of the live cells of the engine, using its rule to run the objects
+pub fn census(engine: &dyn Engine) -> Census {
+    let rule = engine.rule();
+
+    let mut counts: HashMap<(String, ObjectKind), usize> = HashMap::new();
+    let mut unclassified = Vec::new();
+    for object in split_objects(engine.live_cells()) {
+        let (code, kind) = classify(&object, &rule);
+        if kind == ObjectKind::Unclassified {
+            unclassified.push((object, code));
+        } else {
+            *counts.entry((code, kind)).or_default() += 1;
+        }
+    }
+
+    // Some objects come apart in some of their phases, like the beacon,
+    // so the pieces that don't make sense alone are tried together
+    for group in group_close_objects(unclassified) {
+        let cells: Vec<_> = group
+            .iter()
+            .flat_map(|(object, _)| object)
+            .copied()
+            .collect();
+        match classify(&cells, &rule) {
+            (code, kind) if group.len() > 1 && kind != ObjectKind::Unclassified => {
+                *counts.entry((code, kind)).or_default() += 1;
+            }
+            _ => {
+                for (_, code) in group {
+                    *counts.entry((code, ObjectKind::Unclassified)).or_default() += 1;
+                }
+            }
+        }
+    }
+
+    let mut entries: Vec<_> = counts
+        .into_iter()
+        .map(|((code, kind), count)| CensusEntry {
+            name: object_name(&code, &rule),
+            code,
+            kind,
+            count,
+        })
+        .collect();
+    entries.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.code.cmp(&b.code)));
+
+    Census { entries }
+}
+
+/// Groups the cells that touch each other, diagonals included
+pub fn split_objects(cells: Vec<[i64; 2]>) -> Vec<Vec<[i64; 2]>> {
+    let mut left: HashSet<[i64; 2]> = cells.into_iter().collect();
+    let mut objects = Vec::new();
+
+    while let Some(&start) = left.iter().next() {
+        left.remove(&start);
+
+        let mut object = vec![start];
+        let mut index = 0;
+        while let Some(&[x, y]) = object.get(index) {
+            for offset_y in -1..=1 {
+                for offset_x in -1..=1 {
+                    if left.remove(&[x + offset_x, y + offset_y]) {
+                        object.push([x + offset_x, y + offset_y]);
+                    }
+                }
+            }
+            index += 1;
+        }
+
+        objects.push(object);
+    }
+
+    objects
+}
+
+/// Puts together the objects that are close enough to affect each other,
+/// as in two squares or less apart
+fn group_close_objects<T>(objects: Vec<(Vec<[i64; 2]>, T)>) -> Vec<Vec<(Vec<[i64; 2]>, T)>> {
+    let close = |a: &[[i64; 2]], b: &[[i64; 2]]| {
+        a.iter().any(|[ax, ay]| {
+            b.iter()
+                .any(|[bx, by]| (ax - bx).abs() <= 2 && (ay - by).abs() <= 2)
+        })
+    };
+
+    let mut groups: Vec<Vec<(Vec<[i64; 2]>, T)>> = Vec::new();
+    for object in objects {
+        let (touching, mut apart): (Vec<_>, Vec<_>) = groups
+            .into_iter()
+            .partition(|group| group.iter().any(|(cells, _)| close(cells, &object.0)));
+
+        let mut merged: Vec<_> = touching.into_iter().flatten().collect();
+        merged.push(object);
+        apart.push(merged);
+        groups = apart;
+    }
+
+    groups
+}
+
+/// The code and kind of a single object
+pub fn classify(cells: &[[i64; 2]], rule: &Rule) -> (String, ObjectKind) {
+    let mut sparse = SparseLife::from_cells(cells.iter().copied(), 0);
+    let mut detector = CycleDetector::new();
+
+    let cycle = sparse.set_rule(rule.clone()).ok().and_then(|()| {
+        detector.observe(&sparse);
+        // A cycle is only believed once it went around twice
+        (0..2 * MAX_CENSUS_PERIOD).find_map(|_| {
+            sparse.step();
+            detector.observe(&sparse)
+        })
+    });
+
+    // Only the ones that come back to how they started, otherwise it would be
+    // counted by whatever it turns into instead of what it is now
+    let Some(cycle) = cycle.filter(|cycle| cycle.start == 0) else {
+        return (
+            format!("xx{}_{}", cells.len(), wechsler(cells)),
+            ObjectKind::Unclassified,
+        );
+    };
+
+    let mut phases = vec![cells.to_vec()];
+    let mut sparse = SparseLife::from_cells(cells.iter().copied(), 0);
+    sparse.set_rule(rule.clone()).unwrap();
+    for _ in 1..cycle.period {
+        sparse.step();
+        phases.push(sparse.live_cells());
+    }
+
+    let canonical = phases
+        .iter()
+        .flat_map(|phase| {
+            TRANSFORMS
+                .iter()
+                .map(move |transform| wechsler(&transformed(phase, transform)))
+        })
+        .min_by(|a, b| a.len().cmp(&b.len()).then_with(|| a.cmp(b)))
+        .unwrap();
+
+    let (prefix, kind) = if cycle.is_moving() {
+        (
+            format!("xq{}", cycle.period),
+            ObjectKind::Spaceship {
+                period: cycle.period,
+            },
+        )
+    } else if cycle.period == 1 {
+        (format!("xs{}", cells.len()), ObjectKind::StillLife)
+    } else {
+        (
+            format!("xp{}", cycle.period),
+            ObjectKind::Oscillator {
+                period: cycle.period,
+            },
+        )
+    };
+
+    (format!("{}_{}", prefix, canonical), kind)
+}
+
+/// The name in [`NAMED_OBJECTS`] of the object with that code, only for
+/// Conway's Life since other rules make the same cells into something else entirely
+fn object_name(code: &str, rule: &Rule) -> Option<&'static str> {
+    static NAMES: OnceLock<HashMap<String, &'static str>> = OnceLock::new();
+
+    if *rule != Rule::CONWAY {
+        return None;
+    }
+
+    NAMES
+        .get_or_init(|| {
+            NAMED_OBJECTS
+                .iter()
+                .map(|(name, drawing)| (classify(&parse_drawing(drawing), rule).0, *name))
+                .collect()
+        })
+        .get(code)
+        .copied()
+}
+
+fn parse_drawing(drawing: &str) -> Vec<[i64; 2]> {
+    drawing
+        .split('/')
+        .enumerate()
+        .flat_map(|(y, row)| {
+            row.chars()
+                .enumerate()
+                .filter(|(_, cell)| *cell == 'O')
+                .map(move |(x, _)| [x as i64, y as i64])
+        })
+        .collect()
+}
+
+/// The 8 rotations and reflections of the plane as where x and y go
+const TRANSFORMS: [[[i64; 2]; 2]; 8] = [
+    [[1, 0], [0, 1]],
+    [[0, -1], [1, 0]],
+    [[-1, 0], [0, -1]],
+    [[0, 1], [-1, 0]],
+    [[-1, 0], [0, 1]],
+    [[1, 0], [0, -1]],
+    [[0, 1], [1, 0]],
+    [[0, -1], [-1, 0]],
+];
+
+fn transformed(cells: &[[i64; 2]], [[xx, xy], [yx, yy]]: &[[i64; 2]; 2]) -> Vec<[i64; 2]> {
+    cells
+        .iter()
+        .map(|[x, y]| [xx * x + xy * y, yx * x + yy * y])
+        .collect()
+}
+
+/// The extended Wechsler format: the cells get split into strips of 5 rows, every
+/// column of a strip is a digit from `0` to `v` with the top row as the lowest bit,
+/// strips are separated by `z` and runs of zeros are written with `w`, `x` and `y`
+fn wechsler(cells: &[[i64; 2]]) -> String {
+    const DIGITS: &[u8; 32] = b"0123456789abcdefghijklmnopqrstuv";
+
+    let (Some(min_x), Some(min_y)) = (
+        cells.iter().map(|[x, _]| *x).min(),
+        cells.iter().map(|[_, y]| *y).min(),
+    ) else {
+        return String::from("0");
+    };
+    let width = cells.iter().map(|[x, _]| x - min_x).max().unwrap() as usize + 1;
+    let strips = cells.iter().map(|[_, y]| y - min_y).max().unwrap() as usize / 5 + 1;
+
+    let mut columns = vec![vec![0u8; width]; strips];
+    for [x, y] in cells {
+        let (x, y) = ((x - min_x) as usize, (y - min_y) as usize);
+        columns[y / 5][x] |= 1 << (y % 5);
+    }
+
+    let mut code = String::new();
+    for (index, strip) in columns.iter().enumerate() {
+        if index > 0 {
+            code.push('z');
+        }
+
+        let used = strip
+            .iter()
+            .rposition(|column| *column != 0)
+            .map_or(0, |last| last + 1);
+        let mut zeros = 0;
+
+        for column in &strip[..used] {
+            if *column == 0 {
+                zeros += 1;
+                continue;
+            }
+
+            push_zeros(&mut code, zeros);
+            zeros = 0;
+            code.push(DIGITS[*column as usize] as char);
+        }
+    }
+
+    code
+}
+
+/// `w` is two zeros, `x` is three and `y` followed by a digit is four up to 39
+fn push_zeros(code: &mut String, mut zeros: usize) {
+    const DIGITS: &[u8; 36] = b"0123456789abcdefghijklmnopqrstuvwxyz";
+
+    while zeros > 0 {
+        match zeros {
+            1 => code.push('0'),
+            2 => code.push('w'),
+            3 => code.push('x'),
+            _ => {
+                let run = zeros.min(39);
+                code.push('y');
+                code.push(DIGITS[run - 4] as char);
+                zeros -= run;
+                continue;
+            }
+        }
+        zeros = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn placed(drawing: &str, [x, y]: [i64; 2]) -> Vec<[i64; 2]> {
+        parse_drawing(drawing)
+            .into_iter()
+            .map(|[cell_x, cell_y]| [cell_x + x, cell_y + y])
+            .collect()
+    }
+
+    #[test]
+    fn codes_match_apgsearch() {
+        let code = |drawing| classify(&parse_drawing(drawing), &Rule::CONWAY).0;
+
+        assert_eq!(code("OO/OO"), "xs4_33");
+        assert_eq!(code("OOO"), "xp2_7");
+        assert_eq!(code(".O./..O/OOO"), "xq4_153");
+        assert_eq!(code(".OO./O..O/.OO."), "xs6_696");
+        assert_eq!(code("OO./O.O/.O."), "xs5_253");
+    }
+
+    #[test]
+    fn named_objects_are_what_they_say() {
+        for (name, drawing) in NAMED_OBJECTS {
+            let (_, kind) = classify(&parse_drawing(drawing), &Rule::CONWAY);
+            assert_ne!(kind, ObjectKind::Unclassified, "{}", name);
+        }
+
+        let kind = |drawing| classify(&parse_drawing(drawing), &Rule::CONWAY).1;
+        assert_eq!(
+            kind(NAMED_OBJECTS[18].1),
+            ObjectKind::Spaceship { period: 4 }
+        );
+        assert_eq!(
+            kind(NAMED_OBJECTS[19].1),
+            ObjectKind::Oscillator { period: 15 }
+        );
+    }
+
+    #[test]
+    fn beacon_in_two_pieces() {
+        // The phase where the two halves don't touch
+        let beacon = [[0, 0], [1, 0], [0, 1], [3, 2], [2, 3], [3, 3]];
+        let sparse = SparseLife::from_cells(beacon, 0);
+
+        let census = census(&sparse);
+        assert_eq!(census.objects(), 1);
+        assert_eq!(census.entries[0].name, Some("beacon"));
+    }
+
+    #[test]
+    fn counts_objects_in_any_orientation() {
+        let mut cells = Vec::new();
+        cells.extend(placed("OO/OO", [0, 0]));
+        cells.extend(placed("OO/OO", [-20, 7]));
+        cells.extend(placed("O/O/O", [10, 0]));
+        // A glider going the other way
+        cells.extend(placed("OOO/O../.O.", [0, 20]));
+        cells.extend(placed(".OO./O..O/.O.O/..O.", [30, 30]));
+
+        let sparse = SparseLife::from_cells(cells, 0);
+        let census = census(&sparse);
+
+        assert_eq!(census.objects(), 5);
+        assert_eq!(census.count("xs4_33"), 2);
+        assert_eq!(census.count("xp2_7"), 1);
+        assert_eq!(census.count("xq4_153"), 1);
+
+        assert_eq!(census.entries[0].name, Some("block"));
+        assert_eq!(census.entries[0].kind, ObjectKind::StillLife);
+        assert!(census
+            .entries
+            .iter()
+            .any(|entry| entry.name == Some("loaf")));
+    }
+
+    #[test]
+    fn zero_runs() {
+        let cells = [[0, 0], [3, 0], [8, 0], [60, 0]];
+        assert_eq!(wechsler(&cells), "1w1y01yzy81");
+    }
+}
@@ -0,0 +1,267 @@
+/*!
+Noticing when a universe starts repeating itself
+
+Every generation that is seen gets hashed relative to the top left corner of its
+live cells, once a hash comes up again the universe is probably in a cycle:
+a still life if it repeats every generation, an oscillator if it takes longer,
+and a spaceship if it looks the same but somewhere else.
+
+Different cells can hash the same, so a cycle is only believed once the cells
+that came up again are seen once more exactly a period later
+*/
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+
+use super::history::sorted_cells;
+use super::Engine;
+
+/// Past this many generations the oldest ones are forgotten
+/// and cycles longer than that go unnoticed
+const MAX_REMEMBERED: usize = 1 << 20;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cycle {
+    /// The first generation that is part of the cycle
+    pub start: u64,
+    /// How many generations until it looks the same again
+    pub period: u64,
+    /// How far it moves every period
+    pub displacement: [i64; 2],
+}
+
+impl Cycle {
+    /// Nothing changes from one generation to the next
+    pub fn is_still(&self) -> bool {
+        self.period == 1 && self.displacement == [0, 0]
+    }
+
+    pub fn is_moving(&self) -> bool {
+        self.displacement != [0, 0]
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct CycleDetector {
+    /// The generation and corner of every pattern hash seen
+    seen: HashMap<u64, (u64, [i64; 2])>,
+    last_generation: Option<u64>,
+    candidate: Option<Candidate>,
+    cycle: Option<Cycle>,
+}
+
+/// A hash that came up again, waiting to see the same cells a period later
+#[derive(Debug, Clone)]
+struct Candidate {
+    cycle: Cycle,
+    hash: u64,
+    generation: u64,
+    corner: [i64; 2],
+    cells: Vec<([i64; 2], u8)>,
+}
+
+impl CycleDetector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The cycle found so far, if any
+    pub fn cycle(&self) -> Option<Cycle> {
+        self.cycle
+    }
+
+    /// Forgets everything, has to be called when the cells are edited
+    pub fn reset(&mut self) {
+        self.seen.clear();
+        self.last_generation = None;
+        self.candidate = None;
+        self.cycle = None;
+    }
+
+    /// Takes in the current generation, it should be called on every one of them
+    /// to get the shortest period and the right start.
+    /// Going back to an earlier generation starts over,
+    /// seeing the same generation again does nothing
+    pub fn observe(&mut self, engine: &dyn Engine) -> Option<Cycle> {
+        self.observe_cells(engine.generation(), &sorted_cells(engine))
+    }
+
+    /// [`CycleDetector::observe`] for the live and dying cells of `generation`,
+    /// sorted row by row like [`History::current_cells`](super::History::current_cells)
+    pub fn observe_cells(&mut self, generation: u64, cells: &[([i64; 2], u8)]) -> Option<Cycle> {
+        match self.last_generation {
+            Some(last) if generation < last => self.reset(),
+            Some(last) if generation == last => return self.cycle,
+            _ => {}
+        }
+        self.last_generation = Some(generation);
+
+        if self.cycle.is_some() {
+            return self.cycle;
+        }
+
+        if self.seen.len() >= MAX_REMEMBERED {
+            self.seen.clear();
+        }
+
+        let (hash, corner) = pattern_hash(cells);
+
+        if let Some(candidate) = self.candidate.take() {
+            let due = candidate.generation + candidate.cycle.period;
+            if generation < due {
+                self.candidate = Some(candidate);
+            } else if generation == due && candidate.repeats_as(cells, corner) {
+                self.cycle = Some(candidate.cycle);
+                return self.cycle;
+            } else {
+                // Only the hash was the same, the cells it was mistaken for are the newest now
+                self.seen
+                    .insert(candidate.hash, (candidate.generation, candidate.corner));
+            }
+        }
+
+        match self.seen.get(&hash) {
+            Some(&(start, start_corner)) if self.candidate.is_none() => {
+                self.candidate = Some(Candidate {
+                    cycle: Cycle {
+                        start,
+                        period: generation - start,
+                        displacement: [corner[0] - start_corner[0], corner[1] - start_corner[1]],
+                    },
+                    hash,
+                    generation,
+                    corner,
+                    cells: cells.to_vec(),
+                });
+            }
+            Some(_) => {}
+            None => {
+                self.seen.insert(hash, (generation, corner));
+            }
+        }
+
+        self.cycle
+    }
+}
+
+impl Candidate {
+    /// Whether `cells` with their `corner` are the candidate's cells moved as far as the cycle says
+    fn repeats_as(&self, cells: &[([i64; 2], u8)], corner: [i64; 2]) -> bool {
+        let [dx, dy] = self.cycle.displacement;
+        corner == [self.corner[0] + dx, self.corner[1] + dy]
+            && cells.len() == self.cells.len()
+            && self
+                .cells
+                .iter()
+                .zip(cells)
+                .all(|(([x, y], before), (cell, after))| {
+                    *cell == [x + dx, y + dy] && before == after
+                })
+    }
+}
+
+/// A hash of the cells relative to the top left corner of the live ones'
+/// bounding box, along with that corner. Two generations that look the same
+/// hash the same even if the pattern moved, the corners tell how far it went
+fn pattern_hash(cells: &[([i64; 2], u8)]) -> (u64, [i64; 2]) {
+    let mut live = cells.iter().filter(|(_, state)| *state == 1);
+    let corner = [
+        live.clone().map(|([x, _], _)| *x).min().unwrap_or(0),
+        live.next().map_or(0, |([_, y], _)| *y),
+    ];
+
+    let mut hasher = DefaultHasher::new();
+    for ([x, y], state) in cells {
+        ([x - corner[0], y - corner[1]], state).hash(&mut hasher);
+    }
+
+    (hasher.finish(), corner)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Life, SparseLife};
+
+    fn run(engine: &mut dyn Engine, generations: u64) -> Option<Cycle> {
+        let mut detector = CycleDetector::new();
+        detector.observe(engine);
+
+        for _ in 0..generations {
+            engine.step();
+            detector.observe(engine);
+        }
+
+        detector.cycle()
+    }
+
+    #[test]
+    fn block_is_still() {
+        let mut sparse = SparseLife::from_cells([[0, 0], [1, 0], [0, 1], [1, 1]], 0);
+
+        let cycle = run(&mut sparse, 5).unwrap();
+        assert!(cycle.is_still());
+        assert_eq!(cycle.start, 0);
+    }
+
+    #[test]
+    fn blinker_after_a_while() {
+        // The lone cell dies right away, leaving just the blinker
+        let mut sparse = SparseLife::from_cells([[0, 0], [1, 0], [2, 0], [10, 10]], 0);
+
+        let cycle = run(&mut sparse, 10).unwrap();
+        assert_eq!(cycle.period, 2);
+        assert_eq!(cycle.start, 1);
+        assert!(!cycle.is_moving());
+    }
+
+    #[test]
+    fn glider_moves() {
+        let glider = [[1, 0], [2, 1], [0, 2], [1, 2], [2, 2]];
+        let mut sparse = SparseLife::from_cells(glider, 0);
+
+        let cycle = run(&mut sparse, 10).unwrap();
+        assert_eq!(cycle.period, 4);
+        assert_eq!(cycle.start, 0);
+        assert_eq!(cycle.displacement, [1, 1]);
+    }
+
+    #[test]
+    fn going_back_starts_over() {
+        let mut life = Life::new(5, 5);
+        for column in 1..4 {
+            life.set(column, 2, true);
+        }
+
+        let mut detector = CycleDetector::new();
+        for _ in 0..5 {
+            detector.observe(&life);
+            life.step();
+        }
+        assert_eq!(detector.cycle().map(|cycle| cycle.period), Some(2));
+
+        life.set_generation(0);
+        assert_eq!(detector.observe(&life), None);
+    }
+
+    #[test]
+    fn believed_once_it_repeats_again() {
+        let a = [([0, 0], 1), ([1, 0], 1)];
+        let b = [([5, 5], 1)];
+        let c = [([0, 0], 1), ([0, 1], 3)];
+
+        // Coming back once is not enough, and not coming back again is forgotten
+        let mut detector = CycleDetector::new();
+        for (generation, cells) in [&a[..], &b, &a, &c, &b].into_iter().enumerate() {
+            assert_eq!(detector.observe_cells(generation as u64, cells), None);
+        }
+
+        let mut detector = CycleDetector::new();
+        for (generation, cells) in [&a[..], &b, &a, &b].into_iter().enumerate() {
+            assert_eq!(detector.observe_cells(generation as u64, cells), None);
+        }
+        let cycle = detector.observe_cells(4, &a).unwrap();
+        assert_eq!((cycle.start, cycle.period), (0, 2));
+    }
+}
@@ -0,0 +1,174 @@
+use super::*;
+
+/// Anything that can run a Game of Life
+///
+/// The coordinates are signed so that engines without borders can use
+/// the whole plane, x grows to the right and y grows downwards.
+/// Engines with borders treat everything outside of them as dead cells
+/// that can never be set
+pub trait Engine: std::fmt::Debug {
+    /// Computes the next generation
+    fn step(&mut self);
+
+    /// Computes `n` generations
+    fn step_n(&mut self, n: u64) {
+        for _ in 0..n {
+            self.step();
+        }
+    }
+
+    /// How many generations have been computed since the engine was created
+    fn generation(&self) -> u64;
+
+    /// For when the cells are put back to how they were in another generation
+    fn set_generation(&mut self, generation: u64);
+
+    fn rule(&self) -> Rule;
+
+    /// Engines without borders refuse rules with B0
+    fn set_rule(&mut self, rule: Rule) -> Result<(), RuleError>;
+
+    fn cell(&self, x: i64, y: i64) -> bool;
+
+    fn set_cell(&mut self, x: i64, y: i64, alive: bool);
+
+    /// 0 for dead, 1 for alive and from 2 up for the dying states of Generations rules
+    fn cell_state(&self, x: i64, y: i64) -> u8 {
+        self.cell(x, y) as u8
+    }
+
+    /// Engines that only run rules with two states take anything but 1 as dead
+    fn set_cell_state(&mut self, x: i64, y: i64, state: u8) {
+        self.set_cell(x, y, state == 1);
+    }
+
+    /// The coordinates and states of every dying cell, in no particular order
+    fn dying_cells(&self) -> Vec<([i64; 2], u8)> {
+        Vec::new()
+    }
+
+    fn population(&self) -> u64;
+
+    /// How many cells were born and how many died going into the current generation,
+    /// if the engine keeps track of it
+    fn births_and_deaths(&self) -> Option<[u64; 2]> {
+        None
+    }
+
+    /// Kills every cell and resets the generation count
+    fn clear(&mut self);
+
+    /// The coordinates of every live cell, in no particular order
+    fn live_cells(&self) -> Vec<[i64; 2]>;
+
+    /// For whoever can do better with the bits of a grid than with its cells
+    fn as_life(&self) -> Option<&Life> {
+        None
+    }
+
+    /// The smallest rectangle containing every live cell
+    /// as `[min_x, min_y, max_x, max_y]`, all inclusive,
+    /// or `None` if there are no live cells
+    fn bounding_box(&self) -> Option<[i64; 4]> {
+        self.live_cells().into_iter().fold(None, |bounds, [x, y]| {
+            Some(match bounds {
+                None => [x, y, x, y],
+                Some([min_x, min_y, max_x, max_y]) => {
+                    [min_x.min(x), min_y.min(y), max_x.max(x), max_y.max(y)]
+                }
+            })
+        })
+    }
+}
+
+impl Life {
+    /// The column and row of the cell if it is inside of the grid
+    fn square(&self, x: i64, y: i64) -> Option<(usize, usize)> {
+        if (0..self.columns() as i64).contains(&x) && (0..self.rows() as i64).contains(&y) {
+            Some((x as usize, y as usize))
+        } else {
+            None
+        }
+    }
+}
+
+impl Engine for Life {
+    fn step(&mut self) {
+        Life::step(self);
+    }
+
+    fn step_n(&mut self, n: u64) {
+        Life::step_n(self, n);
+    }
+
+    fn generation(&self) -> u64 {
+        Life::generation(self)
+    }
+
+    fn set_generation(&mut self, generation: u64) {
+        Life::set_generation(self, generation);
+    }
+
+    fn rule(&self) -> Rule {
+        Life::rule(self)
+    }
+
+    fn set_rule(&mut self, rule: Rule) -> Result<(), RuleError> {
+        Life::set_rule(self, rule)
+    }
+
+    fn cell(&self, x: i64, y: i64) -> bool {
+        self.square(x, y)
+            .is_some_and(|(column, row)| self.get(column, row))
+    }
+
+    fn set_cell(&mut self, x: i64, y: i64, alive: bool) {
+        if let Some((column, row)) = self.square(x, y) {
+            self.set(column, row, alive);
+        }
+    }
+
+    fn cell_state(&self, x: i64, y: i64) -> u8 {
+        self.square(x, y)
+            .map_or(0, |(column, row)| self.state(column, row))
+    }
+
+    fn set_cell_state(&mut self, x: i64, y: i64, state: u8) {
+        if let Some((column, row)) = self.square(x, y) {
+            self.set_state(column, row, state);
+        }
+    }
+
+    fn dying_cells(&self) -> Vec<([i64; 2], u8)> {
+        self.dying().iter().collect()
+    }
+
+    fn population(&self) -> u64 {
+        Life::population(self) as u64
+    }
+
+    fn births_and_deaths(&self) -> Option<[u64; 2]> {
+        Life::births_and_deaths(self)
+    }
+
+    fn clear(&mut self) {
+        Life::clear(self);
+    }
+
+    fn live_cells(&self) -> Vec<[i64; 2]> {
+        self.cells()
+            .iter_alive()
+            .map(|(column, row)| [column as i64, row as i64])
+            .collect()
+    }
+
+    fn bounding_box(&self) -> Option<[i64; 4]> {
+        self.cells()
+            .bounding_box()
+            .map(|bounds| bounds.map(|bound| bound as i64))
+    }
+
+    fn as_life(&self) -> Option<&Life> {
+        Some(self)
+    }
+}
@@ -0,0 +1,298 @@
+/*!
+The dying cells of rules from the Generations family
+
+The live cells are stepped just like with any other rule,
+these are only the ones in between being alive and being dead.
+An infinite plane keeps them in a map, a grid has a state for every square
+*/
+
+use std::collections::HashMap;
+
+use super::{BitGrid, Rule, Topology, WORD_BITS};
+
+/// Every dying cell along with its state, from 2 up to the rule's states minus one
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DyingCells {
+    states: HashMap<[i64; 2], u8>,
+}
+
+impl DyingCells {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// `None` if the cell is either alive or dead
+    pub fn get(&self, x: i64, y: i64) -> Option<u8> {
+        self.states.get(&[x, y]).copied()
+    }
+
+    /// Anything below 2 takes the cell out
+    pub fn set(&mut self, x: i64, y: i64, state: u8) {
+        if state >= 2 {
+            self.states.insert([x, y], state);
+        } else if !self.states.is_empty() {
+            self.states.remove(&[x, y]);
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.states.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.states.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.states.clear();
+    }
+
+    /// In no particular order
+    pub fn iter(&self) -> impl Iterator<Item = ([i64; 2], u8)> + '_ {
+        self.states.iter().map(|(cell, state)| (*cell, *state))
+    }
+
+    /// Only keeps the cells that `keep` says yes to
+    pub fn retain(&mut self, mut keep: impl FnMut([i64; 2], u8) -> bool) {
+        self.states.retain(|cell, state| keep(*cell, *state));
+    }
+
+    /// For when the rule changes, states the new one does not have are dropped
+    pub fn fit_rule(&mut self, rule: &Rule) {
+        self.retain(|_, state| state < rule.states());
+    }
+
+    /// Every dying cell goes one state further, and the ones in `died`
+    /// (alive before stepping and no longer alive after) start dying
+    pub fn step(&mut self, rule: &Rule, died: impl IntoIterator<Item = [i64; 2]>) {
+        self.states.retain(|_, state| {
+            *state = rule.decay(*state);
+            *state != 0
+        });
+
+        let dying = rule.decay(1);
+        if dying != 0 {
+            self.states
+                .extend(died.into_iter().map(|cell| (cell, dying)));
+        }
+    }
+}
+
+/// [`DyingCells`] for a grid, where most squares can be dying at once
+#[derive(Debug, Clone)]
+pub struct DyingSquares {
+    columns: usize,
+    rows: usize,
+    /// Row by row, 0 for the squares that are not dying
+    states: Vec<u8>,
+    /// Which squares are dying, so only those are gone through
+    mask: BitGrid,
+    len: usize,
+    /// The squares that died in the last step, kept to not allocate it every time
+    died: BitGrid,
+}
+
+impl PartialEq for DyingSquares {
+    fn eq(&self, other: &Self) -> bool {
+        (self.columns, self.rows) == (other.columns, other.rows) && self.states == other.states
+    }
+}
+
+impl Eq for DyingSquares {}
+
+impl DyingSquares {
+    pub fn new(columns: usize, rows: usize) -> Self {
+        Self {
+            columns,
+            rows,
+            states: vec![0; columns * rows],
+            mask: BitGrid::new(columns, rows),
+            len: 0,
+            died: BitGrid::new(0, 0),
+        }
+    }
+
+    fn index(&self, x: i64, y: i64) -> Option<usize> {
+        let inside = (0..self.columns as i64).contains(&x) && (0..self.rows as i64).contains(&y);
+        inside.then(|| y as usize * self.columns + x as usize)
+    }
+
+    /// `None` if the square is either alive or dead, or outside of the grid
+    pub fn get(&self, x: i64, y: i64) -> Option<u8> {
+        self.index(x, y)
+            .map(|index| self.states[index])
+            .filter(|state| *state != 0)
+    }
+
+    /// Anything below 2 takes the square out, squares outside of the grid are left alone
+    pub fn set(&mut self, x: i64, y: i64, state: u8) {
+        let Some(index) = self.index(x, y) else {
+            return;
+        };
+        let state = if state >= 2 { state } else { 0 };
+
+        match (self.states[index] != 0, state != 0) {
+            (false, true) => self.len += 1,
+            (true, false) => self.len -= 1,
+            _ => {}
+        }
+        self.states[index] = state;
+        self.mask.set(x as usize, y as usize, state != 0);
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn clear(&mut self) {
+        if !self.is_empty() {
+            self.states.fill(0);
+            self.mask.clear();
+            self.len = 0;
+        }
+    }
+
+    /// The squares that still fit keep their states
+    pub fn resize(&mut self, columns: usize, rows: usize) {
+        let old = std::mem::replace(self, Self::new(columns, rows));
+        for ([x, y], state) in old.iter() {
+            self.set(x, y, state);
+        }
+    }
+
+    /// Row by row
+    pub fn iter(&self) -> impl Iterator<Item = ([i64; 2], u8)> + '_ {
+        self.mask.iter_alive().map(|(column, row)| {
+            (
+                [column as i64, row as i64],
+                self.states[row * self.columns + column],
+            )
+        })
+    }
+
+    /// For when the rule changes, states the new one does not have are dropped
+    pub fn fit_rule(&mut self, rule: &Rule) {
+        let dropped: Vec<_> = self
+            .iter()
+            .filter(|(_, state)| *state >= rule.states())
+            .collect();
+        for ([x, y], _) in dropped {
+            self.set(x, y, 0);
+        }
+    }
+
+    /// Every dying square goes one state further, and the ones in `died`
+    /// (alive before stepping and no longer alive after) start dying
+    pub fn step(&mut self, rule: &Rule, died: impl IntoIterator<Item = [i64; 2]>) {
+        self.decay(rule);
+
+        let dying = rule.decay(1);
+        if dying != 0 {
+            for [x, y] in died {
+                self.set(x, y, dying);
+            }
+        }
+    }
+
+    /// Steps the live squares in `cells` and then the dying ones,
+    /// the dying ones can't be born. Returns how many were born and how many died
+    pub(super) fn step_grid(
+        &mut self,
+        cells: &mut BitGrid,
+        threads: usize,
+        rule: &Rule,
+        topology: Topology,
+    ) -> [u64; 2] {
+        let mut died = std::mem::replace(&mut self.died, BitGrid::new(0, 0));
+        let [mut births, deaths] = cells.step_parallel_deaths(threads, rule, topology, &mut died);
+
+        for row in 0..self.rows {
+            for (alive, dying) in cells.row_mut(row).iter_mut().zip(self.mask.row(row)) {
+                births -= (*alive & dying).count_ones() as u64;
+                *alive &= !dying;
+            }
+        }
+
+        self.step(
+            rule,
+            died.iter_alive()
+                .map(|(column, row)| [column as i64, row as i64]),
+        );
+        self.died = died;
+        [births, deaths]
+    }
+
+    fn decay(&mut self, rule: &Rule) {
+        for row in 0..self.rows {
+            for word in 0..self.mask.words_per_row() {
+                let mut bits = self.mask.row(row)[word];
+                while bits != 0 {
+                    let column = word * WORD_BITS + bits.trailing_zeros() as usize;
+                    bits &= bits - 1;
+
+                    let index = row * self.columns + column;
+                    self.states[index] = rule.decay(self.states[index]);
+                    if self.states[index] == 0 {
+                        self.mask.set(column, row, false);
+                        self.len -= 1;
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_up_until_dead() {
+        let star_wars: Rule = "B2/S345/C4".parse().unwrap();
+        let mut dying = DyingCells::new();
+
+        dying.step(&star_wars, [[0, 0]]);
+        assert_eq!(dying.get(0, 0), Some(2));
+
+        dying.step(&star_wars, [[1, 0]]);
+        assert_eq!(dying.get(0, 0), Some(3));
+        assert_eq!(dying.get(1, 0), Some(2));
+
+        dying.step(&star_wars, []);
+        assert_eq!(dying.get(0, 0), None);
+        assert_eq!(dying.len(), 1);
+
+        // Life-like rules have no dying cells at all
+        dying.step(&Rule::CONWAY, [[5, 5]]);
+        assert!(dying.is_empty());
+    }
+
+    #[test]
+    fn squares_count_up_like_cells() {
+        let star_wars: Rule = "B2/S345/C4".parse().unwrap();
+        let mut dying = DyingSquares::new(70, 3);
+
+        dying.step(&star_wars, [[0, 0], [69, 2], [70, 2]]);
+        assert_eq!(dying.get(69, 2), Some(2));
+        assert_eq!(dying.get(70, 2), None);
+        assert_eq!(dying.len(), 2);
+
+        dying.step(&star_wars, [[1, 0]]);
+        assert_eq!(
+            dying.iter().collect::<Vec<_>>(),
+            [([0, 0], 3), ([1, 0], 2), ([69, 2], 3)]
+        );
+
+        dying.resize(50, 3);
+        dying.step(&star_wars, []);
+        assert_eq!(dying.iter().collect::<Vec<_>>(), [([1, 0], 3)]);
+
+        dying.fit_rule(&"B2/S345/C3".parse().unwrap());
+        assert!(dying.is_empty());
+        assert_eq!(dying, DyingSquares::new(50, 3));
+    }
+}
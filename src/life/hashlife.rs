@@ -0,0 +1,600 @@
+/*!
+Gosper's HashLife
+
+The universe is a quadtree where every node is interned, so any two
+identical regions of the plane are the same node no matter where or when
+they show up. On top of that the result of advancing the center of a node
+is memoized, which lets huge and/or repetitive patterns jump ahead
+2^k generations at a time
+
+A node of level `k` is a square of 2^k by 2^k cells,
+level 0 nodes being single cells
+*/
+
+use std::collections::HashMap;
+
+use super::{Engine, Rule, RuleError, RuleKind};
+
+type NodeId = u32;
+
+const DEAD: NodeId = 0;
+const ALIVE: NodeId = 1;
+
+/// The root never gets smaller than this
+const MIN_ROOT_LEVEL: u8 = 3;
+
+/// The biggest jump that can be done at once, any bigger and the
+/// coordinates of the root would not fit in an i64
+pub const MAX_JUMP_LOG2: u8 = 56;
+
+/// Once there are more nodes than this, the ones that are not part of
+/// the current universe get thrown away along with every memoized result
+const GARBAGE_COLLECTION_NODES: usize = 1 << 22;
+
+#[derive(Debug, Clone, Copy)]
+struct Node {
+    level: u8,
+    /// North west, north east, south west and south east, in that order
+    children: [NodeId; 4],
+    population: u64,
+}
+
+#[derive(Debug, Clone)]
+pub struct HashLife {
+    nodes: Vec<Node>,
+    interned: HashMap<[NodeId; 4], NodeId>,
+    /// The center of a node after 2^j generations, keyed by the node and j
+    results: HashMap<(NodeId, u8), NodeId>,
+    /// The empty node of every level that has been needed so far
+    empty: Vec<NodeId>,
+    /// Centered on the origin, so a root of level `k`
+    /// covers from -2^(k-1) up to 2^(k-1) - 1 in both axes
+    root: NodeId,
+    generation: u64,
+    rule: Rule,
+}
+
+impl Default for HashLife {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl HashLife {
+    pub fn new() -> Self {
+        let mut hashlife = Self {
+            nodes: Vec::new(),
+            interned: HashMap::new(),
+            results: HashMap::new(),
+            empty: Vec::new(),
+            root: DEAD,
+            generation: 0,
+            rule: Rule::default(),
+        };
+
+        hashlife.reset_nodes();
+        hashlife.root = hashlife.empty(MIN_ROOT_LEVEL);
+
+        hashlife
+    }
+
+    /// Leaves only the two level 0 nodes
+    fn reset_nodes(&mut self) {
+        self.nodes.clear();
+        self.interned.clear();
+        self.results.clear();
+        self.empty.clear();
+
+        for (id, population) in [(DEAD, 0), (ALIVE, 1)] {
+            debug_assert_eq!(self.nodes.len(), id as usize);
+            self.nodes.push(Node {
+                level: 0,
+                children: [DEAD; 4],
+                population,
+            });
+        }
+        self.empty.push(DEAD);
+    }
+
+    fn level(&self, id: NodeId) -> u8 {
+        self.nodes[id as usize].level
+    }
+
+    fn children(&self, id: NodeId) -> [NodeId; 4] {
+        self.nodes[id as usize].children
+    }
+
+    fn node_population(&self, id: NodeId) -> u64 {
+        self.nodes[id as usize].population
+    }
+
+    /// The one and only node with these children
+    fn node(&mut self, children: [NodeId; 4]) -> NodeId {
+        if let Some(id) = self.interned.get(&children) {
+            return *id;
+        }
+
+        let id = self.nodes.len() as NodeId;
+        self.nodes.push(Node {
+            level: self.level(children[0]) + 1,
+            children,
+            population: children
+                .iter()
+                .map(|child| self.node_population(*child))
+                .sum(),
+        });
+        self.interned.insert(children, id);
+
+        id
+    }
+
+    fn empty(&mut self, level: u8) -> NodeId {
+        while self.empty.len() <= level as usize {
+            let below = *self.empty.last().unwrap();
+            let empty = self.node([below; 4]);
+            self.empty.push(empty);
+        }
+
+        self.empty[level as usize]
+    }
+
+    /// Half the side of the root, a.k.a. how far it reaches from the origin
+    fn root_half(&self) -> i64 {
+        1 << (self.level(self.root) - 1)
+    }
+
+    /// Doubles the side of the root keeping the current one at its center
+    fn expand(&mut self) {
+        let [nw, ne, sw, se] = self.children(self.root);
+        let empty = self.empty(self.level(self.root) - 1);
+
+        let nw = self.node([empty, empty, empty, nw]);
+        let ne = self.node([empty, empty, ne, empty]);
+        let sw = self.node([empty, sw, empty, empty]);
+        let se = self.node([se, empty, empty, empty]);
+
+        self.root = self.node([nw, ne, sw, se]);
+    }
+
+    fn contains(&self, x: i64, y: i64) -> bool {
+        let half = self.root_half();
+        (-half..half).contains(&x) && (-half..half).contains(&y)
+    }
+
+    /// The node of one level less made out of the four grandchildren in the middle
+    fn center(&mut self, id: NodeId) -> NodeId {
+        let [nw, ne, sw, se] = self.children(id);
+        self.node([
+            self.children(nw)[3],
+            self.children(ne)[2],
+            self.children(sw)[1],
+            self.children(se)[0],
+        ])
+    }
+
+    /// The node of the same level as `west` and `east` that sits right between them
+    fn horizontal_center(&mut self, west: NodeId, east: NodeId) -> NodeId {
+        let [_, west_ne, _, west_se] = self.children(west);
+        let [east_nw, _, east_sw, _] = self.children(east);
+        self.node([west_ne, east_nw, west_se, east_sw])
+    }
+
+    /// The node of the same level as `north` and `south` that sits right between them
+    fn vertical_center(&mut self, north: NodeId, south: NodeId) -> NodeId {
+        let [_, _, north_sw, north_se] = self.children(north);
+        let [south_nw, south_ne, _, _] = self.children(south);
+        self.node([north_sw, north_se, south_nw, south_ne])
+    }
+
+    /// Advances a 4x4 node a single generation, leaving its 2x2 center
+    fn base_successor(&mut self, id: NodeId) -> NodeId {
+        let mut cells = [[false; 4]; 4];
+
+        for (quadrant, child) in self.children(id).into_iter().enumerate() {
+            for (index, leaf) in self.children(child).into_iter().enumerate() {
+                let x = (quadrant % 2) * 2 + index % 2;
+                let y = (quadrant / 2) * 2 + index / 2;
+                cells[y][x] = leaf == ALIVE;
+            }
+        }
+
+        let mut center = [DEAD; 4];
+
+        for (index, cell) in center.iter_mut().enumerate() {
+            let (x, y) = (1 + index % 2, 1 + index / 2);
+
+            // the whole 3x3 square around the cell, itself included
+            let neighborhood = cells[y - 1..=y + 1]
+                .iter()
+                .flat_map(|row| &row[x - 1..=x + 1])
+                .enumerate()
+                .filter(|(_, alive)| **alive)
+                .fold(0, |neighborhood, (bit, _)| neighborhood | 1 << bit);
+
+            if self.rule.next_neighborhood(neighborhood) {
+                *cell = ALIVE;
+            }
+        }
+
+        self.node(center)
+    }
+
+    /// The center of a node of level `k` after 2^j generations, j being at most k - 2
+    fn successor(&mut self, id: NodeId, j: u8) -> NodeId {
+        let level = self.level(id);
+        debug_assert!(level >= 2 && j <= level - 2);
+
+        if self.node_population(id) == 0 {
+            return self.empty(level - 1);
+        }
+        if let Some(result) = self.results.get(&(id, j)) {
+            return *result;
+        }
+
+        let result = if level == 2 {
+            self.base_successor(id)
+        } else {
+            let [nw, ne, sw, se] = self.children(id);
+
+            // Nine overlapping nodes of one level less covering the whole node
+            let n = self.horizontal_center(nw, ne);
+            let w = self.vertical_center(nw, sw);
+            let c = self.center(id);
+            let e = self.vertical_center(ne, se);
+            let s = self.horizontal_center(sw, se);
+            let nine = [nw, n, ne, w, c, e, sw, s, se];
+
+            // At full speed both halves of the jump are done by recursion,
+            // otherwise the first half takes no time at all
+            let full_speed = j == level - 2;
+            let mut advanced = [DEAD; 9];
+            for (advanced, node) in advanced.iter_mut().zip(nine) {
+                *advanced = if full_speed {
+                    self.successor(node, level - 3)
+                } else {
+                    self.center(node)
+                };
+            }
+
+            let [r00, r01, r02, r10, r11, r12, r20, r21, r22] = advanced;
+            let quarters = [
+                self.node([r00, r01, r10, r11]),
+                self.node([r01, r02, r11, r12]),
+                self.node([r10, r11, r20, r21]),
+                self.node([r11, r12, r21, r22]),
+            ];
+
+            let second_half = if full_speed { level - 3 } else { j };
+            let mut result = [DEAD; 4];
+            for (result, quarter) in result.iter_mut().zip(quarters) {
+                *result = self.successor(quarter, second_half);
+            }
+
+            self.node(result)
+        };
+
+        self.results.insert((id, j), result);
+        result
+    }
+
+    /// Jumps 2^j generations ahead at once
+    ///
+    /// # Panics
+    /// If `j` is bigger than [`MAX_JUMP_LOG2`]
+    pub fn step_pow2(&mut self, j: u8) {
+        assert!(j <= MAX_JUMP_LOG2, "can't jump 2^{} generations at once", j);
+
+        // Cells travel one cell per generation at most, so as long as the pattern
+        // sits in the middle of the middle of the root, 2^j generations
+        // later it will still fit in the center that the root gets replaced with
+        loop {
+            if self.level(self.root) >= j + 3 {
+                let center = self.center(self.root);
+                let middle = self.center(center);
+
+                if self.node_population(middle) == self.node_population(self.root) {
+                    break;
+                }
+            }
+            self.expand();
+        }
+
+        self.root = self.successor(self.root, j);
+        while self.level(self.root) < MIN_ROOT_LEVEL {
+            self.expand();
+        }
+
+        self.generation += 1 << j;
+
+        if self.nodes.len() > GARBAGE_COLLECTION_NODES {
+            self.collect_garbage();
+        }
+    }
+
+    /// Rebuilds the nodes from scratch keeping only the current universe
+    fn collect_garbage(&mut self) {
+        let old_nodes = std::mem::take(&mut self.nodes);
+        self.reset_nodes();
+
+        let mut copied = HashMap::new();
+        self.root = self.copy_node(&old_nodes, self.root, &mut copied);
+    }
+
+    fn copy_node(
+        &mut self,
+        old_nodes: &[Node],
+        id: NodeId,
+        copied: &mut HashMap<NodeId, NodeId>,
+    ) -> NodeId {
+        if id == DEAD || id == ALIVE {
+            return id;
+        }
+        if let Some(copy) = copied.get(&id) {
+            return *copy;
+        }
+
+        let mut children = old_nodes[id as usize].children;
+        for child in children.iter_mut() {
+            *child = self.copy_node(old_nodes, *child, copied);
+        }
+
+        let copy = self.node(children);
+        copied.insert(id, copy);
+        copy
+    }
+
+    /// `x` and `y` are relative to the top left corner of the node
+    fn set_in(&mut self, id: NodeId, x: i64, y: i64, alive: bool) -> NodeId {
+        let level = self.level(id);
+        if level == 0 {
+            return if alive { ALIVE } else { DEAD };
+        }
+
+        let half = 1 << (level - 1);
+        let quadrant = (y >= half) as usize * 2 + (x >= half) as usize;
+
+        let mut children = self.children(id);
+        children[quadrant] = self.set_in(children[quadrant], x % half, y % half, alive);
+
+        self.node(children)
+    }
+
+    /// Appends the live cells of the node whose top left corner is at `x`, `y`
+    fn collect_cells(&self, id: NodeId, x: i64, y: i64, cells: &mut Vec<[i64; 2]>) {
+        if self.node_population(id) == 0 {
+            return;
+        }
+
+        let level = self.level(id);
+        if level == 0 {
+            cells.push([x, y]);
+            return;
+        }
+
+        let half = 1 << (level - 1);
+        for (quadrant, child) in self.children(id).into_iter().enumerate() {
+            let child_x = x + (quadrant % 2) as i64 * half;
+            let child_y = y + (quadrant / 2) as i64 * half;
+            self.collect_cells(child, child_x, child_y, cells);
+        }
+    }
+
+    /// How many nodes are around right now, mostly to see how much memory it is using
+    pub fn node_count(&self) -> usize {
+        self.nodes.len()
+    }
+}
+
+impl Engine for HashLife {
+    fn step(&mut self) {
+        self.step_pow2(0);
+    }
+
+    /// Uses the binary representation of `n` to jump
+    /// as many generations as possible at a time
+    fn step_n(&mut self, n: u64) {
+        for j in 0..u64::BITS as u8 {
+            if n >> j & 1 == 0 {
+                continue;
+            }
+
+            if j <= MAX_JUMP_LOG2 {
+                self.step_pow2(j);
+            } else {
+                for _ in 0..1u64 << (j - MAX_JUMP_LOG2) {
+                    self.step_pow2(MAX_JUMP_LOG2);
+                }
+            }
+        }
+    }
+
+    fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    fn set_generation(&mut self, generation: u64) {
+        self.generation = generation;
+    }
+
+    fn rule(&self) -> Rule {
+        self.rule.clone()
+    }
+
+    /// Every memoized result goes away since they were computed with the old rule
+    fn set_rule(&mut self, rule: Rule) -> Result<(), RuleError> {
+        if rule.has_b0() {
+            return Err(RuleError::B0OnInfinitePlane);
+        }
+        let unsupported = match rule.kind() {
+            RuleKind::Table(_) => Some(RuleError::TableUnsupported),
+            // Nodes are only worth remembering if they always turn out the same
+            RuleKind::Stochastic(_) => Some(RuleError::StochasticUnsupported),
+            // Every node is made of live and dead cells only
+            _ if rule.is_generations() => Some(RuleError::MultiStateUnsupported),
+            RuleKind::Larger(_) | RuleKind::Block(_) => Some(RuleError::NeighborhoodUnsupported),
+            RuleKind::Totalistic | RuleKind::Isotropic(_) => None,
+        };
+        if let Some(error) = unsupported {
+            return Err(error);
+        }
+
+        if rule != self.rule {
+            self.rule = rule;
+            self.results.clear();
+        }
+
+        Ok(())
+    }
+
+    fn cell(&self, x: i64, y: i64) -> bool {
+        if !self.contains(x, y) {
+            return false;
+        }
+
+        let half = self.root_half();
+        let (mut x, mut y) = (x + half, y + half);
+        let mut id = self.root;
+
+        while self.level(id) > 0 {
+            let half = 1 << (self.level(id) - 1);
+            let quadrant = (y >= half) as usize * 2 + (x >= half) as usize;
+
+            id = self.children(id)[quadrant];
+            x %= half;
+            y %= half;
+        }
+
+        id == ALIVE
+    }
+
+    fn set_cell(&mut self, x: i64, y: i64, alive: bool) {
+        while !self.contains(x, y) {
+            self.expand();
+        }
+
+        let half = self.root_half();
+        self.root = self.set_in(self.root, x + half, y + half, alive);
+    }
+
+    fn population(&self) -> u64 {
+        self.node_population(self.root)
+    }
+
+    fn clear(&mut self) {
+        *self = Self::new();
+    }
+
+    fn live_cells(&self) -> Vec<[i64; 2]> {
+        let mut cells = Vec::new();
+        let half = self.root_half();
+        self.collect_cells(self.root, -half, -half, &mut cells);
+        cells
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::life::tests::soup;
+    use crate::Life;
+
+    const GLIDER: [[i64; 2]; 5] = [[1, 0], [2, 1], [0, 2], [1, 2], [2, 2]];
+
+    fn sorted(mut cells: Vec<[i64; 2]>) -> Vec<[i64; 2]> {
+        cells.sort_by_key(|[x, y]| (*y, *x));
+        cells
+    }
+
+    #[test]
+    fn set_and_get_far_away() {
+        let mut hashlife = HashLife::new();
+
+        hashlife.set_cell(-3_000_000_000, 17, true);
+        hashlife.set_cell(5, 9_000_000_000, true);
+
+        assert!(hashlife.cell(-3_000_000_000, 17));
+        assert!(hashlife.cell(5, 9_000_000_000));
+        assert!(!hashlife.cell(5, 17));
+        assert_eq!(hashlife.population(), 2);
+        assert_eq!(
+            hashlife.bounding_box(),
+            Some([-3_000_000_000, 17, 5, 9_000_000_000])
+        );
+    }
+
+    #[test]
+    fn matches_plain_stepper() {
+        let mut life = Life::new(200, 200);
+        let mut hashlife = HashLife::new();
+
+        // A random soup in the middle, far enough from the borders
+        for [x, y] in soup([84, 84, 115, 115], 0.5, 0xC0FFEE) {
+            Engine::set_cell(&mut life, x, y, true);
+            hashlife.set_cell(x, y, true);
+        }
+
+        for n in [1, 2, 5, 16, 40] {
+            Engine::step_n(&mut life, n);
+            hashlife.step_n(n);
+
+            assert_eq!(hashlife.generation(), Engine::generation(&life));
+            assert_eq!(
+                sorted(hashlife.live_cells()),
+                sorted(Engine::live_cells(&life))
+            );
+        }
+    }
+
+    #[test]
+    fn isotropic_rules() {
+        let rule: Rule = "B2-a/S12".parse().unwrap();
+        let mut sparse = crate::SparseLife::new();
+        sparse.set_rule(rule.clone()).unwrap();
+        let mut hashlife = HashLife::new();
+        hashlife.set_rule(rule).unwrap();
+
+        for [x, y] in [[0, 0], [1, 0], [3, 1], [0, 2], [2, 3], [4, 4]] {
+            sparse.set_cell(x, y, true);
+            hashlife.set_cell(x, y, true);
+        }
+
+        for n in [1, 3, 8] {
+            sparse.step_n(n);
+            hashlife.step_n(n);
+            assert_eq!(sorted(hashlife.live_cells()), sorted(sparse.live_cells()));
+        }
+    }
+
+    #[test]
+    fn glider_jumps_far_ahead() {
+        let mut hashlife = HashLife::new();
+        for [x, y] in GLIDER {
+            hashlife.set_cell(x, y, true);
+        }
+
+        // A glider moves one cell diagonally every four generations
+        hashlife.step_pow2(40);
+
+        let moved = (1 << 40) / 4;
+        let expected: Vec<_> = GLIDER.iter().map(|[x, y]| [x + moved, y + moved]).collect();
+
+        assert_eq!(hashlife.generation(), 1 << 40);
+        assert_eq!(sorted(hashlife.live_cells()), sorted(expected));
+    }
+
+    #[test]
+    fn garbage_collection_keeps_the_universe() {
+        let mut hashlife = HashLife::new();
+        for [x, y] in GLIDER {
+            hashlife.set_cell(x, y, true);
+        }
+        hashlife.step_n(100);
+
+        let before = sorted(hashlife.live_cells());
+        hashlife.collect_garbage();
+
+        assert_eq!(sorted(hashlife.live_cells()), before);
+        hashlife.step_n(4);
+        assert_eq!(hashlife.population(), 5);
+    }
+}
@@ -0,0 +1,578 @@
+/*!
+Remembering past generations so they can be gone back to
+
+Only the cells that changed between one generation and the next are kept,
+sorted row by row and written as variable length differences from the
+previous changed cell, so a typical change takes a couple of bytes.
+Going back a generation is toggling those cells again
+
+On a grid with two states the cells that changed are found by XORing the words
+of its rows, anything else has its cells sorted and gone through
+
+With Generations rules a cell can change between more than two states,
+so the deltas that need it also have the old state XOR the new one for every cell
+*/
+
+use std::collections::VecDeque;
+
+use super::{BitGrid, Engine, Life};
+
+/// 64 MiB
+pub const DEFAULT_HISTORY_LIMIT: usize = 64 << 20;
+
+/// The cells that changed going from `generation` to the next one
+#[derive(Debug, Clone)]
+struct Delta {
+    generation: u64,
+    changes: Box<[u8]>,
+    /// Whether the changes have states, without them every cell is toggled
+    with_states: bool,
+}
+
+impl Delta {
+    /// Bytes taken, even a generation where nothing changed takes some
+    fn size(&self) -> usize {
+        std::mem::size_of::<Self>() + self.changes.len()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct History {
+    /// Oldest first, the last one leading up to the current generation
+    past: VecDeque<Delta>,
+    /// Generations that were gone back from, the next one last
+    future: Vec<Delta>,
+    /// The live and dying cells of the current generation sorted row by row,
+    /// if nothing has changed them since they were stepped
+    current: Option<Vec<([i64; 2], u8)>>,
+    /// The same for a grid with two states, as its bits
+    current_bits: Option<BitGrid>,
+    /// Bytes taken by all of the deltas
+    memory: usize,
+    /// Old generations are forgotten to stay under this many bytes
+    limit: usize,
+}
+
+impl Default for History {
+    fn default() -> Self {
+        Self::new(DEFAULT_HISTORY_LIMIT)
+    }
+}
+
+impl History {
+    pub fn new(limit: usize) -> Self {
+        Self {
+            past: VecDeque::new(),
+            future: Vec::new(),
+            current: None,
+            current_bits: None,
+            memory: 0,
+            limit,
+        }
+    }
+
+    pub fn limit(&self) -> usize {
+        self.limit
+    }
+
+    /// A limit of zero turns the history off
+    pub fn set_limit(&mut self, limit: usize) {
+        self.limit = limit;
+        self.enforce_limit();
+    }
+
+    /// Roughly how many bytes the history is taking
+    pub fn memory(&self) -> usize {
+        self.memory
+    }
+
+    /// How many generations can be gone back
+    pub fn len(&self) -> usize {
+        self.past.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.past.is_empty()
+    }
+
+    /// The generation furthest back that can be gone back to
+    pub fn oldest_generation(&self) -> Option<u64> {
+        self.past.front().map(|delta| delta.generation)
+    }
+
+    /// The generation furthest ahead that can be gone forward to without stepping
+    pub fn newest_generation(&self) -> Option<u64> {
+        self.future.first().map(|delta| delta.generation + 1)
+    }
+
+    pub fn can_step_back(&self) -> bool {
+        !self.past.is_empty()
+    }
+
+    pub fn can_step_forward(&self) -> bool {
+        !self.future.is_empty()
+    }
+
+    /// Forgets everything, for when the cells are replaced altogether
+    pub fn clear(&mut self) {
+        self.past.clear();
+        self.future.clear();
+        self.current = None;
+        self.current_bits = None;
+        self.memory = 0;
+    }
+
+    /// Has to be called whenever cells are changed by something else than the history,
+    /// the generations that were gone back from are forgotten since they no longer follow
+    pub fn edited(&mut self) {
+        self.current = None;
+        self.current_bits = None;
+        self.forget_future();
+    }
+
+    fn forget_future(&mut self) {
+        for delta in self.future.drain(..) {
+            self.memory -= delta.size();
+        }
+    }
+
+    /// The live and dying cells of the current generation sorted row by row,
+    /// only sorted again if they changed since the last time
+    pub fn current_cells(&mut self, engine: &dyn Engine) -> &[([i64; 2], u8)] {
+        self.current.get_or_insert_with(|| sorted_cells(engine))
+    }
+
+    /// Computes the next generation, remembering the current one
+    pub fn step(&mut self, engine: &mut dyn Engine) {
+        self.step_n(engine, 1);
+    }
+
+    pub fn step_n(&mut self, engine: &mut dyn Engine, n: u64) {
+        if n == 0 {
+            return;
+        }
+
+        self.forget_future();
+
+        if self.limit == 0 {
+            self.clear();
+            engine.step_n(n);
+            return;
+        }
+
+        for _ in 0..n {
+            let generation = engine.generation();
+
+            let difference = match grid_bits(engine) {
+                Some(bits) => {
+                    let before = match self.current_bits.take() {
+                        Some(before)
+                            if before.columns() == bits.columns()
+                                && before.rows() == bits.rows() =>
+                        {
+                            before
+                        }
+                        _ => bits.clone(),
+                    };
+
+                    engine.step();
+
+                    let after = grid_bits(engine).expect("still a grid").clone();
+                    let difference = before
+                        .iter_different(&after)
+                        .map(|(column, row)| ([column as i64, row as i64], 1))
+                        .collect();
+                    self.current = None;
+                    self.current_bits = Some(after);
+                    difference
+                }
+                None => {
+                    let before = self.current.take().unwrap_or_else(|| sorted_cells(engine));
+
+                    engine.step();
+
+                    let after = sorted_cells(engine);
+                    let difference = symmetric_difference(&before, &after);
+                    self.current = Some(after);
+                    self.current_bits = None;
+                    difference
+                }
+            };
+            let with_states = difference.iter().any(|(_, change)| *change != 1);
+            let delta = Delta {
+                generation,
+                changes: encode(&difference, with_states),
+                with_states,
+            };
+
+            self.memory += delta.size();
+            self.past.push_back(delta);
+
+            self.enforce_limit();
+        }
+    }
+
+    /// Puts the cells back to how they were a generation ago,
+    /// returns whether there was anything to go back to
+    pub fn step_back(&mut self, engine: &mut dyn Engine) -> bool {
+        let Some(delta) = self.past.pop_back() else {
+            return false;
+        };
+
+        toggle(engine, &delta);
+        engine.set_generation(delta.generation);
+
+        self.current = None;
+        self.current_bits = None;
+        self.future.push(delta);
+
+        true
+    }
+
+    /// Undoes [`History::step_back`],
+    /// returns whether there was anything to go forward to
+    pub fn step_forward(&mut self, engine: &mut dyn Engine) -> bool {
+        let Some(delta) = self.future.pop() else {
+            return false;
+        };
+
+        toggle(engine, &delta);
+        engine.set_generation(delta.generation + 1);
+
+        self.current = None;
+        self.current_bits = None;
+        self.past.push_back(delta);
+
+        true
+    }
+
+    /// Goes back or forward until reaching `generation`, but only through
+    /// generations that are remembered. Returns whether it got there
+    pub fn rewind_to(&mut self, engine: &mut dyn Engine, generation: u64) -> bool {
+        while engine.generation() > generation && self.step_back(engine) {}
+        while engine.generation() < generation && self.step_forward(engine) {}
+
+        engine.generation() == generation
+    }
+
+    /// Forgets the oldest generations until under the limit,
+    /// and then the ones that were gone back from if that isn't enough
+    fn enforce_limit(&mut self) {
+        while self.memory > self.limit {
+            let Some(delta) = self.past.pop_front() else {
+                self.forget_future();
+                break;
+            };
+            self.memory -= delta.size();
+        }
+    }
+}
+
+/// The live and dying cells along with their states, live cells being 1
+pub(super) fn sorted_cells(engine: &dyn Engine) -> Vec<([i64; 2], u8)> {
+    let mut cells: Vec<_> = engine
+        .live_cells()
+        .into_iter()
+        .map(|cell| (cell, 1))
+        .chain(engine.dying_cells())
+        .collect();
+    cells.sort_unstable_by_key(|([x, y], _)| (*y, *x));
+    cells
+}
+
+/// The live cells of a grid whose rule has no other states
+fn grid_bits(engine: &dyn Engine) -> Option<&BitGrid> {
+    engine
+        .as_life()
+        .filter(|life| life.rule().states() == 2)
+        .map(Life::cells)
+}
+
+/// The cells that are in one of them but not the other or in both with
+/// different states, along with both states XORed. Both sorted row by row
+fn symmetric_difference(a: &[([i64; 2], u8)], b: &[([i64; 2], u8)]) -> Vec<([i64; 2], u8)> {
+    let key = |([x, y], _): ([i64; 2], u8)| (y, x);
+
+    let mut difference = Vec::new();
+    let (mut a, mut b) = (a.iter().peekable(), b.iter().peekable());
+
+    loop {
+        match (a.peek(), b.peek()) {
+            (Some(&&from_a), Some(&&from_b)) => match key(from_a).cmp(&key(from_b)) {
+                std::cmp::Ordering::Less => {
+                    difference.push(from_a);
+                    a.next();
+                }
+                std::cmp::Ordering::Greater => {
+                    difference.push(from_b);
+                    b.next();
+                }
+                std::cmp::Ordering::Equal => {
+                    if from_a.1 != from_b.1 {
+                        difference.push((from_a.0, from_a.1 ^ from_b.1));
+                    }
+                    a.next();
+                    b.next();
+                }
+            },
+            (Some(_), None) => difference.extend(a.by_ref()),
+            (None, Some(_)) => difference.extend(b.by_ref()),
+            (None, None) => return difference,
+        }
+    }
+}
+
+fn toggle(engine: &mut dyn Engine, delta: &Delta) {
+    for ([x, y], change) in decode(&delta.changes, delta.with_states) {
+        let state = engine.cell_state(x, y);
+        engine.set_cell_state(x, y, state ^ change);
+    }
+}
+
+/// Every cell as how many rows down it is from the previous one, and then
+/// either its column if it is on a new row or how far right it is otherwise,
+/// followed by how its state changed if `with_states`
+fn encode(cells: &[([i64; 2], u8)], with_states: bool) -> Box<[u8]> {
+    let mut bytes = Vec::new();
+    let mut previous = [0, 0];
+
+    for &([x, y], change) in cells {
+        let rows_down = y.wrapping_sub(previous[1]);
+        write_varint(&mut bytes, rows_down);
+        write_varint(
+            &mut bytes,
+            if rows_down == 0 {
+                x.wrapping_sub(previous[0])
+            } else {
+                x
+            },
+        );
+        if with_states {
+            bytes.push(change);
+        }
+        previous = [x, y];
+    }
+
+    bytes.into_boxed_slice()
+}
+
+/// Cells along with how their state changed, which is always 1 without states
+fn decode(mut bytes: &[u8], with_states: bool) -> impl Iterator<Item = ([i64; 2], u8)> + '_ {
+    let mut previous = [0i64, 0];
+
+    std::iter::from_fn(move || {
+        if bytes.is_empty() {
+            return None;
+        }
+
+        let rows_down = read_varint(&mut bytes);
+        let x = read_varint(&mut bytes);
+
+        previous = if rows_down == 0 {
+            [previous[0].wrapping_add(x), previous[1]]
+        } else {
+            [x, previous[1].wrapping_add(rows_down)]
+        };
+
+        let change = match bytes.split_first() {
+            Some((&change, rest)) if with_states => {
+                bytes = rest;
+                change
+            }
+            _ => 1,
+        };
+
+        Some((previous, change))
+    })
+}
+
+/// Zigzag so small negative numbers stay small, then 7 bits per byte
+fn write_varint(bytes: &mut Vec<u8>, value: i64) {
+    let mut value = ((value << 1) ^ (value >> 63)) as u64;
+
+    while value >= 0x80 {
+        bytes.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    bytes.push(value as u8);
+}
+
+fn read_varint(bytes: &mut &[u8]) -> i64 {
+    let mut value = 0u64;
+    let mut shift = 0;
+
+    while let Some((&byte, rest)) = bytes.split_first() {
+        *bytes = rest;
+        value |= ((byte & 0x7F) as u64) << shift;
+        shift += 7;
+
+        if byte & 0x80 == 0 {
+            break;
+        }
+    }
+
+    (value >> 1) as i64 ^ -((value & 1) as i64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Life, SparseLife};
+
+    const GLIDER: [[i64; 2]; 5] = [[1, 0], [2, 1], [0, 2], [1, 2], [2, 2]];
+
+    #[test]
+    fn encoding_round_trips() {
+        let cells = [
+            ([-5, -3], 1),
+            ([0, -3], 1),
+            ([i64::MAX, -3], 1),
+            ([i64::MIN, 7], 1),
+            ([3, 7], 1),
+            ([2, 1_000_000_000_000], 1),
+        ];
+        let bytes = encode(&cells, false);
+
+        assert_eq!(decode(&bytes, false).collect::<Vec<_>>(), cells);
+        assert_eq!(
+            encode(&[([1, 0], 1), ([2, 0], 1), ([0, 1], 1)], false).len(),
+            6
+        );
+
+        let changes = [([1, 0], 3), ([-2, 4], 1)];
+        let bytes = encode(&changes, true);
+        assert_eq!(decode(&bytes, true).collect::<Vec<_>>(), changes);
+    }
+
+    #[test]
+    fn steps_back_and_forward() {
+        let mut sparse = SparseLife::from_cells(GLIDER, 0);
+        let mut history = History::default();
+
+        let mut generations = vec![sorted_cells(&sparse)];
+        for _ in 0..10 {
+            history.step(&mut sparse);
+            generations.push(sorted_cells(&sparse));
+        }
+        assert_eq!(history.len(), 10);
+
+        for generation in (0..10).rev() {
+            assert!(history.step_back(&mut sparse));
+            assert_eq!(sparse.generation(), generation);
+            assert_eq!(sorted_cells(&sparse), generations[generation as usize]);
+        }
+        assert!(!history.step_back(&mut sparse));
+
+        assert!(history.rewind_to(&mut sparse, 7));
+        assert_eq!(sorted_cells(&sparse), generations[7]);
+        assert_eq!(history.newest_generation(), Some(10));
+    }
+
+    #[test]
+    fn grids_remember_the_same_as_planes() {
+        // Wide enough for rows of more than one word
+        let mut life = Life::new(100, 40);
+        let mut sparse = SparseLife::from_cells([], 0);
+        for [x, y] in GLIDER {
+            Engine::set_cell(&mut life, x + 60, y, true);
+            sparse.set_cell(x + 60, y, true);
+        }
+        let (mut grid_history, mut sparse_history) = (History::default(), History::default());
+
+        grid_history.step_n(&mut life, 30);
+        sparse_history.step_n(&mut sparse, 30);
+        assert_eq!(grid_history.memory(), sparse_history.memory());
+        assert_eq!(sorted_cells(&life), sorted_cells(&sparse));
+
+        assert!(grid_history.rewind_to(&mut life, 3));
+        assert!(sparse_history.rewind_to(&mut sparse, 3));
+        assert_eq!(sorted_cells(&life), sorted_cells(&sparse));
+    }
+
+    #[test]
+    fn editing_forgets_the_future() {
+        let mut life = Life::new(10, 10);
+        for [x, y] in GLIDER {
+            Engine::set_cell(&mut life, x, y, true);
+        }
+        let mut history = History::default();
+
+        history.step_n(&mut life, 4);
+        history.step_back(&mut life);
+        assert!(history.can_step_forward());
+
+        Engine::set_cell(&mut life, 9, 9, true);
+        history.edited();
+        assert!(!history.can_step_forward());
+
+        // The edit is part of generation 3 now, so going back to it keeps the edit
+        history.step(&mut life);
+        history.step_back(&mut life);
+        assert!(Engine::cell(&life, 9, 9));
+    }
+
+    #[test]
+    fn generations_step_back_with_dying_cells() {
+        let mut life = Life::new(20, 20);
+        life.set_rule("B2/S/C3".parse().unwrap()).unwrap();
+        for [x, y] in GLIDER {
+            Engine::set_cell(&mut life, x + 8, y + 8, true);
+        }
+        let mut history = History::default();
+
+        let mut generations = vec![sorted_cells(&life)];
+        for _ in 0..6 {
+            history.step(&mut life);
+            generations.push(sorted_cells(&life));
+        }
+
+        assert!(history.rewind_to(&mut life, 2));
+        assert_eq!(sorted_cells(&life), generations[2]);
+        assert!(history.rewind_to(&mut life, 5));
+        assert_eq!(sorted_cells(&life), generations[5]);
+    }
+
+    #[test]
+    fn forgets_the_oldest_generations() {
+        let mut sparse = SparseLife::from_cells(GLIDER, 0);
+        let mut history = History::new(100);
+
+        history.step_n(&mut sparse, 50);
+
+        assert!(history.memory() <= 100);
+        assert!(history.len() < 50);
+        assert_eq!(history.oldest_generation(), Some(50 - history.len() as u64));
+        let oldest = history.oldest_generation().unwrap();
+        assert!(!history.rewind_to(&mut sparse, 0));
+        assert_eq!(sparse.generation(), oldest);
+
+        history.set_limit(0);
+        assert!(history.is_empty());
+        assert_eq!(history.memory(), 0);
+        history.step(&mut sparse);
+        assert!(history.is_empty());
+        assert_eq!(sparse.generation(), oldest + 1);
+    }
+
+    #[test]
+    fn still_lifes_take_memory_too() {
+        let mut life = Life::new(16, 16);
+        for [x, y] in [[4, 4], [5, 4], [4, 5], [5, 5]] {
+            Engine::set_cell(&mut life, x, y, true);
+        }
+        let limit = 1000;
+        let mut history = History::new(limit);
+
+        for _ in 0..5000 {
+            history.step(&mut life);
+        }
+        assert!(history.memory() <= limit);
+        assert!(history.len() <= limit / std::mem::size_of::<Delta>());
+        assert!(!history.is_empty());
+
+        // Stepping one at a time keeps the bits it already has
+        assert!(history.current_bits.is_some());
+        history.edited();
+        assert!(history.current_bits.is_none());
+    }
+}
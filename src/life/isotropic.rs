@@ -0,0 +1,311 @@
+/*!
+Isotropic non-totalistic rules, the ones that care about which of the 8 neighbors
+are alive and not only how many, as long as rotating or flipping the neighborhood
+does not change what happens
+
+They are written in Hensel notation, with letters after a neighbor count
+for the shapes the live neighbors make, e.g. `B2-a/S12`:
+- `2ce` only the shapes `c` and `e` with two live neighbors
+- `2-a` every shape with two live neighbors except `a`
+- `2` alone every shape, just like in the usual rulestrings
+
+Counts 0 and 8 only have one shape, and every count above 4 uses the same letters
+as the count of dead neighbors it leaves (5 is like 3, 6 like 2 and so on).
+
+Neighborhoods are 9 bits, bit `3 * y + x` being the cell at column `x` and row `y`
+of the 3x3 square, so bit 4 is the middle one. Every rule is turned into a table with
+whether the middle is alive in the next generation for each of the 512 of them
+*/
+
+use super::{Life, RuleError};
+
+/// The middle cell of a neighborhood
+pub const MIDDLE: u16 = 1 << 4;
+
+/// Every cell of a neighborhood but the middle
+const OUTER: u16 = 0x1FF & !MIDDLE;
+
+/// The letters of every neighbor count up to 4, in the order they are written in
+const LETTERS: [&str; 5] = ["", "ce", "ceaikn", "ceaiknjqry", "ceaiknjqrtwyz"];
+
+/// One neighborhood with the shape of each letter, in the same order as [`LETTERS`]
+const SHAPES: [&[u16]; 5] = [
+    &[0],
+    &[1, 2],
+    &[5, 10, 3, 40, 33, 68],
+    &[69, 42, 11, 7, 98, 13, 14, 70, 41, 97],
+    &[325, 170, 15, 45, 99, 71, 106, 102, 43, 101, 105, 78, 108],
+];
+
+/// The letters for `count` live neighbors, none for 0 and 8
+pub fn letters(count: u8) -> &'static str {
+    LETTERS[count.min(8 - count) as usize]
+}
+
+/// Every letter of `count` set, with the one shape of 0 and 8 taking bit 0
+fn all_letters(count: u8) -> u16 {
+    (1 << letters(count).len().max(1)) - 1
+}
+
+/// Turned a quarter clockwise
+fn rotate(neighborhood: u16) -> u16 {
+    (0..9)
+        .filter(|bit| neighborhood >> bit & 1 == 1)
+        .fold(0, |rotated, bit| {
+            let (x, y) = (bit % 3, bit / 3);
+            rotated | 1 << (3 * x + 2 - y)
+        })
+}
+
+/// Mirrored left to right
+fn flip(neighborhood: u16) -> u16 {
+    (0..9)
+        .filter(|bit| neighborhood >> bit & 1 == 1)
+        .fold(0, |flipped, bit| {
+            let (x, y) = (bit % 3, bit / 3);
+            flipped | 1 << (3 * y + 2 - x)
+        })
+}
+
+/// Which letter the live neighbors of `neighborhood` make, as its index
+fn shape(neighborhood: u16) -> usize {
+    let mut outer = neighborhood & OUTER;
+    if outer.count_ones() > 4 {
+        outer ^= OUTER;
+    }
+
+    let shapes = SHAPES[outer.count_ones() as usize];
+    for _ in 0..4 {
+        for turned in [outer, flip(outer)] {
+            if let Some(index) = shapes.iter().position(|shape| *shape == turned) {
+                return index;
+            }
+        }
+        outer = rotate(outer);
+    }
+    unreachable!("every neighborhood has a shape")
+}
+
+/// A neighborhood with the shape of letter `index` of `count`, without the middle
+fn example(count: u8, index: usize) -> u16 {
+    let shape = SHAPES[count.min(8 - count) as usize][index];
+    if count > 4 {
+        shape ^ OUTER
+    } else {
+        shape
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Isotropic {
+    /// Bit `n` is set if the middle of neighborhood `n` is alive in the next generation
+    table: [u64; 8],
+}
+
+impl Isotropic {
+    /// Bit `i` of `birth[n]` is set if a dead cell is born when its `n` live neighbors
+    /// make the shape of letter `i` of [`letters(n)`](letters), same for survival
+    pub fn from_letters(birth: [u16; 9], survival: [u16; 9]) -> Self {
+        let mut table = [0; 8];
+        for neighborhood in 0..512u16 {
+            let count = (neighborhood & OUTER).count_ones() as usize;
+            let letters = if neighborhood & MIDDLE == 0 {
+                birth[count]
+            } else {
+                survival[count]
+            };
+
+            if letters >> shape(neighborhood) & 1 == 1 {
+                table[neighborhood as usize / 64] |= 1 << (neighborhood % 64);
+            }
+        }
+        Self { table }
+    }
+
+    /// Whether the middle of `neighborhood` is alive in the next generation
+    pub fn next(&self, neighborhood: u16) -> bool {
+        let neighborhood = neighborhood & 0x1FF;
+        self.table[neighborhood as usize / 64] >> (neighborhood % 64) & 1 == 1
+    }
+
+    /// The letters for `count` live neighbors as a mask like the ones of [`Isotropic::from_letters`]
+    pub fn letters(&self, alive: bool, count: u8) -> u16 {
+        let middle = if alive { MIDDLE } else { 0 };
+        (0..letters(count).len().max(1))
+            .filter(|index| self.next(example(count, *index) | middle))
+            .fold(0, |mask, index| mask | 1 << index)
+    }
+
+    /// The birth or survival part of the rulestring without its letter,
+    /// going with whichever of the letters or the missing ones is shorter
+    pub(super) fn counts(&self, alive: bool) -> String {
+        let mut counts = String::new();
+
+        for count in 0..=8 {
+            let mask = self.letters(alive, count);
+            if mask == 0 {
+                continue;
+            }
+
+            counts.push(char::from(b'0' + count));
+            let all = all_letters(count);
+            if mask == all {
+                continue;
+            }
+
+            let (written, negated) = if mask.count_ones() * 2 <= all.count_ones() {
+                (mask, false)
+            } else {
+                (all & !mask, true)
+            };
+            if negated {
+                counts.push('-');
+            }
+            counts.extend(
+                letters(count)
+                    .chars()
+                    .enumerate()
+                    .filter(|(index, _)| written >> index & 1 == 1)
+                    .map(|(_, letter)| letter),
+            );
+        }
+
+        counts
+    }
+}
+
+/// The masks of letters for each neighbor count in a part of the rulestring like `2-a3ce`,
+/// a count without letters having all of them
+pub(super) fn parse_letters(part: &str) -> Result<[u16; 9], RuleError> {
+    let mut masks = [0; 9];
+    let mut characters = part.chars().peekable();
+
+    while let Some(character) = characters.next() {
+        let count = match character.to_digit(10) {
+            Some(count) if count <= 8 => count as u8,
+            _ => return Err(RuleError::InvalidNeighborCount(character)),
+        };
+
+        let negated = characters.next_if_eq(&'-').is_some();
+        let mut mask = 0;
+        while let Some(letter) = characters.next_if(char::is_ascii_lowercase) {
+            let index = letters(count)
+                .find(letter)
+                .ok_or(RuleError::InvalidLetter(letter))?;
+            mask |= 1 << index;
+        }
+
+        let all = all_letters(count);
+        masks[count as usize] |= match (negated, mask) {
+            (false, 0) => all,
+            (true, 0) => return Err(RuleError::InvalidFormat),
+            (false, mask) => mask,
+            (true, mask) => all & !mask,
+        };
+    }
+
+    Ok(masks)
+}
+
+/// Whether every count has either all of its letters or none, so the rule doesn't need them
+pub(super) fn is_totalistic(masks: [u16; 9]) -> bool {
+    (0..=8).all(|count| {
+        let mask = masks[count as usize];
+        mask == 0 || mask == all_letters(count)
+    })
+}
+
+impl Life {
+    /// [`Life::step`] for isotropic non-totalistic rules
+    pub(super) fn step_isotropic(&mut self, isotropic: Isotropic) {
+        let (columns, rows) = (self.columns(), self.rows());
+
+        // The grid with one more cell on every side, whatever the topology puts there
+        let width = columns + 2;
+        let mut padded = vec![false; width * (rows + 2)];
+        for (index, alive) in padded.iter_mut().enumerate() {
+            let (x, y) = ((index % width) as i64 - 1, (index / width) as i64 - 1);
+            *alive = self
+                .topology
+                .locate(x, y, columns, rows)
+                .is_some_and(|(column, row)| self.get(column, row));
+        }
+
+        self.step_cells(|life, [x, y], state| {
+            let (column, row) = (x as usize, y as usize);
+            life.rule.next_state(state, || {
+                let neighborhood = (0..9)
+                    .filter(|bit| padded[(row + bit / 3) * width + column + bit % 3])
+                    .fold(0, |neighborhood, bit| neighborhood | 1 << bit);
+                isotropic.next(neighborhood)
+            })
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::life::tests::{assert_grid_and_plane_agree, soup};
+    use crate::{Rule, RULE_PRESETS};
+
+    #[test]
+    fn shapes_cover_every_neighborhood() {
+        let sizes: [&[usize]; 5] = [
+            &[1],
+            &[4, 4],
+            &[4, 4, 8, 2, 8, 2],
+            &[4, 4, 4, 4, 4, 8, 8, 8, 8, 4],
+            &[1, 1, 8, 4, 8, 8, 8, 4, 8, 8, 4, 4, 4],
+        ];
+
+        for count in 0..=8u8 {
+            let mut found = vec![0; letters(count).len().max(1)];
+            for neighborhood in (0..512).filter(|n| n & MIDDLE == 0) {
+                if (neighborhood & OUTER).count_ones() == count as u32 {
+                    found[shape(neighborhood)] += 1;
+                }
+            }
+            assert_eq!(found, sizes[count.min(8 - count) as usize], "{}", count);
+        }
+    }
+
+    #[test]
+    fn parses_and_prints_back() {
+        let just_friends: Rule = "B2-a/S12".parse().unwrap();
+        let isotropic = just_friends.isotropic().unwrap();
+
+        // N and NE are an `a`, N and S an `i`
+        assert!(!isotropic.next(0b000_000_110));
+        assert!(isotropic.next(0b010_000_010));
+        assert_eq!(just_friends.to_string(), "B2-a/S12");
+        assert_eq!("b2cekin/s12".parse(), Ok(just_friends));
+
+        let rule: Rule = "B3ai4-z/S2c5/C3".parse().unwrap();
+        assert_eq!(rule.states(), 3);
+        assert_eq!(rule.to_string(), "B3ai4-z/S2c5/C3");
+    }
+
+    #[test]
+    fn all_letters_is_totalistic() {
+        assert_eq!("B3ceaiknjqry/S23".parse(), Ok(Rule::CONWAY));
+        assert_eq!("B3/S2cekain3".parse(), Ok(Rule::CONWAY));
+        assert_eq!("B2/S/C3".parse(), Ok(RULE_PRESETS[6].1.clone()));
+
+        assert_eq!(
+            "B2x/S23".parse::<Rule>(),
+            Err(RuleError::InvalidLetter('x'))
+        );
+        assert_eq!(
+            "B1a/S23".parse::<Rule>(),
+            Err(RuleError::InvalidLetter('a'))
+        );
+        assert_eq!("B2-/S23".parse::<Rule>(), Err(RuleError::InvalidFormat));
+    }
+
+    #[test]
+    fn grid_and_plane_agree() {
+        let cells = soup([40, 40, 59, 59], 1.0 / 3.0, 0x15070);
+        assert_grid_and_plane_agree("B2-a/S12".parse().unwrap(), 100, &cells, 10);
+    }
+}
@@ -0,0 +1,113 @@
+/*!
+Jumping ahead to a later generation as fast as possible instead of at the updates per second
+
+Generations are computed in batches so that whatever is showing the progress
+still gets a chance to, and to cancel the jump. The batches grow while they are
+quick and shrink when they take too long, so every one of them takes about as long
+as it is given however slow the generations are
+*/
+
+use std::time::Duration;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Jump {
+    /// Where it started, for the progress
+    from: u64,
+    target: u64,
+    /// How many generations the next batch has at most
+    batch: u64,
+}
+
+impl Jump {
+    /// From `generation` up to `target`, a target that was already reached is done right away
+    pub fn to(generation: u64, target: u64) -> Self {
+        Self {
+            from: generation,
+            target: target.max(generation),
+            batch: 1,
+        }
+    }
+
+    /// `generations` more than `generation`
+    pub fn by(generation: u64, generations: u64) -> Self {
+        Self::to(generation, generation.saturating_add(generations))
+    }
+
+    /// The generation it started at
+    pub fn from(&self) -> u64 {
+        self.from
+    }
+
+    pub fn target(&self) -> u64 {
+        self.target
+    }
+
+    pub fn is_done(&self, generation: u64) -> bool {
+        generation >= self.target
+    }
+
+    /// From 0 when it started to 1 when it is done
+    pub fn progress(&self, generation: u64) -> f32 {
+        if self.target == self.from {
+            return 1.0;
+        }
+        let done = generation.clamp(self.from, self.target) - self.from;
+        (done as f64 / (self.target - self.from) as f64) as f32
+    }
+
+    /// How many generations to compute next when at `generation`
+    pub fn next_batch(&self, generation: u64) -> u64 {
+        self.batch.min(self.target.saturating_sub(generation))
+    }
+
+    /// Makes the next batches bigger or smaller depending on whether the last one
+    /// took well under or over `budget`
+    pub fn batch_took(&mut self, took: Duration, budget: Duration) {
+        if took < budget / 2 {
+            self.batch = self.batch.saturating_mul(2);
+        } else if took > budget {
+            self.batch = (self.batch / 2).max(1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn progress_goes_from_start_to_target() {
+        let jump = Jump::by(100, 50);
+        assert_eq!(jump.target(), 150);
+        assert_eq!(jump.progress(100), 0.0);
+        assert_eq!(jump.progress(125), 0.5);
+        assert_eq!(jump.progress(150), 1.0);
+        assert!(!jump.is_done(149));
+        assert!(jump.is_done(150));
+
+        // Targets in the past are already there
+        let jump = Jump::to(100, 20);
+        assert!(jump.is_done(100));
+        assert_eq!(jump.progress(100), 1.0);
+        assert_eq!(jump.next_batch(100), 0);
+    }
+
+    #[test]
+    fn batches_follow_the_budget() {
+        let budget = Duration::from_millis(20);
+        let mut jump = Jump::by(0, 1000);
+        assert_eq!(jump.next_batch(0), 1);
+
+        for _ in 0..4 {
+            jump.batch_took(Duration::from_millis(1), budget);
+        }
+        assert_eq!(jump.next_batch(0), 16);
+        // Never past the target
+        assert_eq!(jump.next_batch(990), 10);
+
+        jump.batch_took(Duration::from_millis(15), budget);
+        assert_eq!(jump.next_batch(0), 16);
+        jump.batch_took(Duration::from_millis(50), budget);
+        assert_eq!(jump.next_batch(0), 8);
+    }
+}
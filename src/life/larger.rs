@@ -0,0 +1,473 @@
+/*!
+Larger than Life, rules that count the live cells in a bigger neighborhood
+and are born or survive when that count is within an interval
+
+They are written like Golly does, e.g. Bosco's rule is `R5,C0,M1,S34..58,B34..45,NM`:
+- `R` the range, how far the neighborhood reaches
+- `C` the states, anything below 3 being the usual dead and alive
+  and more than that dying like Generations rules do
+- `M1` if a live cell counts itself, `M0` otherwise
+- `S` and `B` the intervals for survival and birth, both ends included
+- `N` the neighborhood, `M` Moore, `N` von Neumann, `C` circular, or `@` followed
+  by the cells of a custom one in hexadecimal, row by row and most significant bit first
+
+Grids are stepped with the sums of every row, so the neighborhood is split into
+one span of columns per row and each span takes two lookups, whatever its length.
+Infinite planes go the other way around and add every live cell to its neighbors
+*/
+
+use std::fmt;
+
+use super::{Life, RuleError};
+
+/// Further than this and the sums of a grid's rows take way too long anyway
+pub const MAX_RANGE: u16 = 500;
+
+/// Custom neighborhoods have to fit in the rows of [`Neighborhood::Custom`]
+pub const MAX_CUSTOM_RANGE: u16 = 7;
+
+/// Side of the biggest custom neighborhood
+const CUSTOM_SIDE: usize = 2 * MAX_CUSTOM_RANGE as usize + 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Neighborhood {
+    /// Every cell in the square around
+    Moore,
+    /// The cells up to the range away going only up, down, left and right
+    VonNeumann,
+    /// The cells whose centers are less than the range and a half away
+    Circular,
+    /// Bit `x` of row `y` is set if the cell at `x` and `y` from the
+    /// top left corner of the square around counts, the middle one is left to `M`
+    Custom([u16; CUSTOM_SIDE]),
+}
+
+impl Neighborhood {
+    /// Whether the cell `x`, `y` away from the middle counts, for a neighborhood of `range`
+    pub fn contains(&self, range: u16, x: i64, y: i64) -> bool {
+        let range = range as i64;
+        if x.abs() > range || y.abs() > range {
+            return false;
+        }
+
+        match self {
+            Self::Moore => true,
+            Self::VonNeumann => x.abs() + y.abs() <= range,
+            // Squared, (range + 0.5)² being range² + range + 0.25
+            Self::Circular => x * x + y * y <= range * range + range,
+            Self::Custom(rows) => rows[(y + range) as usize] >> (x + range) & 1 == 1,
+        }
+    }
+
+    /// The cells of a neighborhood of `range` that are up to `new_range` away,
+    /// as a custom neighborhood. `None` if the new range is too big for one
+    pub fn to_custom(&self, range: u16, new_range: u16) -> Option<Self> {
+        if new_range > MAX_CUSTOM_RANGE {
+            return None;
+        }
+
+        let mut rows = [0; CUSTOM_SIDE];
+        let new_range = new_range as i64;
+        for y in -new_range..=new_range {
+            for x in -new_range..=new_range {
+                if self.contains(range, x, y) {
+                    rows[(y + new_range) as usize] |= 1 << (x + new_range);
+                }
+            }
+        }
+        Some(Self::Custom(rows))
+    }
+
+    /// Flips whether the cell `x`, `y` away from the middle counts,
+    /// only custom neighborhoods can be changed
+    pub fn toggle(&mut self, range: u16, x: i64, y: i64) {
+        let range = range as i64;
+        if let Self::Custom(rows) = self {
+            if x.abs() <= range && y.abs() <= range {
+                rows[(y + range) as usize] ^= 1 << (x + range);
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct LargerThanLife {
+    pub range: u16,
+    pub neighborhood: Neighborhood,
+    /// Whether a live cell counts itself
+    pub middle: bool,
+    /// Both ends included
+    pub birth: [u32; 2],
+    pub survival: [u32; 2],
+}
+
+impl LargerThanLife {
+    /// Whether a cell is alive in the next generation,
+    /// `count` being how many live cells its neighborhood has
+    pub fn next(&self, alive: bool, count: u32) -> bool {
+        let [low, high] = if alive { self.survival } else { self.birth };
+        (low..=high).contains(&count)
+    }
+
+    /// Dead cells with no neighbors at all are born
+    pub fn has_b0(&self) -> bool {
+        self.birth[0] == 0
+    }
+
+    /// Where every cell that counts is relative to the middle,
+    /// the middle one being there if it counts itself
+    pub fn offsets(&self) -> Vec<[i64; 2]> {
+        let range = self.range as i64;
+
+        let mut offsets = Vec::new();
+        for y in -range..=range {
+            for x in -range..=range {
+                if [x, y] == [0, 0] {
+                    if self.middle {
+                        offsets.push([0, 0]);
+                    }
+                } else if self.neighborhood.contains(self.range, x, y) {
+                    offsets.push([x, y]);
+                }
+            }
+        }
+        offsets
+    }
+
+    /// The neighborhood as runs of cells in the same row, `[y, first x, last x]`,
+    /// with the middle always in one of them
+    fn spans(&self) -> Vec<[i64; 3]> {
+        let range = self.range as i64;
+        let counts =
+            |x: i64, y: i64| [x, y] == [0, 0] || self.neighborhood.contains(self.range, x, y);
+
+        let mut spans = Vec::new();
+        for y in -range..=range {
+            let mut start = None;
+            for x in -range..=range + 1 {
+                match (start, x <= range && counts(x, y)) {
+                    (None, true) => start = Some(x),
+                    (Some(first), false) => {
+                        spans.push([y, first, x - 1]);
+                        start = None;
+                    }
+                    _ => {}
+                }
+            }
+        }
+        spans
+    }
+
+    /// Checks the range fits the neighborhood
+    pub fn validate(&self) -> Result<(), RuleError> {
+        let max = match self.neighborhood {
+            Neighborhood::Custom(_) => MAX_CUSTOM_RANGE,
+            _ => MAX_RANGE,
+        };
+
+        if (1..=max).contains(&self.range) {
+            Ok(())
+        } else {
+            Err(RuleError::InvalidRange)
+        }
+    }
+
+    /// Writes the rulestring with `states` for its `C`, since they are kept in the [`Rule`](super::Rule)
+    pub(super) fn write(&self, f: &mut fmt::Formatter<'_>, states: u8) -> fmt::Result {
+        write!(
+            f,
+            "R{},C{},M{},S{}..{},B{}..{},N",
+            self.range,
+            if states > 2 { states } else { 0 },
+            self.middle as u8,
+            self.survival[0],
+            self.survival[1],
+            self.birth[0],
+            self.birth[1],
+        )?;
+
+        match self.neighborhood {
+            Neighborhood::Moore => write!(f, "M"),
+            Neighborhood::VonNeumann => write!(f, "N"),
+            Neighborhood::Circular => write!(f, "C"),
+            Neighborhood::Custom(_) => {
+                let side = 2 * self.range as i64 + 1;
+                let bits: Vec<bool> = (0..side * side)
+                    .map(|i| {
+                        let (x, y) = (i % side - self.range as i64, i / side - self.range as i64);
+                        self.neighborhood.contains(self.range, x, y)
+                    })
+                    .collect();
+
+                write!(f, "@")?;
+                for nibble in bits.chunks(4) {
+                    let digit = nibble
+                        .iter()
+                        .chain(std::iter::repeat(&false))
+                        .take(4)
+                        .fold(0, |digit, bit| digit << 1 | *bit as u32);
+                    write!(f, "{:x}", digit)?;
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// Takes an already uppercased rulestring, returns the rule and its states
+    pub(super) fn parse(rulestring: &str) -> Result<(Self, u8), RuleError> {
+        let mut range = None;
+        let mut states = 2;
+        let mut middle = false;
+        let mut birth = None;
+        let mut survival = None;
+        let mut neighborhood = "M";
+
+        for part in rulestring.split(',') {
+            let part = part.trim();
+            let Some(letter) = part.chars().next().filter(char::is_ascii_alphabetic) else {
+                return Err(RuleError::InvalidFormat);
+            };
+            let value = &part[1..];
+
+            match letter {
+                'R' => range = Some(value.parse().map_err(|_| RuleError::InvalidRange)?),
+                'C' => {
+                    states = match value.parse::<u8>() {
+                        Ok(0..=2) => 2,
+                        Ok(states) => states,
+                        Err(_) => return Err(RuleError::InvalidStateCount),
+                    }
+                }
+                'M' => {
+                    middle = match value {
+                        "0" => false,
+                        "1" => true,
+                        _ => return Err(RuleError::InvalidFormat),
+                    }
+                }
+                'S' => survival = Some(parse_interval(value)?),
+                'B' => birth = Some(parse_interval(value)?),
+                'N' => neighborhood = value,
+                _ => return Err(RuleError::InvalidFormat),
+            }
+        }
+
+        let (Some(range), Some(birth), Some(survival)) = (range, birth, survival) else {
+            return Err(RuleError::InvalidFormat);
+        };
+
+        let neighborhood = match neighborhood {
+            "M" => Neighborhood::Moore,
+            "N" => Neighborhood::VonNeumann,
+            "C" => Neighborhood::Circular,
+            hex => {
+                let hex = hex.strip_prefix('@').ok_or(RuleError::InvalidFormat)?;
+                parse_custom(hex, range)?
+            }
+        };
+
+        let larger = Self {
+            range,
+            neighborhood,
+            middle,
+            birth,
+            survival,
+        };
+        larger.validate()?;
+
+        Ok((larger, states))
+    }
+}
+
+/// `34..58` or a single `34`
+fn parse_interval(value: &str) -> Result<[u32; 2], RuleError> {
+    let (low, high) = value.split_once("..").unwrap_or((value, value));
+    let parse = |count: &str| count.parse().map_err(|_| RuleError::InvalidFormat);
+
+    match [parse(low)?, parse(high)?] {
+        [low, high] if low <= high => Ok([low, high]),
+        _ => Err(RuleError::InvalidFormat),
+    }
+}
+
+fn parse_custom(hex: &str, range: u16) -> Result<Neighborhood, RuleError> {
+    if !(1..=MAX_CUSTOM_RANGE).contains(&range) {
+        return Err(RuleError::InvalidRange);
+    }
+
+    let side = 2 * range as usize + 1;
+    if hex.len() != (side * side).div_ceil(4) {
+        return Err(RuleError::InvalidFormat);
+    }
+
+    let mut rows = [0; CUSTOM_SIDE];
+    for (index, digit) in hex.chars().enumerate() {
+        let digit = digit.to_digit(16).ok_or(RuleError::InvalidFormat)?;
+
+        for bit in 0..4 {
+            let cell = index * 4 + bit;
+            if cell < side * side && digit >> (3 - bit) & 1 == 1 {
+                rows[cell / side] |= 1 << (cell % side);
+            }
+        }
+    }
+
+    Ok(Neighborhood::Custom(rows))
+}
+
+impl Life {
+    /// [`Life::step`] for Larger than Life rules
+    pub(super) fn step_larger(&mut self, larger: LargerThanLife) {
+        let (columns, rows) = (self.columns(), self.rows());
+        let range = larger.range as i64;
+
+        // Every row with `range` cells more on each side and `range` more rows
+        // above and below, as sums of the live cells up to every column
+        let width = columns + 2 * larger.range as usize;
+        let height = rows + 2 * larger.range as usize;
+        let mut sums = vec![0u32; height * (width + 1)];
+        for padded_y in 0..height {
+            let y = padded_y as i64 - range;
+            let row = &mut sums[padded_y * (width + 1)..(padded_y + 1) * (width + 1)];
+
+            for padded_x in 0..width {
+                let x = padded_x as i64 - range;
+                let alive = self
+                    .topology
+                    .locate(x, y, columns, rows)
+                    .is_some_and(|(column, row)| self.get(column, row));
+                row[padded_x + 1] = row[padded_x] + alive as u32;
+            }
+        }
+
+        let spans = larger.spans();
+        self.step_cells(|life, [x, y], state| {
+            life.rule.next_state(state, || {
+                let mut count = 0;
+                for &[offset_y, first_x, last_x] in &spans {
+                    let start = (y + range + offset_y) as usize * (width + 1);
+                    let x = x + range;
+                    count += sums[start + (x + last_x + 1) as usize]
+                        - sums[start + (x + first_x) as usize];
+                }
+
+                let alive = state == 1;
+                if alive && !larger.middle {
+                    count -= 1;
+                }
+                larger.next(alive, count)
+            })
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::life::tests::{assert_grid_and_plane_agree, soup};
+    use crate::{Rule, Topology};
+
+    const BOSCO: &str = "R5,C0,M1,S34..58,B34..45,NM";
+
+    #[test]
+    fn parses_and_prints_back() {
+        let bosco: Rule = BOSCO.parse().unwrap();
+        let larger = bosco.larger_than_life().unwrap();
+
+        assert_eq!(larger.range, 5);
+        assert!(larger.middle);
+        assert_eq!(larger.survival, [34, 58]);
+        assert_eq!(larger.birth, [34, 45]);
+        assert_eq!(bosco.to_string(), BOSCO);
+        assert_eq!("r5,c0,m1,s34..58,b34..45,nm".parse::<Rule>(), Ok(bosco));
+
+        let generations: Rule = "R2,C4,M0,S3..5,B2..3,NN".parse().unwrap();
+        assert_eq!(generations.states(), 4);
+        assert_eq!(generations.to_string(), "R2,C4,M0,S3..5,B2..3,NN");
+
+        assert_eq!(
+            "R0,C0,M0,S1..2,B3..3,NM".parse::<Rule>(),
+            Err(RuleError::InvalidRange)
+        );
+        assert_eq!(
+            "R2,C0,M0,S5..1,B3..3,NM".parse::<Rule>(),
+            Err(RuleError::InvalidFormat)
+        );
+    }
+
+    #[test]
+    fn neighborhood_sizes() {
+        let count = |neighborhood| {
+            LargerThanLife {
+                range: 2,
+                neighborhood,
+                middle: false,
+                birth: [1, 1],
+                survival: [1, 1],
+            }
+            .offsets()
+            .len()
+        };
+
+        assert_eq!(count(Neighborhood::Moore), 24);
+        assert_eq!(count(Neighborhood::VonNeumann), 12);
+        // The 5x5 square without its corners
+        assert_eq!(count(Neighborhood::Circular), 20);
+    }
+
+    #[test]
+    fn to_custom_keeps_the_cells() {
+        let custom = Neighborhood::VonNeumann.to_custom(2, 2).unwrap();
+        let grown = custom.to_custom(2, 3).unwrap();
+
+        for y in -3..=3 {
+            for x in -3..=3 {
+                let expected = Neighborhood::VonNeumann.contains(2, x, y);
+                assert_eq!(custom.contains(2, x, y), expected);
+                assert_eq!(grown.contains(3, x, y), expected);
+            }
+        }
+        assert_eq!(custom.to_custom(2, MAX_CUSTOM_RANGE + 1), None);
+    }
+
+    #[test]
+    fn custom_neighborhood_round_trips() {
+        // The hexagonal-ish neighborhood of range 1, without the top right and bottom left
+        let rule: Rule = "R1,C0,M0,S2..3,B2..2,N@d58".parse().unwrap();
+        let larger = rule.larger_than_life().unwrap();
+
+        assert_eq!(
+            larger.offsets(),
+            vec![[-1, -1], [0, -1], [-1, 0], [1, 0], [0, 1], [1, 1]]
+        );
+        assert_eq!(rule.to_string(), "R1,C0,M0,S2..3,B2..2,N@d58");
+        assert_eq!(
+            "R8,C0,M0,S2..3,B2..2,N@d98".parse::<Rule>(),
+            Err(RuleError::InvalidRange)
+        );
+    }
+
+    #[test]
+    fn range_one_is_plain_life() {
+        let larger: Rule = "R1,C0,M0,S2..3,B3..3,NM".parse().unwrap();
+
+        let mut plain = Life::new(40, 30);
+        plain.set_topology(Topology::KleinBottle);
+        for [x, y] in soup([0, 0, 39, 29], 1.0 / 3.0, 0xC0FFEE) {
+            plain.set(x as usize, y as usize, true);
+        }
+        let mut stepped = plain.clone();
+        stepped.set_rule(larger).unwrap();
+
+        plain.step_n(20);
+        stepped.step_n(20);
+        assert!(plain.cells() == stepped.cells());
+        assert_eq!(plain.births_and_deaths(), stepped.births_and_deaths());
+    }
+
+    #[test]
+    fn grid_and_plane_agree() {
+        let cells = soup([50, 50, 69, 69], 0.5, 0xB05C0);
+        assert_grid_and_plane_agree(BOSCO.parse().unwrap(), 120, &cells, 10);
+    }
+}
@@ -0,0 +1,269 @@
+/*!
+How the cells of a grid are laid out, which decides who their neighbors are
+
+Every lattice is stored in the same columns and rows, only the neighbors change:
+- Square, the usual 8 around
+- Hexagonal, every odd row being shifted half a cell to the right so that
+  each cell touches 6 others, two above, two below and one on each side
+- Triangular, the cells of every row pointing up and down in turns
+  (the top left one up), each touching the 3 it shares an edge with
+
+Rules only count the live neighbors on anything but squares,
+so e.g. `B2/S34` is a hexagonal rule and `B3/S23` on triangles is born with 3 out of 3.
+Grids that go around their edges also need the right number of rows for the cells
+to line up, see [`Lattice::wraps`]
+*/
+
+use std::fmt;
+use std::str::FromStr;
+
+use super::{Life, Rule, Topology};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Lattice {
+    #[default]
+    Square,
+    Hexagonal,
+    Triangular,
+}
+
+/// Also used by the steppers of rules that only ever run on squares
+pub(super) const SQUARE_NEIGHBORS: [[i64; 2]; 8] = [
+    [-1, -1],
+    [0, -1],
+    [1, -1],
+    [-1, 0],
+    [1, 0],
+    [-1, 1],
+    [0, 1],
+    [1, 1],
+];
+const EVEN_ROW_NEIGHBORS: [[i64; 2]; 6] = [[-1, -1], [0, -1], [-1, 0], [1, 0], [-1, 1], [0, 1]];
+const ODD_ROW_NEIGHBORS: [[i64; 2]; 6] = [[0, -1], [1, -1], [-1, 0], [1, 0], [0, 1], [1, 1]];
+const UP_NEIGHBORS: [[i64; 2]; 3] = [[-1, 0], [1, 0], [0, 1]];
+const DOWN_NEIGHBORS: [[i64; 2]; 3] = [[0, -1], [-1, 0], [1, 0]];
+
+impl Lattice {
+    pub const ALL: [Lattice; 3] = [Self::Square, Self::Hexagonal, Self::Triangular];
+
+    /// A name for showing to people
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Square => "Squares",
+            Self::Hexagonal => "Hexagons",
+            Self::Triangular => "Triangles",
+        }
+    }
+
+    /// Where the neighbors of the cell at `column`, `row` are, relative to it
+    pub fn neighbors(self, column: i64, row: i64) -> &'static [[i64; 2]] {
+        match self {
+            Self::Square => &SQUARE_NEIGHBORS,
+            Self::Hexagonal if row.rem_euclid(2) == 0 => &EVEN_ROW_NEIGHBORS,
+            Self::Hexagonal => &ODD_ROW_NEIGHBORS,
+            Self::Triangular if Self::points_up(column, row) => &UP_NEIGHBORS,
+            Self::Triangular => &DOWN_NEIGHBORS,
+        }
+    }
+
+    /// Anything but squares only works with rules that just count the neighbors
+    pub fn supports(self, rule: &Rule) -> bool {
+        self == Self::Square || rule.is_totalistic()
+    }
+
+    /// Whether the cells of a grid of `columns` by `rows` still line up when going
+    /// around its edges, otherwise some would be neighbors of cells that aren't theirs
+    pub fn wraps(self, topology: Topology, columns: usize, rows: usize) -> bool {
+        match (self, topology) {
+            (Self::Square, _) | (_, Topology::Dead | Topology::Reflective) => true,
+            // The rows take turns, so they have to come back around in the same turn
+            (_, Topology::Torus) => rows.is_multiple_of(2),
+            // Flipping a row of hexagons shifts it the other way, like the next row is
+            (Self::Hexagonal, _) => !rows.is_multiple_of(2),
+            // Flipping a row of triangles has to turn them the way the other side is
+            (Self::Triangular, _) => !(columns + rows).is_multiple_of(2),
+        }
+    }
+
+    /// Whether the triangle at `column`, `row` of a triangular lattice points up
+    pub fn points_up(column: i64, row: i64) -> bool {
+        (column + row).rem_euclid(2) == 0
+    }
+}
+
+impl fmt::Display for Lattice {
+    /// The name used in files
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Square => "square",
+            Self::Hexagonal => "hexagonal",
+            Self::Triangular => "triangular",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for Lattice {
+    type Err = ();
+
+    fn from_str(name: &str) -> Result<Self, ()> {
+        Self::ALL
+            .into_iter()
+            .find(|lattice| lattice.to_string() == name)
+            .ok_or(())
+    }
+}
+
+impl Life {
+    /// [`Life::step`] for anything but squares, going through the neighbors of every cell
+    pub(super) fn step_lattice(&mut self) {
+        self.step_cells(|life, [x, y], state| {
+            life.rule.next_state(state, || {
+                let neighbors = life
+                    .lattice
+                    .neighbors(x, y)
+                    .iter()
+                    .filter(|[offset_x, offset_y]| life.state_at(x + offset_x, y + offset_y) == 1)
+                    .count();
+                life.rule.next(state == 1, neighbors as u8)
+            })
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::life::tests::alive_cells;
+    use crate::{BlockRule, RuleError, Topology};
+
+    #[test]
+    fn neighbors_go_both_ways() {
+        for lattice in Lattice::ALL {
+            for (x, y) in [(0, 0), (1, 0), (0, 1), (3, 3), (-1, -2)] {
+                for [offset_x, offset_y] in lattice.neighbors(x, y) {
+                    let back = lattice.neighbors(x + offset_x, y + offset_y);
+                    assert!(back.contains(&[-offset_x, -offset_y]), "{:?}", lattice);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn wrapping_keeps_neighbors_both_ways() {
+        let both_ways = |lattice: Lattice, topology: Topology, columns: usize, rows: usize| {
+            let locate = |x, y| topology.locate(x, y, columns, rows);
+            (0..rows as i64).all(|y| {
+                (0..columns as i64).all(|x| {
+                    lattice.neighbors(x, y).iter().all(|[offset_x, offset_y]| {
+                        let Some((column, row)) = locate(x + offset_x, y + offset_y) else {
+                            return true;
+                        };
+                        let (column, row) = (column as i64, row as i64);
+                        lattice
+                            .neighbors(column, row)
+                            .iter()
+                            .any(|[back_x, back_y]| {
+                                locate(column + back_x, row + back_y)
+                                    == Some((x as usize, y as usize))
+                            })
+                    })
+                })
+            })
+        };
+
+        for lattice in Lattice::ALL {
+            for topology in [
+                Topology::Torus,
+                Topology::KleinBottle,
+                Topology::CrossSurface,
+            ] {
+                let mut wrapping = 0;
+                for columns in 3..10 {
+                    for rows in 3..10 {
+                        if lattice.wraps(topology, columns, rows) {
+                            wrapping += 1;
+                            assert!(
+                                both_ways(lattice, topology, columns, rows),
+                                "{:?} on a {} of {}x{}",
+                                lattice,
+                                topology,
+                                columns,
+                                rows
+                            );
+                        }
+                    }
+                }
+                assert!(wrapping > 0);
+            }
+        }
+
+        let mut life = Life::new(6, 5);
+        life.set_topology(Topology::Torus);
+        assert_eq!(
+            life.set_lattice(Lattice::Hexagonal),
+            Err(RuleError::LatticeDoesNotWrap)
+        );
+    }
+
+    #[test]
+    fn hexagons_touch_two_above_and_below() {
+        let mut life = Life::new(7, 7);
+        life.set_lattice(Lattice::Hexagonal).unwrap();
+        life.set_rule("B2/S2".parse().unwrap()).unwrap();
+        for column in 2..5 {
+            life.set(column, 3, true);
+        }
+
+        // Row 3 is shifted to the right, so the cells above and below it
+        // touching two of the line are the ones at columns 3 and 4
+        life.step();
+        assert_eq!(
+            alive_cells(&life),
+            vec![(3, 2), (4, 2), (3, 3), (3, 4), (4, 4)]
+        );
+    }
+
+    #[test]
+    fn triangles_only_see_their_edges() {
+        let mut life = Life::new(6, 4);
+        life.set_lattice(Lattice::Triangular).unwrap();
+        life.set_topology(Topology::Torus);
+        life.set_rule("B1/S".parse().unwrap()).unwrap();
+        life.set(2, 2, true);
+
+        // (2, 2) points up, so it touches the ones on its sides and the one below
+        life.step();
+        assert_eq!(alive_cells(&life), vec![(1, 2), (3, 2), (2, 3)]);
+    }
+
+    #[test]
+    fn only_counting_rules_on_other_lattices() {
+        let mut life = Life::new(6, 6);
+        life.set_lattice(Lattice::Hexagonal).unwrap();
+        for rulestring in ["R2,C0,M0,S2..3,B3..3,NM", "B2n3/S23-q", "B3:50/S23"] {
+            assert_eq!(
+                life.set_rule(rulestring.parse().unwrap()),
+                Err(RuleError::NeighborhoodUnsupported)
+            );
+        }
+        assert_eq!(life.rule(), Rule::CONWAY);
+
+        let mut life = Life::new(6, 6);
+        life.set_rule(Rule::from_block(BlockRule::CRITTERS))
+            .unwrap();
+        assert_eq!(
+            life.set_lattice(Lattice::Triangular),
+            Err(RuleError::NeighborhoodUnsupported)
+        );
+        assert_eq!(life.lattice(), Lattice::Square);
+    }
+
+    #[test]
+    fn names_parse_back() {
+        for lattice in Lattice::ALL {
+            assert_eq!(lattice.to_string().parse(), Ok(lattice));
+        }
+        assert_eq!("pentagonal".parse::<Lattice>(), Err(()));
+    }
+}
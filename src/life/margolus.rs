@@ -0,0 +1,225 @@
+/*!
+Block cellular automata on the Margolus neighborhood, where instead of every cell
+looking at its neighbors the plane is split into 2x2 blocks and every block
+turns into another one as a whole
+
+The blocks start at even columns and rows on even generations and at odd ones
+on odd generations, so that what happens in one block spreads to the ones around
+the next generation. The rule says what each of the 16 blocks turns into, with bit 0
+being the top left cell, bit 1 the top right, bit 2 the bottom left and bit 3 the bottom right.
+
+They are written like MCell and Golly do, e.g. `MS,D0;8;4;3;2;5;9;7;1;6;10;11;12;13;14;15`
+for the billiard ball machine, with what each block turns into from 0 to 15.
+Grids with an odd size that wrap around have one row or column of blocks that
+don't quite fit, they see the cells across the edges but those only change with their own block
+*/
+
+use std::fmt;
+
+use super::{Life, RuleError};
+
+/// The cells of a block relative to its top left corner, in the order of their bits
+pub(super) const BLOCK: [[i64; 2]; 4] = [[0, 0], [1, 0], [0, 1], [1, 1]];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct BlockRule {
+    /// What every block turns into
+    table: [u8; 16],
+}
+
+impl BlockRule {
+    /// Blocks with two cells stay as they are, the rest are turned inside out,
+    /// and the ones with three cells also turned around
+    pub const CRITTERS: BlockRule =
+        BlockRule::new([15, 14, 13, 3, 11, 5, 6, 1, 7, 9, 10, 2, 12, 4, 8, 0]);
+
+    /// Only empty and full blocks change, into each other
+    pub const TRON: BlockRule =
+        BlockRule::new([15, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 0]);
+
+    /// Lone cells go to the opposite corner, and two on a diagonal
+    /// bounce off each other into the other one
+    pub const BILLIARD_BALLS: BlockRule =
+        BlockRule::new([0, 8, 4, 3, 2, 5, 9, 7, 1, 6, 10, 11, 12, 13, 14, 15]);
+
+    /// Anything past the 4 bits of a block is ignored
+    pub const fn new(table: [u8; 16]) -> Self {
+        let mut masked = [0; 16];
+        let mut block = 0;
+        while block < 16 {
+            masked[block] = table[block] & 0xF;
+            block += 1;
+        }
+        Self { table: masked }
+    }
+
+    /// What the `block` turns into
+    pub fn next(&self, block: u8) -> u8 {
+        self.table[block as usize & 0xF]
+    }
+
+    /// Empty blocks turning into something
+    pub fn has_b0(&self) -> bool {
+        self.next(0) != 0
+    }
+
+    /// How far the blocks of `generation` are from starting at 0, 0,
+    /// both to the right and downwards
+    pub fn offset(generation: u64) -> i64 {
+        (generation % 2) as i64
+    }
+
+    /// The top left corner of the block `x`, `y` is in for `generation`
+    pub fn corner(x: i64, y: i64, generation: u64) -> [i64; 2] {
+        let offset = Self::offset(generation);
+        [
+            x - (x - offset).rem_euclid(2),
+            y - (y - offset).rem_euclid(2),
+        ]
+    }
+
+    /// The state of the cell at `x`, `y` after its block turns into another,
+    /// `alive` saying which cells are alive before
+    pub(super) fn next_cell(
+        &self,
+        [x, y]: [i64; 2],
+        generation: u64,
+        alive: impl Fn(i64, i64) -> bool,
+    ) -> u8 {
+        let [corner_x, corner_y] = Self::corner(x, y, generation);
+        let before = BLOCK
+            .iter()
+            .enumerate()
+            .filter(|(_, [offset_x, offset_y])| alive(corner_x + offset_x, corner_y + offset_y))
+            .fold(0, |before, (bit, _)| before | 1 << bit);
+
+        let bit = 2 * (y - corner_y) + x - corner_x;
+        self.next(before) >> bit & 1
+    }
+
+    /// Takes `MS,D` followed by 16 numbers split by semicolons, in any case
+    pub(super) fn parse(rulestring: &str) -> Result<Self, RuleError> {
+        let numbers = rulestring
+            .get(..4)
+            .filter(|prefix| prefix.eq_ignore_ascii_case("MS,D"))
+            .map(|_| &rulestring[4..])
+            .ok_or(RuleError::InvalidFormat)?;
+
+        let table = numbers
+            .split(';')
+            .map(|number| match number.trim().parse() {
+                Ok(block @ 0..=15) => Ok(block),
+                _ => Err(RuleError::InvalidFormat),
+            })
+            .collect::<Result<Vec<u8>, _>>()?;
+
+        Ok(Self::new(
+            table.try_into().map_err(|_| RuleError::InvalidFormat)?,
+        ))
+    }
+}
+
+impl fmt::Display for BlockRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let table: Vec<String> = self.table.iter().map(u8::to_string).collect();
+        write!(f, "MS,D{}", table.join(";"))
+    }
+}
+
+impl Life {
+    /// [`Life::step`] for block rules, every cell going with the block it is in
+    pub(super) fn step_block(&mut self, block: BlockRule) {
+        self.step_cells(|life, cell, _| {
+            block.next_cell(cell, life.generation, |x, y| life.state_at(x, y) == 1)
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::life::tests::alive_cells;
+    use crate::{Engine, Rule, SparseLife, Topology};
+
+    #[test]
+    fn critters_are_what_they_say() {
+        for block in 0..16u8 {
+            let expected = match block.count_ones() {
+                2 => block,
+                // Turned around is the same as going through the bits backwards
+                3 => (!block & 0xF).reverse_bits() >> 4,
+                _ => !block & 0xF,
+            };
+            assert_eq!(BlockRule::CRITTERS.next(block), expected, "{}", block);
+        }
+    }
+
+    #[test]
+    fn parses_and_prints_back() {
+        let rulestring = "MS,D0;8;4;3;2;5;9;7;1;6;10;11;12;13;14;15";
+        let rule: Rule = rulestring.parse().unwrap();
+        assert_eq!(rule.block(), Some(BlockRule::BILLIARD_BALLS));
+        assert_eq!(rule.to_string(), rulestring);
+        assert_eq!(
+            "ms,d0;8;4;3;2;5;9;7;1;6;10;11;12;13;14;15".parse(),
+            Ok(rule)
+        );
+
+        assert_eq!(
+            "MS,D0;8;4;3;2;5;9;7;1;6;10;11;12;13;14".parse::<Rule>(),
+            Err(RuleError::InvalidFormat)
+        );
+        assert_eq!(
+            "MS,D0;8;4;3;2;5;9;7;1;6;10;11;12;13;14;16".parse::<Rule>(),
+            Err(RuleError::InvalidFormat)
+        );
+    }
+
+    #[test]
+    fn balls_go_diagonally() {
+        let rule = Rule::from_block(BlockRule::BILLIARD_BALLS);
+
+        let mut life = Life::new(8, 8);
+        life.set_rule(rule.clone()).unwrap();
+        life.set(2, 2, true);
+
+        // The top left of the block at 2, 2 goes to its bottom right,
+        // which is then the top left of a block starting at odd columns and rows
+        life.step();
+        assert_eq!(alive_cells(&life), vec![(3, 3)]);
+        life.step();
+        assert_eq!(alive_cells(&life), vec![(4, 4)]);
+        assert_eq!(life.births_and_deaths(), Some([1, 1]));
+
+        let mut sparse = SparseLife::new();
+        sparse.set_rule(rule).unwrap();
+        sparse.set_cell(-2, -2, true);
+        sparse.step_n(3);
+        assert_eq!(sparse.live_cells(), vec![[1, 1]]);
+    }
+
+    #[test]
+    fn tron_wraps_around() {
+        let mut life = Life::new(4, 4);
+        life.set_rule(Rule::from_block(BlockRule::TRON)).unwrap();
+        life.set_topology(Topology::Torus);
+
+        // Every block is empty and fills up, and then the ones
+        // across the edges are full and empty out again
+        life.step();
+        assert_eq!(life.population(), 16);
+        life.step();
+        assert_eq!(life.population(), 0);
+
+        // Without wrapping around the blocks across the edges only have
+        // some of their cells, and those are still not full
+        life.set_topology(Topology::Dead);
+        life.step_n(2);
+        assert_eq!(life.population(), 12);
+
+        assert_eq!(
+            SparseLife::new().set_rule(Rule::from_block(BlockRule::TRON)),
+            Err(RuleError::B0OnInfinitePlane)
+        );
+    }
+}
@@ -0,0 +1,592 @@
+/*!
+The actual Game of Life, as in the thing that computes
+the next generation out of the current one
+
+[`Life`] is the plain stepper for a grid of a fixed size,
+what is past the edges of the grid depends on its [`Topology`].
+[`SparseLife`] has no borders and only stores the chunks of the plane
+where something is going on, and [`HashLife`] has no borders either
+and is meant for enormous and/or long-running patterns.
+All of them are behind the [`Engine`] trait,
+and any of them can be stepped through a [`History`] to be able to go back
+*/
+
+mod age;
+pub use age::*;
+
+mod bitgrid;
+pub use bitgrid::*;
+
+mod census;
+pub use census::*;
+
+mod cycle;
+pub use cycle::*;
+
+mod engine;
+pub use engine::*;
+
+mod generations;
+pub use generations::*;
+
+mod hashlife;
+pub use hashlife::*;
+
+mod history;
+pub use history::*;
+
+mod isotropic;
+pub use isotropic::*;
+
+mod jump;
+pub use jump::*;
+
+mod lattice;
+pub use lattice::*;
+
+mod margolus;
+pub use margolus::*;
+
+mod larger;
+pub use larger::*;
+
+mod rule;
+pub use rule::*;
+
+mod search;
+pub use search::*;
+
+mod soup;
+pub use soup::*;
+
+mod sparse;
+pub use sparse::*;
+
+mod stats;
+pub use stats::*;
+
+mod stochastic;
+pub use stochastic::*;
+
+mod table;
+pub use table::*;
+
+mod topology;
+pub use topology::*;
+
+mod universe;
+pub use universe::*;
+
+use std::time::Duration;
+
+/// Never try to catch up on more than this many seconds worth of updates,
+/// otherwise a long hiccup (e.g. the window being dragged around)
+/// would make the simulation jump ahead all of a sudden
+const MAX_CATCH_UP_SECS: f64 = 1.0;
+
+#[derive(Debug, Clone)]
+pub struct Life {
+    cells: BitGrid,
+    generation: u64,
+    /// How many threads stepping can use
+    threads: usize,
+    rule: Rule,
+    topology: Topology,
+    lattice: Lattice,
+    /// How many cells were born and died in the last step
+    births_and_deaths: Option<[u64; 2]>,
+    /// Always empty unless the rule is from the Generations family
+    dying: DyingSquares,
+}
+
+impl Life {
+    pub fn new(columns: usize, rows: usize) -> Self {
+        Self {
+            cells: BitGrid::new(columns, rows),
+            generation: 0,
+            threads: std::thread::available_parallelism().map_or(1, |threads| threads.get()),
+            rule: Rule::default(),
+            topology: Topology::default(),
+            lattice: Lattice::default(),
+            births_and_deaths: None,
+            dying: DyingSquares::new(columns, rows),
+        }
+    }
+
+    pub fn threads(&self) -> usize {
+        self.threads
+    }
+
+    /// Anything below one is taken as one
+    pub fn set_threads(&mut self, threads: usize) {
+        self.threads = threads.max(1);
+    }
+
+    pub fn rule(&self) -> Rule {
+        self.rule.clone()
+    }
+
+    /// Dying cells in states the new rule does not have are dropped.
+    /// Fails if the lattice doesn't [support](Lattice::supports) the rule
+    pub fn set_rule(&mut self, rule: Rule) -> Result<(), RuleError> {
+        if !self.lattice.supports(&rule) {
+            return Err(RuleError::NeighborhoodUnsupported);
+        }
+
+        self.dying.fit_rule(&rule);
+        self.rule = rule;
+        Ok(())
+    }
+
+    /// What is past the edges of the grid
+    pub fn topology(&self) -> Topology {
+        self.topology
+    }
+
+    pub fn set_topology(&mut self, topology: Topology) {
+        self.topology = topology;
+    }
+
+    /// The shape of the cells, which decides who their neighbors are
+    pub fn lattice(&self) -> Lattice {
+        self.lattice
+    }
+
+    /// Fails if the lattice doesn't [support](Lattice::supports) the rule
+    /// or doesn't [wrap](Lattice::wraps) around the edges of the grid
+    pub fn set_lattice(&mut self, lattice: Lattice) -> Result<(), RuleError> {
+        if !lattice.supports(&self.rule) {
+            return Err(RuleError::NeighborhoodUnsupported);
+        }
+        if !lattice.wraps(self.topology, self.columns(), self.rows()) {
+            return Err(RuleError::LatticeDoesNotWrap);
+        }
+
+        self.lattice = lattice;
+        Ok(())
+    }
+
+    pub fn columns(&self) -> usize {
+        self.cells.columns()
+    }
+
+    pub fn rows(&self) -> usize {
+        self.cells.rows()
+    }
+
+    /// How many generations have been computed since the grid was created
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    pub fn set_generation(&mut self, generation: u64) {
+        self.generation = generation;
+    }
+
+    /// Whether the cell is alive, dying cells are not
+    pub fn get(&self, column: usize, row: usize) -> bool {
+        self.cells.get(column, row)
+    }
+
+    /// A dying cell stops dying either way
+    pub fn set(&mut self, column: usize, row: usize, alive: bool) {
+        self.cells.set(column, row, alive);
+        self.dying.set(column as i64, row as i64, 0);
+    }
+
+    /// Dying cells are taken as dead
+    pub fn toggle(&mut self, column: usize, row: usize) {
+        self.cells.toggle(column, row);
+        self.dying.set(column as i64, row as i64, 0);
+    }
+
+    /// 0 for dead, 1 for alive and from 2 up for dying
+    pub fn state(&self, column: usize, row: usize) -> u8 {
+        if self.get(column, row) {
+            1
+        } else {
+            self.dying.get(column as i64, row as i64).unwrap_or(0)
+        }
+    }
+
+    /// States the rule does not have are taken as dead
+    pub fn set_state(&mut self, column: usize, row: usize, state: u8) {
+        self.set(column, row, state == 1);
+        if state < self.rule.states() {
+            self.dying.set(column as i64, row as i64, state);
+        }
+    }
+
+    pub fn dying(&self) -> &DyingSquares {
+        &self.dying
+    }
+
+    /// Kills every cell and resets the generation count
+    pub fn clear(&mut self) {
+        self.cells.clear();
+        self.dying.clear();
+        self.generation = 0;
+        self.births_and_deaths = None;
+    }
+
+    /// Changes the dimensions of the grid,
+    /// the cells that still fit in it are kept as they were
+    pub fn resize(&mut self, columns: usize, rows: usize) {
+        self.cells.resize(columns, rows);
+        self.dying.resize(columns, rows);
+    }
+
+    pub fn cells(&self) -> &BitGrid {
+        &self.cells
+    }
+
+    /// Iterates over the rows from top to bottom
+    pub fn iter_rows(&self) -> impl Iterator<Item = impl Iterator<Item = bool> + '_> + '_ {
+        self.cells.iter_rows()
+    }
+
+    /// Same as [`Life::iter_rows`] but with the [state](Life::state) of every cell
+    pub fn iter_state_rows(&self) -> impl Iterator<Item = impl Iterator<Item = u8> + '_> + '_ {
+        (0..self.rows())
+            .map(move |row| (0..self.columns()).map(move |column| self.state(column, row)))
+    }
+
+    pub fn population(&self) -> usize {
+        self.cells.population()
+    }
+
+    /// How many cells were born and how many died going into the current generation
+    pub fn births_and_deaths(&self) -> Option<[u64; 2]> {
+        self.births_and_deaths
+    }
+
+    /// Computes the next generation following the rule, B3/S23 by default:
+    /// - A dead cell with exactly three live neighbors is born
+    /// - A live cell with two or three live neighbors survives
+    /// - Every other cell dies or stays dead
+    ///
+    /// With Generations rules dying cells can't be born, and live cells
+    /// that don't survive start dying
+    pub fn step(&mut self) {
+        // Cloned so that a table can be looked at while the cells change
+        match self.rule.kind().clone() {
+            RuleKind::Table(table) => self.step_table(&table),
+            RuleKind::Block(block) => self.step_block(block),
+            RuleKind::Stochastic(stochastic) => self.step_stochastic(stochastic),
+            RuleKind::Larger(larger) => self.step_larger(larger),
+            RuleKind::Isotropic(isotropic) => self.step_isotropic(isotropic),
+            RuleKind::Totalistic if self.lattice != Lattice::Square => self.step_lattice(),
+            RuleKind::Totalistic => {
+                let changes = if self.rule.is_generations() {
+                    self.dying
+                        .step_grid(&mut self.cells, self.threads, &self.rule, self.topology)
+                } else {
+                    self.cells
+                        .step_parallel(self.threads, &self.rule, self.topology)
+                };
+                self.births_and_deaths = Some(changes);
+                self.generation += 1;
+            }
+        }
+    }
+
+    /// The state of the cell at `x`, `y` wherever the topology puts it, dead if nowhere
+    fn state_at(&self, x: i64, y: i64) -> u8 {
+        self.topology
+            .locate(x, y, self.columns(), self.rows())
+            .map_or(0, |(column, row)| self.state(column, row))
+    }
+
+    /// Goes through every cell with `next` saying which state it goes to
+    /// out of the one it is in, and only changes them once that is known for all of them
+    fn step_cells(&mut self, mut next: impl FnMut(&Self, [i64; 2], u8) -> u8) {
+        let (columns, rows) = (self.columns() as i64, self.rows() as i64);
+        let cells = (0..rows).flat_map(|y| (0..columns).map(move |x| [x, y]));
+
+        let changes = changes(
+            cells,
+            |[x, y]| self.state(x as usize, y as usize),
+            |cell, state| next(self, cell, state),
+        );
+        for ([x, y], _, state) in &changes {
+            self.set_state(*x as usize, *y as usize, *state);
+        }
+        self.births_and_deaths = Some(births_and_deaths(&changes));
+        self.generation += 1;
+    }
+
+    pub fn step_n(&mut self, n: u64) {
+        for _ in 0..n {
+            self.step();
+        }
+    }
+}
+
+/// Every cell out of `cells` that `next` gives another state than the one `state` says
+/// it is in, along with both states
+fn changes(
+    cells: impl IntoIterator<Item = [i64; 2]>,
+    state: impl Fn([i64; 2]) -> u8,
+    mut next: impl FnMut([i64; 2], u8) -> u8,
+) -> Vec<([i64; 2], u8, u8)> {
+    cells
+        .into_iter()
+        .filter_map(|cell| {
+            let before = state(cell);
+            let after = next(cell, before);
+            (after != before).then_some((cell, before, after))
+        })
+        .collect()
+}
+
+/// How many cells became alive and how many stopped being alive
+fn births_and_deaths(changes: &[([i64; 2], u8, u8)]) -> [u64; 2] {
+    let births = changes.iter().filter(|(_, _, after)| *after == 1).count();
+    let deaths = changes.iter().filter(|(_, before, _)| *before == 1).count();
+    [births as u64, deaths as u64]
+}
+
+/// Decides how many generations should be computed
+/// given how much time has passed and the updates per second,
+/// unless it is jumping ahead in which case that is up to the [`Jump`]
+#[derive(Debug, Clone, Default)]
+pub struct UpdateTimer {
+    /// Time that was not enough for a whole update, in seconds
+    leftover: f64,
+    paused: bool,
+    jump: Option<Jump>,
+}
+
+impl UpdateTimer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn paused(&self) -> bool {
+        self.paused
+    }
+
+    pub fn set_paused(&mut self, paused: bool) {
+        self.paused = paused;
+        self.leftover = 0.0;
+    }
+
+    pub fn jump(&self) -> Option<&Jump> {
+        self.jump.as_ref()
+    }
+
+    pub fn jump_mut(&mut self) -> Option<&mut Jump> {
+        self.jump.as_mut()
+    }
+
+    /// Starting a jump pauses, so that it stays where the jump ended,
+    /// and `None` stops jumping
+    pub fn set_jump(&mut self, jump: Option<Jump>) {
+        if jump.is_some() {
+            self.set_paused(true);
+        }
+        self.jump = jump;
+    }
+
+    /// Returns how many generations are due after `elapsed` time, none while jumping
+    pub fn tick(&mut self, elapsed: Duration, updates_sec: f32) -> u64 {
+        if self.paused || self.jump.is_some() || updates_sec <= 0.0 {
+            return 0;
+        }
+
+        let interval = 1.0 / updates_sec as f64;
+
+        self.leftover =
+            (self.leftover + elapsed.as_secs_f64()).min(MAX_CATCH_UP_SECS.max(interval));
+
+        let updates = (self.leftover / interval).floor();
+        self.leftover -= updates * interval;
+
+        updates as u64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn with_cells(columns: usize, rows: usize, alive: &[(usize, usize)]) -> Life {
+        let mut life = Life::new(columns, rows);
+        for &(column, row) in alive {
+            life.set(column, row, true);
+        }
+        life
+    }
+
+    /// The column and row of every live cell, row by row
+    pub(super) fn alive_cells(life: &Life) -> Vec<(usize, usize)> {
+        let mut alive = Vec::new();
+        for row in 0..life.rows() {
+            for column in 0..life.columns() {
+                if life.get(column, row) {
+                    alive.push((column, row));
+                }
+            }
+        }
+        alive
+    }
+
+    /// A random soup in `[min_x, min_y, max_x, max_y]`, the same one for the same `seed`
+    pub(super) fn soup(region: [i64; 4], density: f64, seed: u64) -> Vec<[i64; 2]> {
+        let soup = Soup {
+            seed,
+            density,
+            symmetry: Symmetry::C1,
+        };
+        soup.cells(region)
+    }
+
+    /// Steps `cells` on a grid of `size` by `size` and on an infinite plane, which go
+    /// the same way as long as they stay far enough from the borders of the grid
+    pub(super) fn assert_grid_and_plane_agree(rule: Rule, size: usize, cells: &[[i64; 2]], n: u64) {
+        let mut life = Life::new(size, size);
+        life.set_rule(rule.clone()).unwrap();
+        let mut sparse = SparseLife::new();
+        sparse.set_rule(rule).unwrap();
+        for &[x, y] in cells {
+            Engine::set_cell(&mut life, x, y, true);
+            sparse.set_cell(x, y, true);
+        }
+
+        fn sorted<T: Ord>(mut cells: Vec<T>) -> Vec<T> {
+            cells.sort_unstable();
+            cells
+        }
+        for _ in 0..n {
+            life.step();
+            sparse.step();
+
+            assert_eq!(
+                sorted(Engine::live_cells(&life)),
+                sorted(sparse.live_cells())
+            );
+            assert_eq!(
+                sorted(Engine::dying_cells(&life)),
+                sorted(sparse.dying_cells())
+            );
+            assert_eq!(Engine::births_and_deaths(&life), sparse.births_and_deaths());
+        }
+    }
+
+    #[test]
+    fn block_is_still() {
+        let mut life = with_cells(4, 4, &[(1, 1), (2, 1), (1, 2), (2, 2)]);
+        life.step();
+        assert_eq!(alive_cells(&life), vec![(1, 1), (2, 1), (1, 2), (2, 2)]);
+    }
+
+    #[test]
+    fn blinker_oscillates() {
+        let mut life = with_cells(5, 5, &[(1, 2), (2, 2), (3, 2)]);
+
+        life.step();
+        assert_eq!(alive_cells(&life), vec![(2, 1), (2, 2), (2, 3)]);
+
+        life.step();
+        assert_eq!(alive_cells(&life), vec![(1, 2), (2, 2), (3, 2)]);
+        assert_eq!(life.generation(), 2);
+    }
+
+    #[test]
+    fn glider_moves_diagonally() {
+        let mut life = with_cells(8, 8, &[(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)]);
+
+        life.step_n(4);
+
+        assert_eq!(
+            alive_cells(&life),
+            vec![(2, 1), (3, 2), (1, 3), (2, 3), (3, 3)]
+        );
+        assert_eq!(life.generation(), 4);
+    }
+
+    #[test]
+    fn border_cells_are_dead() {
+        // A blinker cut in half by the edge dies out
+        let mut life = with_cells(3, 3, &[(0, 0), (1, 0)]);
+        life.step();
+        assert_eq!(life.population(), 0);
+    }
+
+    #[test]
+    fn b0_fills_the_grid_but_not_past_it() {
+        let mut life = Life::new(70, 3);
+        life.set_rule("B0/S".parse().unwrap()).unwrap();
+
+        life.step();
+        assert_eq!(life.population(), 70 * 3);
+
+        life.step();
+        assert_eq!(life.population(), 0);
+    }
+
+    #[test]
+    fn blinker_wraps_around_a_torus() {
+        let mut life = with_cells(3, 5, &[(0, 0), (0, 1), (0, 4)]);
+        life.set_topology(Topology::Torus);
+
+        life.step();
+        assert_eq!(alive_cells(&life), vec![(0, 0), (1, 0), (2, 0)]);
+    }
+
+    #[test]
+    fn brians_brain_dies_slowly() {
+        let mut life = with_cells(4, 4, &[(0, 1), (2, 1)]);
+        life.set_rule("B2/S/C3".parse().unwrap()).unwrap();
+        life.set_state(1, 1, 2);
+
+        // The dying one in the middle has two live neighbors but can't be born
+        life.step();
+        assert_eq!(alive_cells(&life), vec![(1, 0), (1, 2)]);
+        assert_eq!(life.state(1, 1), 0);
+        assert_eq!([life.state(0, 1), life.state(2, 1)], [2, 2]);
+        assert_eq!(life.births_and_deaths(), Some([2, 2]));
+
+        // Going back to Life-like drops the dying cells
+        life.set_rule(Rule::CONWAY).unwrap();
+        assert!(life.dying().is_empty());
+    }
+
+    #[test]
+    fn resize_keeps_cells() {
+        let mut life = with_cells(3, 3, &[(0, 0), (2, 2)]);
+        life.resize(5, 2);
+        assert_eq!(alive_cells(&life), vec![(0, 0)]);
+        assert_eq!((life.columns(), life.rows()), (5, 2));
+    }
+
+    #[test]
+    fn timer_follows_updates_sec() {
+        let mut timer = UpdateTimer::new();
+
+        assert_eq!(timer.tick(Duration::from_millis(250), 2.0), 0);
+        assert_eq!(timer.tick(Duration::from_millis(250), 2.0), 1);
+        assert_eq!(timer.tick(Duration::from_millis(500), 10.0), 5);
+
+        timer.set_paused(true);
+        assert_eq!(timer.tick(Duration::from_secs(3), 10.0), 0);
+    }
+
+    #[test]
+    fn timer_does_not_catch_up_forever() {
+        let mut timer = UpdateTimer::new();
+        assert_eq!(timer.tick(Duration::from_secs(60), 10.0), 10);
+    }
+
+    #[test]
+    fn timer_waits_for_jumps() {
+        let mut timer = UpdateTimer::new();
+        timer.set_jump(Some(Jump::by(0, 100)));
+        assert!(timer.paused());
+        assert_eq!(timer.tick(Duration::from_secs(1), 10.0), 0);
+
+        timer.set_jump(None);
+        timer.set_paused(false);
+        assert_eq!(timer.tick(Duration::from_secs(1), 10.0), 10);
+    }
+}
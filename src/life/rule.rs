@@ -0,0 +1,596 @@
+/*!
+Life-like rules, the ones that only care about how many
+of the 8 neighbors of a cell are alive
+
+They are written in the usual rulestring notation, either `B3/S23`
+(birth and survival) or the older `23/3` (survival and then birth)
+
+Rules of the Generations family have more than two states, a live cell that
+does not survive goes through the dying states one generation at a time
+before it is dead, and dying cells neither count as neighbors nor can be born.
+They get a third part with how many states there are, e.g. `B2/S/C3`
+(Brian's Brain) or the older `/2/3`
+
+Rules can also be [isotropic non-totalistic](super::Isotropic) ones, with letters
+for the shapes the neighbors make (e.g. `B2-a/S12`), or [Larger than Life](super::LargerThanLife)
+ones, which are written in their own way.
+[Rule tables](super::RuleTable) from Golly's `.rule` files have no rulestring, only a name,
+and [block rules](super::BlockRule) start with `MS,D`.
+[Stochastic](super::Stochastic) ones have chances for their counts, like `B3/S2:99,3:99@42`
+*/
+
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use super::{
+    isotropic, BlockRule, Isotropic, LargerThanLife, Neighborhood, RuleTable, Stochastic, MIDDLE,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Rule {
+    /// Bit `n` is set if a dead cell with `n` live neighbors is born
+    birth: u16,
+    /// Bit `n` is set if a live cell with `n` live neighbors survives
+    survival: u16,
+    /// Dead and alive plus however many dying states there are
+    states: u8,
+    kind: RuleKind,
+}
+
+/// What decides the next state of a cell, everything but [`RuleKind::Totalistic`]
+/// replaces the masks
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum RuleKind {
+    /// Only how many of the usual 8 neighbors are alive matters
+    Totalistic,
+    /// Bigger neighborhoods
+    Larger(LargerThanLife),
+    /// Where the neighbors are matters too
+    Isotropic(Isotropic),
+    /// Every transition is listed, rules made out of the same table share it
+    Table(Arc<RuleTable>),
+    /// 2x2 blocks turn into others
+    Block(BlockRule),
+    /// Being born and surviving are up to chance
+    Stochastic(Stochastic),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RuleError {
+    /// The rulestring is not in B/S nor S/B notation
+    InvalidFormat,
+    /// Something that is not a neighbor count (0 to 8) where one was expected
+    InvalidNeighborCount(char),
+    /// A letter that is not one of the shapes of the neighbor count before it
+    InvalidLetter(char),
+    /// Rules with B0 make infinitely many cells alive in an infinite plane
+    B0OnInfinitePlane,
+    /// Less than two states, or more than fit in a byte
+    InvalidStateCount,
+    /// The engine can only run rules with two states
+    MultiStateUnsupported,
+    /// A Larger than Life range that is zero or too big for its neighborhood
+    InvalidRange,
+    /// The engine can only run rules with the usual 8 neighbors
+    NeighborhoodUnsupported,
+    /// The engine can't run rule tables
+    TableUnsupported,
+    /// The engine can only run rules that always do the same with the same neighbors
+    StochasticUnsupported,
+    /// The cells don't line up with each other around the edges of a grid that size
+    LatticeDoesNotWrap,
+}
+
+impl fmt::Display for RuleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidFormat => write!(f, "expected something like B3/S23 or 23/3"),
+            Self::InvalidNeighborCount(character) => {
+                write!(f, "'{}' is not a neighbor count", character)
+            }
+            Self::InvalidLetter(letter) => {
+                write!(f, "'{}' is not a letter for that many neighbors", letter)
+            }
+            Self::B0OnInfinitePlane => write!(f, "B0 rules need a grid with borders"),
+            Self::InvalidStateCount => write!(f, "the states have to be between 2 and 255"),
+            Self::MultiStateUnsupported => write!(f, "only rules with two states work here"),
+            Self::InvalidRange => write!(
+                f,
+                "the range has to be between 1 and {} ({} for custom neighborhoods)",
+                super::MAX_RANGE,
+                super::MAX_CUSTOM_RANGE
+            ),
+            Self::NeighborhoodUnsupported => {
+                write!(f, "only rules with the usual 8 neighbors work here")
+            }
+            Self::TableUnsupported => write!(f, "rule tables don't work here"),
+            Self::StochasticUnsupported => write!(f, "rules with chances don't work here"),
+            Self::LatticeDoesNotWrap => write!(
+                f,
+                "those cells don't line up around the edges, try one more or one less row"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for RuleError {}
+
+/// Some well known rules along with their names
+pub const RULE_PRESETS: [(&str, Rule); 13] = [
+    ("Conway's Life", Rule::CONWAY),
+    ("HighLife", Rule::from_masks(0b100_1000, 0b1100)),
+    (
+        "Day & Night",
+        Rule::from_masks(0b1_1100_1000, 0b1_1101_1000),
+    ),
+    ("Seeds", Rule::from_masks(0b100, 0)),
+    (
+        "Life without Death",
+        Rule::from_masks(0b1000, 0b1_1111_1111),
+    ),
+    ("Replicator", Rule::from_masks(0b1010_1010, 0b1010_1010)),
+    ("Brian's Brain", Rule::from_masks(0b100, 0).with_states(3)),
+    (
+        "Star Wars",
+        Rule::from_masks(0b100, 0b11_1000).with_states(4),
+    ),
+    (
+        "Bosco's Rule",
+        Rule::from_larger_than_life(LargerThanLife {
+            range: 5,
+            neighborhood: Neighborhood::Moore,
+            middle: true,
+            birth: [34, 45],
+            survival: [34, 58],
+        }),
+    ),
+    (
+        "Majority",
+        Rule::from_larger_than_life(LargerThanLife {
+            range: 4,
+            neighborhood: Neighborhood::Moore,
+            middle: true,
+            birth: [41, 81],
+            survival: [41, 81],
+        }),
+    ),
+    ("Critters", Rule::from_block(BlockRule::CRITTERS)),
+    ("Tron", Rule::from_block(BlockRule::TRON)),
+    (
+        "Billiard Balls",
+        Rule::from_block(BlockRule::BILLIARD_BALLS),
+    ),
+];
+
+impl Default for Rule {
+    fn default() -> Self {
+        Self::CONWAY
+    }
+}
+
+impl Rule {
+    /// B3/S23
+    pub const CONWAY: Rule = Rule::from_masks(0b1000, 0b1100);
+
+    /// Bit `n` of the masks says what happens with `n` live neighbors,
+    /// anything past bit 8 is ignored
+    pub const fn from_masks(birth: u16, survival: u16) -> Self {
+        Self {
+            birth: birth & 0x1FF,
+            survival: survival & 0x1FF,
+            states: 2,
+            kind: RuleKind::Totalistic,
+        }
+    }
+
+    /// Has to be [valid](LargerThanLife::validate)
+    pub const fn from_larger_than_life(larger: LargerThanLife) -> Self {
+        Self {
+            birth: 0,
+            survival: 0,
+            states: 2,
+            kind: RuleKind::Larger(larger),
+        }
+    }
+
+    pub const fn from_isotropic(isotropic: Isotropic) -> Self {
+        Self {
+            birth: 0,
+            survival: 0,
+            states: 2,
+            kind: RuleKind::Isotropic(isotropic),
+        }
+    }
+
+    pub fn from_table(table: RuleTable) -> Self {
+        Self {
+            birth: 0,
+            survival: 0,
+            states: table.states(),
+            kind: RuleKind::Table(Arc::new(table)),
+        }
+    }
+
+    /// `None` for rules with the usual 8 neighbors
+    pub fn larger_than_life(&self) -> Option<LargerThanLife> {
+        match self.kind {
+            RuleKind::Larger(larger) => Some(larger),
+            _ => None,
+        }
+    }
+
+    /// `None` for rules that only care about how many neighbors are alive
+    pub fn isotropic(&self) -> Option<Isotropic> {
+        match self.kind {
+            RuleKind::Isotropic(isotropic) => Some(isotropic),
+            _ => None,
+        }
+    }
+
+    pub const fn from_block(block: BlockRule) -> Self {
+        Self {
+            birth: 0,
+            survival: 0,
+            states: 2,
+            kind: RuleKind::Block(block),
+        }
+    }
+
+    pub const fn from_stochastic(stochastic: Stochastic) -> Self {
+        Self {
+            birth: 0,
+            survival: 0,
+            states: 2,
+            kind: RuleKind::Stochastic(stochastic),
+        }
+    }
+
+    /// `None` for rules that have a rulestring
+    pub fn table(&self) -> Option<&RuleTable> {
+        match &self.kind {
+            RuleKind::Table(table) => Some(table),
+            _ => None,
+        }
+    }
+
+    /// `None` for rules where every cell looks at its neighbors
+    pub fn block(&self) -> Option<BlockRule> {
+        match self.kind {
+            RuleKind::Block(block) => Some(block),
+            _ => None,
+        }
+    }
+
+    /// `None` for rules that always do the same with the same neighbors
+    pub fn stochastic(&self) -> Option<Stochastic> {
+        match self.kind {
+            RuleKind::Stochastic(stochastic) => Some(stochastic),
+            _ => None,
+        }
+    }
+
+    pub fn kind(&self) -> &RuleKind {
+        &self.kind
+    }
+
+    /// The same rule with `states` states, anything below two is taken as two
+    pub const fn with_states(mut self, states: u8) -> Self {
+        self.states = if states < 2 { 2 } else { states };
+        self
+    }
+
+    /// Whether only how many of the usual 8 neighbors are alive matters,
+    /// which is all the masks can say
+    pub fn is_totalistic(&self) -> bool {
+        self.kind == RuleKind::Totalistic
+    }
+
+    /// Two for Life-like rules, more for the Generations family
+    pub fn states(&self) -> u8 {
+        self.states
+    }
+
+    /// Whether live cells go through dying states instead of dying right away
+    pub fn is_generations(&self) -> bool {
+        self.states > 2
+    }
+
+    /// The state a cell in `state` goes to when it is not alive,
+    /// dying cells count up until they wrap around to dead
+    pub fn decay(&self, state: u8) -> u8 {
+        match state {
+            0 => 0,
+            _ if state as u16 + 1 >= self.states as u16 => 0,
+            _ => state + 1,
+        }
+    }
+
+    /// The state a cell in `state` goes to with `alive` saying whether it's alive next,
+    /// which is only asked for dead and live cells since dying ones can't be born
+    pub fn next_state(&self, state: u8, alive: impl FnOnce() -> bool) -> u8 {
+        if state < 2 && alive() {
+            1
+        } else {
+            self.decay(state)
+        }
+    }
+
+    /// The masks and everything that uses them are only for the usual 8 neighbors,
+    /// and only when it's just how many of them are alive that matters
+    pub fn birth_mask(&self) -> u16 {
+        self.birth
+    }
+
+    pub fn survival_mask(&self) -> u16 {
+        self.survival
+    }
+
+    pub fn born(&self, neighbors: u8) -> bool {
+        self.birth >> neighbors & 1 == 1
+    }
+
+    pub fn survives(&self, neighbors: u8) -> bool {
+        self.survival >> neighbors & 1 == 1
+    }
+
+    /// Whether the cell with `neighbors` live neighbors is alive in the next generation
+    pub fn next(&self, alive: bool, neighbors: u8) -> bool {
+        if alive {
+            self.survives(neighbors)
+        } else {
+            self.born(neighbors)
+        }
+    }
+
+    /// Whether the middle of a 3x3 `neighborhood` is alive in the next generation,
+    /// bit `3 * y + x` being the cell at column `x` and row `y` of it
+    pub fn next_neighborhood(&self, neighborhood: u16) -> bool {
+        match self.kind {
+            RuleKind::Isotropic(isotropic) => isotropic.next(neighborhood),
+            _ => self.next(
+                neighborhood & MIDDLE != 0,
+                (neighborhood & 0x1FF & !MIDDLE).count_ones() as u8,
+            ),
+        }
+    }
+
+    /// Dead cells with no neighbors at all are born
+    pub fn has_b0(&self) -> bool {
+        match &self.kind {
+            RuleKind::Totalistic => self.born(0),
+            RuleKind::Larger(larger) => larger.has_b0(),
+            RuleKind::Isotropic(isotropic) => isotropic.next(0),
+            RuleKind::Table(table) => table.next(&[0; 9][..table.neighbors().len() + 1]) != 0,
+            RuleKind::Block(block) => block.has_b0(),
+            RuleKind::Stochastic(stochastic) => stochastic.has_b0(),
+        }
+    }
+}
+
+fn counts(mask: u16) -> String {
+    (0..=8)
+        .filter(|neighbors| mask >> neighbors & 1 == 1)
+        .map(|neighbors| char::from(b'0' + neighbors))
+        .collect()
+}
+
+/// The rule out of the letters of every count, which only becomes isotropic if it has to
+fn from_letters(birth: [u16; 9], survival: [u16; 9]) -> Rule {
+    if isotropic::is_totalistic(birth) && isotropic::is_totalistic(survival) {
+        let mask = |letters: [u16; 9]| {
+            (0..9)
+                .filter(|count| letters[*count] != 0)
+                .fold(0, |mask, count| mask | 1 << count)
+        };
+        Rule::from_masks(mask(birth), mask(survival))
+    } else {
+        Rule::from_isotropic(Isotropic::from_letters(birth, survival))
+    }
+}
+
+fn parse_states(digits: &str) -> Result<u8, RuleError> {
+    match digits.parse() {
+        Ok(states) if states >= 2 => Ok(states),
+        _ => Err(RuleError::InvalidStateCount),
+    }
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.kind {
+            RuleKind::Totalistic => {
+                write!(f, "B{}/S{}", counts(self.birth), counts(self.survival))?
+            }
+            RuleKind::Isotropic(isotropic) => write!(
+                f,
+                "B{}/S{}",
+                isotropic.counts(false),
+                isotropic.counts(true)
+            )?,
+            RuleKind::Larger(larger) => return larger.write(f, self.states),
+            RuleKind::Table(table) => return write!(f, "{}", table.name()),
+            RuleKind::Block(block) => return write!(f, "{}", block),
+            RuleKind::Stochastic(stochastic) => return stochastic.write(f, self.states),
+        }
+
+        if self.is_generations() {
+            write!(f, "/C{}", self.states)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Rule {
+    type Err = RuleError;
+
+    /// Takes `B3/S23`, `S23/B3`, `B3S23` (in any case) as well as `23/3`,
+    /// and for Generations `B2/S/C3` as well as `/2/3`.
+    /// The letters of isotropic rules have to be lowercase, so that `c` is not taken for `C`.
+    /// Larger than Life ones are the ones starting with `R`, block rules the ones starting
+    /// with `MS,D` and stochastic ones the ones with a chance or a seed
+    fn from_str(rulestring: &str) -> Result<Self, RuleError> {
+        let rulestring = rulestring.trim();
+
+        if rulestring.starts_with(['M', 'm']) {
+            return BlockRule::parse(rulestring).map(Self::from_block);
+        }
+        if rulestring.starts_with(['R', 'r']) {
+            let (larger, states) = LargerThanLife::parse(&rulestring.to_ascii_uppercase())?;
+            return Ok(Self::from_larger_than_life(larger).with_states(states));
+        }
+        if rulestring.contains([':', '@']) {
+            let (stochastic, states) = Stochastic::parse(rulestring)?;
+            return Ok(Self::from_stochastic(stochastic).with_states(states));
+        }
+
+        if !rulestring.contains(['B', 'b', 'S', 's', 'C']) {
+            // S/B or S/B/C notation
+            let mut parts = rulestring.split('/');
+            let (Some(survival), Some(birth)) = (parts.next(), parts.next()) else {
+                return Err(RuleError::InvalidFormat);
+            };
+            let states = parts.next().map_or(Ok(2), parse_states)?;
+            if parts.next().is_some() {
+                return Err(RuleError::InvalidFormat);
+            }
+
+            return Ok(from_letters(
+                isotropic::parse_letters(birth)?,
+                isotropic::parse_letters(survival)?,
+            )
+            .with_states(states));
+        }
+
+        let mut birth = None;
+        let mut survival = None;
+        let mut states = None;
+
+        // Every part starts with its letter, the slash between them is optional
+        let mut rest = rulestring;
+        while let Some(letter) = rest.chars().next() {
+            let letter = letter.to_ascii_uppercase();
+            if !matches!(letter, 'B' | 'S' | 'C') {
+                return Err(RuleError::InvalidFormat);
+            }
+
+            let end = rest[1..]
+                .find(['B', 'b', 'S', 's', 'C', '/'])
+                .map_or(rest.len(), |end| end + 1);
+            let digits = &rest[1..end];
+
+            let repeated = match letter {
+                'B' => birth.replace(isotropic::parse_letters(digits)?).is_some(),
+                'S' => survival
+                    .replace(isotropic::parse_letters(digits)?)
+                    .is_some(),
+                _ => states.replace(parse_states(digits)?).is_some(),
+            };
+            if repeated {
+                return Err(RuleError::InvalidFormat);
+            }
+
+            rest = rest[end..].strip_prefix('/').unwrap_or(&rest[end..]);
+        }
+
+        match (birth, survival) {
+            (Some(birth), Some(survival)) => {
+                Ok(from_letters(birth, survival).with_states(states.unwrap_or(2)))
+            }
+            _ => Err(RuleError::InvalidFormat),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_both_notations() {
+        for rulestring in ["B3/S23", "b3/s23", "S23/B3", "B3S23", " B3/S23 ", "23/3"] {
+            assert_eq!(rulestring.parse(), Ok(Rule::CONWAY), "{}", rulestring);
+        }
+
+        assert_eq!("B36/S23".parse(), Ok(RULE_PRESETS[1].1.clone()));
+        assert_eq!("34678/3678".parse(), Ok(RULE_PRESETS[2].1.clone()));
+    }
+
+    #[test]
+    fn empty_parts() {
+        let seeds: Rule = "B2/S".parse().unwrap();
+        assert_eq!(seeds, RULE_PRESETS[3].1.clone());
+        assert_eq!("/2".parse(), Ok(seeds.clone()));
+        assert_eq!(seeds.to_string(), "B2/S");
+    }
+
+    #[test]
+    fn b0() {
+        let rule: Rule = "B0123478/S01234678".parse().unwrap();
+
+        assert!(rule.has_b0());
+        assert!(rule.next(false, 0));
+        assert!(!rule.next(false, 5));
+        assert_eq!(rule.to_string(), "B0123478/S01234678");
+        assert!(!Rule::CONWAY.has_b0());
+    }
+
+    #[test]
+    fn prints_and_parses_back() {
+        for (_, rule) in RULE_PRESETS {
+            assert_eq!(rule.to_string().parse(), Ok(rule));
+        }
+    }
+
+    #[test]
+    fn generations() {
+        let brain: Rule = "B2/S/C3".parse().unwrap();
+        assert_eq!(brain, RULE_PRESETS[6].1.clone());
+        assert_eq!("/2/3".parse(), Ok(brain.clone()));
+        assert_eq!(brain.to_string(), "B2/S/C3");
+        assert_eq!("B2/S/C2".parse(), Ok(RULE_PRESETS[3].1.clone()));
+
+        let star_wars = RULE_PRESETS[7].1.clone();
+        assert_eq!("345/2/4".parse(), Ok(star_wars.clone()));
+        assert_eq!(star_wars.states(), 4);
+        assert_eq!(
+            [0, 1, 2, 3].map(|state| star_wars.decay(state)),
+            [0, 2, 3, 0]
+        );
+        // Dying cells keep dying whatever their neighbors say
+        assert_eq!(
+            [0, 1, 2, 3].map(|state| star_wars.next_state(state, || true)),
+            [1, 1, 3, 0]
+        );
+        assert_eq!(star_wars.next_state(1, || false), 2);
+
+        assert_eq!("B2/S/C1".parse::<Rule>(), Err(RuleError::InvalidStateCount));
+        assert_eq!("/2/256".parse::<Rule>(), Err(RuleError::InvalidStateCount));
+        assert_eq!("B2/S/C3/C3".parse::<Rule>(), Err(RuleError::InvalidFormat));
+    }
+
+    #[test]
+    fn one_kind_each() {
+        assert_eq!(Rule::CONWAY.kind(), &RuleKind::Totalistic);
+        assert_eq!(RULE_PRESETS[7].1.kind(), &RuleKind::Totalistic);
+        assert!(matches!(RULE_PRESETS[8].1.kind(), RuleKind::Larger(_)));
+        assert!(matches!(RULE_PRESETS[10].1.kind(), RuleKind::Block(_)));
+
+        let rule: Rule = "B2-a/S12".parse().unwrap();
+        assert_eq!(rule.kind(), &RuleKind::Isotropic(rule.isotropic().unwrap()));
+        assert!(!rule.is_totalistic());
+    }
+
+    #[test]
+    fn rejects_nonsense() {
+        assert_eq!(
+            "B9/S23".parse::<Rule>(),
+            Err(RuleError::InvalidNeighborCount('9'))
+        );
+        assert_eq!("B3".parse::<Rule>(), Err(RuleError::InvalidFormat));
+        assert_eq!("B3/S2/B3".parse::<Rule>(), Err(RuleError::InvalidFormat));
+        assert_eq!("233".parse::<Rule>(), Err(RuleError::InvalidFormat));
+        assert_eq!("X3/S23".parse::<Rule>(), Err(RuleError::InvalidFormat));
+        assert_eq!("éB3/S23".parse::<Rule>(), Err(RuleError::InvalidFormat));
+    }
+}
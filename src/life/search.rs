@@ -0,0 +1,390 @@
+/*!
+Running lots of random soups and counting what they settle into,
+like apgsearch does
+
+Every soup gets its own seed, one after the other from the first one,
+so anything found can be made again from its seed
+*/
+
+use std::collections::HashMap;
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use super::{census, Engine, ObjectKind, Rule, RuleError, Soup, SparseLife, Symmetry};
+
+/// How many seeds are kept for every object and for the soups that never settled
+pub const MAX_SAMPLES: usize = 5;
+
+/// The longest period that is noticed in the population when checking if a soup settled
+const MAX_SETTLED_PERIOD: usize = 60;
+
+/// How often a soup is checked for having settled, in generations
+const SETTLED_CHECK_INTERVAL: u64 = 60;
+
+#[derive(Debug, Clone)]
+pub struct SearchOptions {
+    pub soups: u64,
+    pub first_seed: u64,
+    /// Soups are `size` by `size` squares
+    pub size: i64,
+    pub density: f64,
+    pub symmetry: Symmetry,
+    pub rule: Rule,
+    /// Soups still changing by then are given up on
+    pub max_generations: u64,
+    pub threads: usize,
+}
+
+impl Default for SearchOptions {
+    fn default() -> Self {
+        Self {
+            soups: 1000,
+            first_seed: 0,
+            size: 16,
+            density: 0.5,
+            symmetry: Symmetry::C1,
+            rule: Rule::CONWAY,
+            max_generations: 10_000,
+            threads: std::thread::available_parallelism().map_or(1, |threads| threads.get()),
+        }
+    }
+}
+
+/// Everything found of one kind of object
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FoundObject {
+    pub code: String,
+    pub name: Option<&'static str>,
+    pub kind: ObjectKind,
+    pub count: u64,
+    /// In how many soups it showed up
+    pub soups: u64,
+    /// The lowest seeds of soups it showed up in
+    pub sample_seeds: Vec<u64>,
+}
+
+#[derive(Debug, Clone)]
+pub struct SearchReport {
+    pub options: SearchOptions,
+    /// The most common first
+    pub objects: Vec<FoundObject>,
+    /// How many soups never settled within [`SearchOptions::max_generations`]
+    pub unsettled: u64,
+    /// The lowest seeds of the soups that never settled
+    pub unsettled_seeds: Vec<u64>,
+}
+
+impl SearchReport {
+    /// Objects that showed up in at most one soup out of a hundred,
+    /// and in at least one of course
+    pub fn rare_objects(&self) -> impl Iterator<Item = &FoundObject> + '_ {
+        let threshold = (self.options.soups / 100).max(1);
+        self.objects
+            .iter()
+            .filter(move |object| object.soups <= threshold)
+    }
+
+    /// Writes the report as plain text, the counts first and the sample seeds after
+    pub fn write_to(&self, mut writer: impl Write) -> io::Result<()> {
+        let options = &self.options;
+
+        writeln!(writer, "# Soup search")?;
+        writeln!(writer, "rule {}", options.rule)?;
+        writeln!(writer, "size {}x{}", options.size, options.size)?;
+        writeln!(writer, "density {}", options.density)?;
+        writeln!(writer, "symmetry {}", options.symmetry)?;
+        writeln!(
+            writer,
+            "seeds {} to {}",
+            options.first_seed,
+            options.first_seed + options.soups.saturating_sub(1)
+        )?;
+        writeln!(writer, "soups {}", options.soups)?;
+        writeln!(writer, "unsettled {}", self.unsettled)?;
+
+        writeln!(writer)?;
+        writeln!(writer, "# Objects")?;
+        writeln!(writer, "code count soups name")?;
+        for object in &self.objects {
+            writeln!(
+                writer,
+                "{} {} {} {}",
+                object.code,
+                object.count,
+                object.soups,
+                object.name.unwrap_or("-")
+            )?;
+        }
+
+        writeln!(writer)?;
+        writeln!(writer, "# Rare objects")?;
+        writeln!(writer, "code seeds")?;
+        for object in self.rare_objects() {
+            writeln!(
+                writer,
+                "{} {}",
+                object.code,
+                seed_list(&object.sample_seeds)
+            )?;
+        }
+
+        if !self.unsettled_seeds.is_empty() {
+            writeln!(writer)?;
+            writeln!(writer, "# Unsettled soups")?;
+            writeln!(writer, "{}", seed_list(&self.unsettled_seeds))?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Runs every soup in [`SearchOptions`] on as many threads as it says,
+/// calling `progress` with how many soups are done after each one
+pub fn search(
+    options: &SearchOptions,
+    progress: &(dyn Fn(u64) + Sync),
+) -> Result<SearchReport, RuleError> {
+    // Checked once here rather than on every soup
+    SparseLife::new().set_rule(options.rule.clone())?;
+
+    let next_seed = AtomicU64::new(0);
+    let done = AtomicU64::new(0);
+    let threads = options.threads.max(1);
+
+    let tallies: Vec<Tally> = std::thread::scope(|scope| {
+        let handles: Vec<_> = (0..threads)
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut tally = Tally::default();
+
+                    loop {
+                        let index = next_seed.fetch_add(1, Ordering::Relaxed);
+                        if index >= options.soups {
+                            break tally;
+                        }
+
+                        tally.add_soup(options.first_seed + index, options);
+                        progress(done.fetch_add(1, Ordering::Relaxed) + 1);
+                    }
+                })
+            })
+            .collect();
+
+        handles
+            .into_iter()
+            .map(|handle| handle.join().unwrap())
+            .collect()
+    });
+
+    let mut total = Tally::default();
+    for tally in tallies {
+        total.merge(tally);
+    }
+
+    Ok(total.into_report(options.clone()))
+}
+
+/// Runs a soup until its population starts repeating, returns `None` if it never does
+pub fn settle_soup(seed: u64, options: &SearchOptions) -> Option<SparseLife> {
+    let soup = Soup {
+        seed,
+        density: options.density,
+        symmetry: options.symmetry,
+    };
+    let mut sparse =
+        SparseLife::from_cells(soup.cells([0, 0, options.size - 1, options.size - 1]), 0);
+    sparse.set_rule(options.rule.clone()).ok()?;
+
+    let window = MAX_SETTLED_PERIOD * 4;
+    let mut populations = Vec::with_capacity(window);
+
+    while sparse.generation() < options.max_generations {
+        sparse.step();
+
+        if populations.len() == window {
+            populations.remove(0);
+        }
+        populations.push(sparse.population());
+
+        if sparse.generation().is_multiple_of(SETTLED_CHECK_INTERVAL)
+            && repeats(&populations, window)
+        {
+            return Some(sparse);
+        }
+    }
+
+    None
+}
+
+/// Whether the last `window` populations go around with a short enough period
+fn repeats(populations: &[u64], window: usize) -> bool {
+    populations.len() == window
+        && (1..=MAX_SETTLED_PERIOD).any(|period| {
+            populations[period..]
+                .iter()
+                .zip(populations)
+                .all(|(later, earlier)| later == earlier)
+        })
+}
+
+fn seed_list(seeds: &[u64]) -> String {
+    seeds
+        .iter()
+        .map(|seed| seed.to_string())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// What one thread found
+#[derive(Default)]
+struct Tally {
+    objects: HashMap<String, FoundObject>,
+    unsettled: u64,
+    unsettled_seeds: Vec<u64>,
+}
+
+impl Tally {
+    fn add_soup(&mut self, seed: u64, options: &SearchOptions) {
+        let Some(settled) = settle_soup(seed, options) else {
+            self.unsettled += 1;
+            keep_sample(&mut self.unsettled_seeds, seed);
+            return;
+        };
+
+        for entry in census(&settled).entries {
+            let object = self
+                .objects
+                .entry(entry.code.clone())
+                .or_insert_with(|| FoundObject {
+                    code: entry.code,
+                    name: entry.name,
+                    kind: entry.kind,
+                    count: 0,
+                    soups: 0,
+                    sample_seeds: Vec::new(),
+                });
+
+            object.count += entry.count as u64;
+            object.soups += 1;
+            keep_sample(&mut object.sample_seeds, seed);
+        }
+    }
+
+    fn merge(&mut self, other: Tally) {
+        for (code, found) in other.objects {
+            match self.objects.get_mut(&code) {
+                Some(object) => {
+                    object.count += found.count;
+                    object.soups += found.soups;
+                    for seed in found.sample_seeds {
+                        keep_sample(&mut object.sample_seeds, seed);
+                    }
+                }
+                None => {
+                    self.objects.insert(code, found);
+                }
+            }
+        }
+
+        self.unsettled += other.unsettled;
+        for seed in other.unsettled_seeds {
+            keep_sample(&mut self.unsettled_seeds, seed);
+        }
+    }
+
+    fn into_report(self, options: SearchOptions) -> SearchReport {
+        let mut objects: Vec<_> = self.objects.into_values().collect();
+        objects.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.code.cmp(&b.code)));
+
+        SearchReport {
+            options,
+            objects,
+            unsettled: self.unsettled,
+            unsettled_seeds: self.unsettled_seeds,
+        }
+    }
+}
+
+/// Keeps the lowest [`MAX_SAMPLES`] seeds, sorted,
+/// so the samples don't depend on which thread got which soup
+fn keep_sample(samples: &mut Vec<u64>, seed: u64) {
+    let index = samples.partition_point(|&sample| sample < seed);
+    if index < MAX_SAMPLES && samples.get(index) != Some(&seed) {
+        samples.insert(index, seed);
+        samples.truncate(MAX_SAMPLES);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options(threads: usize) -> SearchOptions {
+        SearchOptions {
+            soups: 8,
+            first_seed: 100,
+            max_generations: 2000,
+            threads,
+            ..SearchOptions::default()
+        }
+    }
+
+    #[test]
+    fn threads_dont_change_the_results() {
+        let single = search(&options(1), &|_| {}).unwrap();
+        let many = search(&options(4), &|_| {}).unwrap();
+
+        assert_eq!(single.objects, many.objects);
+        assert_eq!(single.unsettled_seeds, many.unsettled_seeds);
+
+        // Blocks are in just about every soup
+        let block = single
+            .objects
+            .iter()
+            .find(|object| object.code == "xs4_33")
+            .unwrap();
+        assert_eq!(block.name, Some("block"));
+        assert!(block
+            .sample_seeds
+            .iter()
+            .all(|seed| (100..108).contains(seed)));
+    }
+
+    #[test]
+    fn settled_soups_repeat() {
+        let options = options(1);
+        let mut settled = (100..108)
+            .find_map(|seed| settle_soup(seed, &options))
+            .unwrap();
+
+        let window = MAX_SETTLED_PERIOD * 4;
+        let populations: Vec<_> = (0..window)
+            .map(|_| {
+                settled.step();
+                settled.population()
+            })
+            .collect();
+        assert!(repeats(&populations, window));
+    }
+
+    #[test]
+    fn report_has_counts_and_seeds() {
+        let report = search(&options(2), &|_| {}).unwrap();
+        let mut text = Vec::new();
+        report.write_to(&mut text).unwrap();
+        let text = String::from_utf8(text).unwrap();
+
+        assert!(text.contains("seeds 100 to 107"));
+        assert!(text.contains("\nxs4_33 "));
+        assert!(text.contains("# Rare objects"));
+    }
+
+    #[test]
+    fn samples_are_the_lowest_seeds() {
+        let mut samples = Vec::new();
+        for seed in [9, 3, 7, 3, 1, 8, 2, 6] {
+            keep_sample(&mut samples, seed);
+        }
+        assert_eq!(samples, vec![1, 2, 3, 6, 7]);
+    }
+}
@@ -0,0 +1,252 @@
+/*!
+Random soups, cells scattered at random to see what comes out of them
+
+Everything comes out of a seed, so the same seed, density and symmetry
+always give the same soup, on any machine
+*/
+
+use std::fmt::{self, Display, Formatter};
+use std::str::FromStr;
+
+/// A small and fast generator, SplitMix64, written here so that
+/// seeds keep giving the same soups no matter what dependencies do
+#[derive(Debug, Clone)]
+pub struct Random {
+    state: u64,
+}
+
+impl Random {
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Between 0 included and 1 excluded
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// True with a `probability` chance
+    pub fn chance(&mut self, probability: f64) -> bool {
+        self.next_f64() < probability
+    }
+}
+
+/// How a soup is made to look the same under rotations and reflections,
+/// named like in apgsearch
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Symmetry {
+    /// No symmetry at all
+    #[default]
+    C1,
+    /// The same after half a turn
+    C2,
+    /// The same after a quarter turn
+    C4,
+    /// Mirrored left to right
+    D2,
+    /// Mirrored left to right and top to bottom
+    D4,
+    /// The same after any rotation or reflection
+    D8,
+}
+
+impl Symmetry {
+    pub const ALL: [Symmetry; 6] = [
+        Symmetry::C1,
+        Symmetry::C2,
+        Symmetry::C4,
+        Symmetry::D2,
+        Symmetry::D4,
+        Symmetry::D8,
+    ];
+
+    /// Quarter turns and diagonal reflections only work on squares
+    pub fn needs_square(&self) -> bool {
+        matches!(self, Self::C4 | Self::D8)
+    }
+
+    /// Every cell that has to be the same as `[x, y]`, `[x, y]` itself included,
+    /// in a region `width` by `height` starting at zero
+    fn images(&self, [x, y]: [i64; 2], [width, height]: [i64; 2]) -> Vec<[i64; 2]> {
+        let (right, bottom) = (width - 1, height - 1);
+
+        match self {
+            Self::C1 => vec![[x, y]],
+            Self::C2 => vec![[x, y], [right - x, bottom - y]],
+            Self::C4 => vec![
+                [x, y],
+                [bottom - y, x],
+                [right - x, bottom - y],
+                [y, right - x],
+            ],
+            Self::D2 => vec![[x, y], [right - x, y]],
+            Self::D4 => vec![
+                [x, y],
+                [right - x, y],
+                [x, bottom - y],
+                [right - x, bottom - y],
+            ],
+            Self::D8 => vec![
+                [x, y],
+                [bottom - y, x],
+                [right - x, bottom - y],
+                [y, right - x],
+                [right - x, y],
+                [x, bottom - y],
+                [y, x],
+                [bottom - y, right - x],
+            ],
+        }
+    }
+}
+
+impl Display for Symmetry {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+impl FromStr for Symmetry {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|symmetry| symmetry.to_string().eq_ignore_ascii_case(s))
+            .ok_or(())
+    }
+}
+
+/// Everything needed to make the same soup again
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Soup {
+    pub seed: u64,
+    /// How likely every cell is to be alive, from 0 to 1
+    pub density: f64,
+    pub symmetry: Symmetry,
+}
+
+impl Default for Soup {
+    fn default() -> Self {
+        Self {
+            seed: 0,
+            density: 0.5,
+            symmetry: Symmetry::C1,
+        }
+    }
+}
+
+impl Soup {
+    /// The live cells of the soup in `[min_x, min_y, max_x, max_y]`, all inclusive.
+    /// With [`Symmetry::needs_square`] only the biggest square that fits
+    /// in the top left of the region is filled
+    pub fn cells(&self, [min_x, min_y, max_x, max_y]: [i64; 4]) -> Vec<[i64; 2]> {
+        let (mut width, mut height) = (max_x - min_x + 1, max_y - min_y + 1);
+        if width <= 0 || height <= 0 {
+            return Vec::new();
+        }
+        if self.symmetry.needs_square() {
+            width = width.min(height);
+            height = width;
+        }
+
+        let mut random = Random::new(self.seed);
+        let mut cells = Vec::new();
+
+        for y in 0..height {
+            for x in 0..width {
+                let images = self.symmetry.images([x, y], [width, height]);
+
+                // Only the first of the images in reading order gets to pick for all of them
+                let first = images.iter().min_by_key(|[x, y]| (*y, *x)).unwrap();
+                if *first != [x, y] || !random.chance(self.density) {
+                    continue;
+                }
+
+                cells.extend(images.iter().map(|[x, y]| [min_x + x, min_y + y]));
+            }
+        }
+
+        cells.sort_unstable_by_key(|[x, y]| (*y, *x));
+        cells.dedup();
+        cells
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const REGION: [i64; 4] = [-3, 5, 12, 20];
+
+    #[test]
+    fn same_seed_same_soup() {
+        let soup = Soup {
+            seed: 1234,
+            ..Soup::default()
+        };
+        let cells = soup.cells(REGION);
+
+        assert_eq!(cells, soup.cells(REGION));
+        assert_ne!(cells, Soup { seed: 1235, ..soup }.cells(REGION));
+        assert!(cells
+            .iter()
+            .all(|&[x, y]| (-3..=12).contains(&x) && (5..=20).contains(&y)));
+
+        // Half of 16 by 16, give or take
+        assert!((96..160).contains(&cells.len()));
+    }
+
+    #[test]
+    fn density_extremes() {
+        let empty = Soup {
+            density: 0.0,
+            ..Soup::default()
+        };
+        let full = Soup {
+            density: 1.0,
+            ..Soup::default()
+        };
+
+        assert!(empty.cells(REGION).is_empty());
+        assert_eq!(full.cells(REGION).len(), 16 * 16);
+    }
+
+    #[test]
+    fn soups_are_symmetric() {
+        for symmetry in Symmetry::ALL {
+            let soup = Soup {
+                seed: 99,
+                density: 0.3,
+                symmetry,
+            };
+            // Not a square, so quarter turns only get the left part
+            let cells = soup.cells([0, 0, 14, 9]);
+            let side = if symmetry.needs_square() { 10 } else { 15 };
+
+            for &[x, y] in &cells {
+                for [x, y] in symmetry.images([x, y], [side, 10]) {
+                    assert!(cells.contains(&[x, y]), "{} at ({}, {})", symmetry, x, y);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn symmetry_names_round_trip() {
+        for symmetry in Symmetry::ALL {
+            assert_eq!(symmetry.to_string().parse(), Ok(symmetry));
+        }
+        assert_eq!("d8".parse(), Ok(Symmetry::D8));
+        assert_eq!("C3".parse::<Symmetry>(), Err(()));
+    }
+}
@@ -0,0 +1,542 @@
+/*!
+An unbounded universe that only stores the parts of the plane with something in them
+
+The plane is split into chunks of 64x64 cells keyed by their signed chunk
+coordinates, every row of a chunk being a single `u64` just like in [`BitGrid`].
+Chunks get allocated when something is born in them and
+freed as soon as everything in them dies
+
+[`BitGrid`]: super::BitGrid
+*/
+
+use std::collections::{HashMap, HashSet};
+
+use super::bitgrid::{neighbor_count, next_word};
+use super::lattice::SQUARE_NEIGHBORS;
+use super::{
+    births_and_deaths, changes, margolus, BlockRule, DyingCells, Engine, Isotropic, LargerThanLife,
+    Rule, RuleError, RuleKind, Stochastic,
+};
+
+pub const CHUNK_SIZE: i64 = 64;
+
+type Chunk = [u64; CHUNK_SIZE as usize];
+
+const EMPTY_CHUNK: Chunk = [0; CHUNK_SIZE as usize];
+
+#[derive(Debug, Clone, Default)]
+pub struct SparseLife {
+    chunks: HashMap<[i64; 2], Box<Chunk>>,
+    generation: u64,
+    rule: Rule,
+    /// How many cells were born and died in the last step
+    births_and_deaths: Option<[u64; 2]>,
+    /// Always empty unless the rule is from the Generations family
+    dying: DyingCells,
+}
+
+/// The chunk a cell is in and where in the chunk it is
+fn locate(x: i64, y: i64) -> ([i64; 2], usize, usize) {
+    (
+        [x.div_euclid(CHUNK_SIZE), y.div_euclid(CHUNK_SIZE)],
+        x.rem_euclid(CHUNK_SIZE) as usize,
+        y.rem_euclid(CHUNK_SIZE) as usize,
+    )
+}
+
+impl SparseLife {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Starts off with `cells` alive and the generation count at `generation`
+    pub fn from_cells(cells: impl IntoIterator<Item = [i64; 2]>, generation: u64) -> Self {
+        let mut sparse = Self {
+            generation,
+            ..Self::default()
+        };
+
+        for [x, y] in cells {
+            sparse.set_cell(x, y, true);
+        }
+
+        sparse
+    }
+
+    /// How many chunks are allocated right now
+    pub fn chunk_count(&self) -> usize {
+        self.chunks.len()
+    }
+
+    /// Same as [`Engine::set_cell`] but leaves the dying cells alone
+    fn set_alive(&mut self, x: i64, y: i64, alive: bool) {
+        let (key, column, row) = locate(x, y);
+
+        if alive {
+            self.chunks
+                .entry(key)
+                .or_insert_with(|| Box::new(EMPTY_CHUNK))[row] |= 1 << column;
+        } else if let Some(chunk) = self.chunks.get_mut(&key) {
+            chunk[row] &= !(1 << column);
+
+            if chunk.iter().all(|word| *word == 0) {
+                self.chunks.remove(&key);
+            }
+        }
+    }
+
+    /// Goes through `cells` with `next` saying which state each of them goes to
+    /// out of the one it is in, and only changes them once that is known for all of them
+    pub(super) fn step_cells(
+        &mut self,
+        cells: impl IntoIterator<Item = [i64; 2]>,
+        mut next: impl FnMut(&Self, [i64; 2], u8) -> u8,
+    ) {
+        let changes = changes(
+            cells,
+            |[x, y]| self.cell_state(x, y),
+            |cell, state| next(self, cell, state),
+        );
+        for ([x, y], _, state) in &changes {
+            self.set_cell_state(*x, *y, *state);
+        }
+        self.births_and_deaths = Some(births_and_deaths(&changes));
+        self.generation += 1;
+    }
+
+    /// The live and dying cells, and the ones `offsets` away from them
+    pub(super) fn around(&self, offsets: &[[i64; 2]]) -> HashSet<[i64; 2]> {
+        let cells = self
+            .live_cells()
+            .into_iter()
+            .chain(self.dying.iter().map(|(cell, _)| cell));
+
+        let mut around = HashSet::new();
+        for [x, y] in cells {
+            around.insert([x, y]);
+            for [offset_x, offset_y] in offsets {
+                around.insert([x - offset_x, y - offset_y]);
+            }
+        }
+        around
+    }
+
+    /// [`Engine::step`] for Larger than Life rules, every live cell
+    /// adds itself to the count of each cell it is a neighbor of
+    fn step_larger(&mut self, larger: LargerThanLife) {
+        let mut counts: HashMap<[i64; 2], u32> = HashMap::new();
+        for [x, y] in self.live_cells() {
+            for [offset_x, offset_y] in larger.offsets() {
+                *counts.entry([x - offset_x, y - offset_y]).or_default() += 1;
+            }
+        }
+
+        // Live cells with nothing around still have to survive or not
+        let mut cells = self.around(&[]);
+        cells.extend(counts.keys());
+        self.step_cells(cells, |sparse, cell, state| {
+            let count = counts.get(&cell).copied().unwrap_or_default();
+            sparse
+                .rule
+                .next_state(state, || larger.next(state == 1, count))
+        });
+    }
+
+    /// [`Engine::step`] for isotropic non-totalistic rules,
+    /// only the live cells and the ones next to them can change
+    fn step_isotropic(&mut self, isotropic: Isotropic) {
+        self.step_cells(self.around(&SQUARE_NEIGHBORS), |sparse, [x, y], state| {
+            sparse.rule.next_state(state, || {
+                let neighborhood = (0..9)
+                    .filter(|bit| sparse.cell(x + bit % 3 - 1, y + bit / 3 - 1))
+                    .fold(0, |neighborhood, bit| neighborhood | 1 << bit);
+                isotropic.next(neighborhood)
+            })
+        });
+    }
+
+    /// [`Engine::step`] for stochastic rules, only the live cells and
+    /// the ones next to them can change since nothing is born out of nowhere
+    fn step_stochastic(&mut self, stochastic: Stochastic) {
+        self.step_cells(self.around(&SQUARE_NEIGHBORS), |sparse, [x, y], state| {
+            sparse.rule.next_state(state, || {
+                let neighbors = SQUARE_NEIGHBORS
+                    .iter()
+                    .filter(|[offset_x, offset_y]| sparse.cell(x + offset_x, y + offset_y))
+                    .count();
+                stochastic.next(state == 1, neighbors as u8, sparse.generation, [x, y])
+            })
+        });
+    }
+
+    /// [`Engine::step`] for block rules, only the blocks with something in them
+    /// can change since empty ones have to stay empty on an infinite plane
+    fn step_block(&mut self, block: BlockRule) {
+        let cells: HashSet<[i64; 2]> = self
+            .live_cells()
+            .into_iter()
+            .flat_map(|[x, y]| {
+                let [corner_x, corner_y] = BlockRule::corner(x, y, self.generation);
+                margolus::BLOCK
+                    .map(|[offset_x, offset_y]| [corner_x + offset_x, corner_y + offset_y])
+            })
+            .collect();
+
+        self.step_cells(cells, |sparse, cell, _| {
+            block.next_cell(cell, sparse.generation, |x, y| sparse.cell(x, y))
+        });
+    }
+
+    /// [`Engine::step`] for totalistic rules, chunk by chunk
+    fn step_chunks(&mut self) {
+        // Something can only be born next to a chunk that has something in it
+        let mut active = HashSet::new();
+        for [chunk_x, chunk_y] in self.chunks.keys() {
+            for offset_y in -1..=1 {
+                for offset_x in -1..=1 {
+                    active.insert([chunk_x + offset_x, chunk_y + offset_y]);
+                }
+            }
+        }
+
+        let (mut births, mut deaths) = (0, 0);
+        let generations = self.rule.is_generations();
+        let mut died = Vec::new();
+
+        let chunks = active
+            .into_iter()
+            .filter_map(|key| {
+                let next = self.step_chunk(key);
+                let before = self.chunks.get(&key).map_or(&EMPTY_CHUNK, |chunk| chunk);
+
+                for (row, (next, before)) in next.iter().zip(before.iter()).enumerate() {
+                    births += (next & !before).count_ones() as u64;
+                    deaths += (!next & before).count_ones() as u64;
+
+                    if !generations {
+                        continue;
+                    }
+                    let mut word = !next & before;
+                    while word != 0 {
+                        let column = word.trailing_zeros() as i64;
+                        word &= word - 1;
+                        died.push([
+                            key[0] * CHUNK_SIZE + column,
+                            key[1] * CHUNK_SIZE + row as i64,
+                        ]);
+                    }
+                }
+
+                next.iter()
+                    .any(|word| *word != 0)
+                    .then(|| (key, Box::new(next)))
+            })
+            .collect();
+
+        self.chunks = chunks;
+
+        // Dying cells can't be born
+        let blocked: Vec<_> = self
+            .dying
+            .iter()
+            .filter(|([x, y], _)| self.cell(*x, *y))
+            .collect();
+        for ([x, y], _) in blocked {
+            self.set_alive(x, y, false);
+            births -= 1;
+        }
+        self.dying.step(&self.rule, died);
+
+        self.generation += 1;
+        self.births_and_deaths = Some([births, deaths]);
+    }
+
+    /// The next generation of a single chunk
+    fn step_chunk(&self, [chunk_x, chunk_y]: [i64; 2]) -> Chunk {
+        // The chunk and its 8 neighbors, row by row
+        let mut around = [[&EMPTY_CHUNK; 3]; 3];
+        for (offset_y, row) in around.iter_mut().enumerate() {
+            for (offset_x, chunk) in row.iter_mut().enumerate() {
+                let key = [chunk_x + offset_x as i64 - 1, chunk_y + offset_y as i64 - 1];
+                if let Some(neighbor) = self.chunks.get(&key) {
+                    *chunk = neighbor;
+                }
+            }
+        }
+
+        // A row going through the chunks to the left and right,
+        // `row` going from -1 up to 64 to reach the chunks above and below
+        let words = |row: i64| -> [u64; 3] {
+            let (chunks, row) = match row {
+                -1 => (&around[0], CHUNK_SIZE as usize - 1),
+                CHUNK_SIZE => (&around[2], 0),
+                _ => (&around[1], row as usize),
+            };
+            [chunks[0][row], chunks[1][row], chunks[2][row]]
+        };
+
+        let mut next = EMPTY_CHUNK;
+        for (row, word) in next.iter_mut().enumerate() {
+            let row = row as i64;
+            let (above, current, below) = (words(row - 1), words(row), words(row + 1));
+
+            let planes = neighbor_count(&above, &current, &below, 1);
+            *word = next_word(planes, current[1], &self.rule);
+        }
+
+        next
+    }
+}
+
+impl Engine for SparseLife {
+    fn step(&mut self) {
+        // Cloned so that a table can be looked at while the cells change
+        match self.rule.kind().clone() {
+            RuleKind::Table(table) => self.step_table(&table),
+            RuleKind::Block(block) => self.step_block(block),
+            RuleKind::Stochastic(stochastic) => self.step_stochastic(stochastic),
+            RuleKind::Larger(larger) => self.step_larger(larger),
+            RuleKind::Isotropic(isotropic) => self.step_isotropic(isotropic),
+            RuleKind::Totalistic => self.step_chunks(),
+        }
+    }
+
+    fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    fn set_generation(&mut self, generation: u64) {
+        self.generation = generation;
+    }
+
+    fn rule(&self) -> Rule {
+        self.rule.clone()
+    }
+
+    fn set_rule(&mut self, rule: Rule) -> Result<(), RuleError> {
+        if rule.has_b0() {
+            return Err(RuleError::B0OnInfinitePlane);
+        }
+
+        self.dying.fit_rule(&rule);
+        self.rule = rule;
+        Ok(())
+    }
+
+    fn cell(&self, x: i64, y: i64) -> bool {
+        let (key, column, row) = locate(x, y);
+        self.chunks
+            .get(&key)
+            .is_some_and(|chunk| chunk[row] >> column & 1 == 1)
+    }
+
+    /// A dying cell stops dying either way
+    fn set_cell(&mut self, x: i64, y: i64, alive: bool) {
+        self.dying.set(x, y, 0);
+        self.set_alive(x, y, alive);
+    }
+
+    fn cell_state(&self, x: i64, y: i64) -> u8 {
+        if self.cell(x, y) {
+            1
+        } else {
+            self.dying.get(x, y).unwrap_or(0)
+        }
+    }
+
+    fn set_cell_state(&mut self, x: i64, y: i64, state: u8) {
+        self.set_cell(x, y, state == 1);
+        if state < self.rule.states() {
+            self.dying.set(x, y, state);
+        }
+    }
+
+    fn dying_cells(&self) -> Vec<([i64; 2], u8)> {
+        self.dying.iter().collect()
+    }
+
+    fn population(&self) -> u64 {
+        self.chunks
+            .values()
+            .flat_map(|chunk| chunk.iter())
+            .map(|word| word.count_ones() as u64)
+            .sum()
+    }
+
+    fn births_and_deaths(&self) -> Option<[u64; 2]> {
+        self.births_and_deaths
+    }
+
+    fn clear(&mut self) {
+        self.chunks.clear();
+        self.dying.clear();
+        self.generation = 0;
+        self.births_and_deaths = None;
+    }
+
+    fn live_cells(&self) -> Vec<[i64; 2]> {
+        let mut cells = Vec::new();
+
+        for ([chunk_x, chunk_y], chunk) in self.chunks.iter() {
+            for (row, word) in chunk.iter().enumerate() {
+                let mut word = *word;
+                while word != 0 {
+                    let column = word.trailing_zeros() as i64;
+                    word &= word - 1;
+
+                    cells.push([
+                        chunk_x * CHUNK_SIZE + column,
+                        chunk_y * CHUNK_SIZE + row as i64,
+                    ]);
+                }
+            }
+        }
+
+        cells
+    }
+
+    /// Only the chunks on the outside need to be looked into
+    fn bounding_box(&self) -> Option<[i64; 4]> {
+        let mut bounds: Option<[i64; 4]> = None;
+
+        for ([chunk_x, chunk_y], chunk) in self.chunks.iter() {
+            let nonzero = |(_, word): &(usize, &u64)| **word != 0;
+            let (Some((first_row, _)), Some((last_row, _))) = (
+                chunk.iter().enumerate().find(nonzero),
+                chunk.iter().enumerate().rfind(nonzero),
+            ) else {
+                continue;
+            };
+            let columns = chunk.iter().fold(0, |columns, word| columns | word);
+
+            let [x, y] = [chunk_x * CHUNK_SIZE, chunk_y * CHUNK_SIZE];
+            let chunk_bounds = [
+                x + columns.trailing_zeros() as i64,
+                y + first_row as i64,
+                x + CHUNK_SIZE - 1 - columns.leading_zeros() as i64,
+                y + last_row as i64,
+            ];
+
+            bounds = Some(match bounds {
+                None => chunk_bounds,
+                Some(bounds) => [
+                    bounds[0].min(chunk_bounds[0]),
+                    bounds[1].min(chunk_bounds[1]),
+                    bounds[2].max(chunk_bounds[2]),
+                    bounds[3].max(chunk_bounds[3]),
+                ],
+            });
+        }
+
+        bounds
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::life::tests::{assert_grid_and_plane_agree, soup};
+    use crate::HashLife;
+
+    fn sorted(mut cells: Vec<[i64; 2]>) -> Vec<[i64; 2]> {
+        cells.sort_by_key(|[x, y]| (*y, *x));
+        cells
+    }
+
+    #[test]
+    fn negative_coordinates() {
+        let mut sparse = SparseLife::new();
+
+        sparse.set_cell(-1, -1, true);
+        sparse.set_cell(-64, 63, true);
+        sparse.set_cell(-65, 0, true);
+
+        assert!(sparse.cell(-1, -1));
+        assert!(sparse.cell(-64, 63));
+        assert!(sparse.cell(-65, 0));
+        assert!(!sparse.cell(0, 0));
+        assert_eq!(sparse.chunk_count(), 3);
+
+        sparse.set_cell(-1, -1, false);
+        assert_eq!(sparse.chunk_count(), 2);
+    }
+
+    #[test]
+    fn blinker_across_chunk_corner() {
+        // Right on the corner where four chunks meet
+        let mut sparse = SparseLife::from_cells([[-1, 0], [0, 0], [1, 0]], 0);
+
+        sparse.step();
+        assert_eq!(sorted(sparse.live_cells()), vec![[0, -1], [0, 0], [0, 1]]);
+
+        sparse.step();
+        assert_eq!(sorted(sparse.live_cells()), vec![[-1, 0], [0, 0], [1, 0]]);
+    }
+
+    #[test]
+    fn chunks_follow_a_glider() {
+        let glider = [[1, 0], [2, 1], [0, 2], [1, 2], [2, 2]];
+        let mut sparse = SparseLife::from_cells(glider, 0);
+
+        // 100 cells away from where it started
+        sparse.step_n(400);
+
+        let expected: Vec<_> = glider.iter().map(|[x, y]| [x + 100, y + 100]).collect();
+        assert_eq!(sorted(sparse.live_cells()), sorted(expected));
+        assert!(sparse.chunk_count() <= 4);
+        assert_eq!(sparse.generation(), 400);
+    }
+
+    #[test]
+    fn matches_hashlife() {
+        let mut sparse = SparseLife::new();
+        let mut hashlife = HashLife::new();
+
+        for [x, y] in soup([-40, -40, 39, 39], 0.5, 0xBADC0DE) {
+            sparse.set_cell(x, y, true);
+            hashlife.set_cell(x, y, true);
+        }
+
+        for _ in 0..10 {
+            sparse.step_n(7);
+            hashlife.step_n(7);
+            assert_eq!(sorted(sparse.live_cells()), sorted(hashlife.live_cells()));
+        }
+
+        let highlife: Rule = "B36/S23".parse().unwrap();
+        sparse.set_rule(highlife.clone()).unwrap();
+        hashlife.set_rule(highlife).unwrap();
+
+        for _ in 0..5 {
+            sparse.step_n(5);
+            hashlife.step_n(5);
+            assert_eq!(sorted(sparse.live_cells()), sorted(hashlife.live_cells()));
+        }
+    }
+
+    #[test]
+    fn bounding_box_and_changes() {
+        let mut sparse = SparseLife::from_cells([[-1, 0], [0, 0], [1, 0], [200, -70]], 0);
+        assert_eq!(sparse.bounding_box(), Some([-1, -70, 200, 0]));
+        assert_eq!(sparse.births_and_deaths(), None);
+
+        sparse.step();
+        assert_eq!(sparse.bounding_box(), Some([0, -1, 0, 1]));
+        assert_eq!(sparse.births_and_deaths(), Some([2, 3]));
+    }
+
+    #[test]
+    fn matches_a_grid_with_generations() {
+        let cells = soup([90, 90, 109, 109], 1.0 / 3.0, 0xFACADE);
+        assert_grid_and_plane_agree("B2/S345/C4".parse().unwrap(), 200, &cells, 20);
+    }
+
+    #[test]
+    fn rejects_b0() {
+        let mut sparse = SparseLife::new();
+
+        let rule = "B0/S8".parse().unwrap();
+        assert_eq!(sparse.set_rule(rule), Err(RuleError::B0OnInfinitePlane));
+        assert_eq!(sparse.rule(), Rule::CONWAY);
+    }
+}
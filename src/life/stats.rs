@@ -0,0 +1,166 @@
+/*!
+Numbers about every generation, kept around to see how a run went
+*/
+
+use std::collections::VecDeque;
+
+use super::Engine;
+
+/// How many generations are kept by default, the oldest ones go first
+pub const DEFAULT_STATS_CAPACITY: usize = 10_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GenerationStats {
+    pub generation: u64,
+    pub population: u64,
+    /// Unknown for the first generation recorded and for engines that don't keep track
+    pub births: Option<u64>,
+    pub deaths: Option<u64>,
+    /// `[min_x, min_y, max_x, max_y]`, all inclusive, `None` if nothing is alive
+    pub bounding_box: Option<[i64; 4]>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Statistics {
+    /// Oldest first
+    generations: VecDeque<GenerationStats>,
+    capacity: usize,
+}
+
+impl Default for Statistics {
+    fn default() -> Self {
+        Self::new(DEFAULT_STATS_CAPACITY)
+    }
+}
+
+impl Statistics {
+    /// Anything below one is taken as one
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            generations: VecDeque::new(),
+            capacity: capacity.max(1),
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.generations.clear();
+    }
+
+    /// Takes in the current generation, which should come right after the last one
+    /// for the births and deaths to be kept. Recording an earlier generation than the
+    /// last one forgets everything from that generation onwards
+    pub fn record(&mut self, engine: &dyn Engine) {
+        let generation = engine.generation();
+
+        while self
+            .generations
+            .back()
+            .is_some_and(|last| last.generation >= generation)
+        {
+            self.generations.pop_back();
+        }
+
+        let follows = self
+            .generations
+            .back()
+            .is_some_and(|last| last.generation + 1 == generation);
+        let [births, deaths] = match engine.births_and_deaths() {
+            Some([births, deaths]) if follows => [Some(births), Some(deaths)],
+            _ => [None, None],
+        };
+
+        if self.generations.len() == self.capacity {
+            self.generations.pop_front();
+        }
+
+        self.generations.push_back(GenerationStats {
+            generation,
+            population: engine.population(),
+            births,
+            deaths,
+            bounding_box: engine.bounding_box(),
+        });
+    }
+
+    /// For when going back to an earlier generation
+    pub fn forget_after(&mut self, generation: u64) {
+        while self
+            .generations
+            .back()
+            .is_some_and(|last| last.generation > generation)
+        {
+            self.generations.pop_back();
+        }
+    }
+
+    pub fn latest(&self) -> Option<&GenerationStats> {
+        self.generations.back()
+    }
+
+    /// Oldest first
+    pub fn iter(&self) -> impl Iterator<Item = &GenerationStats> + '_ {
+        self.generations.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.generations.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.generations.is_empty()
+    }
+
+    /// The highest population recorded so far
+    pub fn peak_population(&self) -> Option<&GenerationStats> {
+        self.generations.iter().max_by_key(|stats| stats.population)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SparseLife;
+
+    #[test]
+    fn records_every_generation() {
+        let mut sparse = SparseLife::from_cells([[-1, 0], [0, 0], [1, 0]], 0);
+        let mut stats = Statistics::default();
+
+        stats.record(&sparse);
+        sparse.step();
+        stats.record(&sparse);
+
+        let first = stats.iter().next().unwrap();
+        assert_eq!(first.population, 3);
+        assert_eq!(first.births, None);
+        assert_eq!(first.bounding_box, Some([-1, 0, 1, 0]));
+
+        let latest = stats.latest().unwrap();
+        assert_eq!(latest.generation, 1);
+        assert_eq!((latest.births, latest.deaths), (Some(2), Some(2)));
+        assert_eq!(latest.bounding_box, Some([0, -1, 0, 1]));
+    }
+
+    #[test]
+    fn going_back_forgets_the_later_generations() {
+        let mut sparse = SparseLife::from_cells([[0, 0], [1, 0], [0, 1], [1, 1]], 0);
+        let mut stats = Statistics::new(3);
+
+        for _ in 0..5 {
+            stats.record(&sparse);
+            sparse.step();
+        }
+        assert_eq!(stats.len(), 3);
+        assert_eq!(stats.iter().next().unwrap().generation, 2);
+
+        sparse.set_generation(3);
+        stats.record(&sparse);
+        assert_eq!(
+            stats
+                .iter()
+                .map(|stats| stats.generation)
+                .collect::<Vec<_>>(),
+            vec![2, 3]
+        );
+    }
+}
@@ -0,0 +1,328 @@
+/*!
+Stochastic rules, where every neighbor count has a chance of a dead cell
+being born and of a live one surviving instead of always doing one or the other
+
+They are written like B/S rules with the chance in percent after a colon,
+counts without one always happen, and the seed goes at the end after an `@`,
+e.g. `B3,6:0.5/S2:99,3:99@42`. Generations rules get their states before the seed,
+like `B3/S2:50,3/C4@7`.
+
+Whether a cell is born or survives only depends on the seed, the generation
+and where the cell is, so the same seed always gives the same run,
+whatever engine runs it and however many times it is stepped back and forth
+*/
+
+use std::fmt;
+
+use super::lattice::SQUARE_NEIGHBORS;
+use super::{Life, Random, RuleError};
+
+/// Chances are kept in hundredths of a percent so that rules can still be compared and hashed
+const CERTAIN: u16 = 10_000;
+
+/// For spreading the generation and the coordinates all over the bits of the seed
+const GENERATION_MIX: u64 = 0x9E37_79B9_7F4A_7C15;
+const X_MIX: u64 = 0xC2B2_AE3D_27D4_EB4F;
+const Y_MIX: u64 = 0x1656_67B1_9E37_79F9;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Stochastic {
+    /// The chance of a dead cell with `n` live neighbors being born, in hundredths of a percent
+    birth: [u16; 9],
+    /// The chance of a live cell with `n` live neighbors surviving, in hundredths of a percent
+    survival: [u16; 9],
+    seed: u64,
+}
+
+impl Stochastic {
+    /// The chances are in hundredths of a percent, anything over 100% is taken as 100%
+    pub const fn new(birth: [u16; 9], survival: [u16; 9], seed: u64) -> Self {
+        let mut stochastic = Self {
+            birth,
+            survival,
+            seed,
+        };
+        let mut count = 0;
+        while count < 9 {
+            if birth[count] > CERTAIN {
+                stochastic.birth[count] = CERTAIN;
+            }
+            if survival[count] > CERTAIN {
+                stochastic.survival[count] = CERTAIN;
+            }
+            count += 1;
+        }
+        stochastic
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// The same chances with another seed
+    pub fn with_seed(self, seed: u64) -> Self {
+        Self { seed, ..self }
+    }
+
+    /// From 0 to 1
+    pub fn birth_chance(&self, neighbors: u8) -> f64 {
+        self.birth[neighbors as usize] as f64 / CERTAIN as f64
+    }
+
+    /// From 0 to 1
+    pub fn survival_chance(&self, neighbors: u8) -> f64 {
+        self.survival[neighbors as usize] as f64 / CERTAIN as f64
+    }
+
+    /// Dead cells with no neighbors at all can be born
+    pub fn has_b0(&self) -> bool {
+        self.birth[0] != 0
+    }
+
+    /// Whether the cell at `x`, `y` with `neighbors` live neighbors
+    /// is alive after `generation`
+    pub fn next(&self, alive: bool, neighbors: u8, generation: u64, [x, y]: [i64; 2]) -> bool {
+        let chance = match alive {
+            true => self.survival[neighbors as usize],
+            false => self.birth[neighbors as usize],
+        };
+
+        match chance {
+            0 => false,
+            CERTAIN => true,
+            _ => {
+                let mut random = Random::new(self.generation_seed(generation));
+                let seed = random.next_u64()
+                    ^ (x as u64).wrapping_mul(X_MIX)
+                    ^ (y as u64).wrapping_mul(Y_MIX);
+                Random::new(seed).next_u64() % (CERTAIN as u64) < chance as u64
+            }
+        }
+    }
+
+    /// What every cell of `generation` takes its chances with
+    pub fn generation_seed(&self, generation: u64) -> u64 {
+        Random::new(self.seed ^ generation.wrapping_mul(GENERATION_MIX)).next_u64()
+    }
+
+    /// Takes `B`, `S` and optionally `C` parts, in any case, and then `@` and the seed
+    /// if it isn't 0, along with how many states there are
+    pub(super) fn parse(rulestring: &str) -> Result<(Self, u8), RuleError> {
+        let (rulestring, seed) = match rulestring.split_once('@') {
+            Some((rulestring, seed)) => (
+                rulestring,
+                seed.trim().parse().map_err(|_| RuleError::InvalidFormat)?,
+            ),
+            None => (rulestring, 0),
+        };
+
+        let mut birth = None;
+        let mut survival = None;
+        let mut states = 2;
+        for part in rulestring.split('/') {
+            let part = part.trim();
+            let Some(letter) = part.chars().next() else {
+                return Err(RuleError::InvalidFormat);
+            };
+
+            let repeated = match letter.to_ascii_uppercase() {
+                'B' => birth.replace(parse_chances(&part[1..])?).is_some(),
+                'S' => survival.replace(parse_chances(&part[1..])?).is_some(),
+                'C' => {
+                    states = match part[1..].parse() {
+                        Ok(states @ 2..) => states,
+                        _ => return Err(RuleError::InvalidStateCount),
+                    };
+                    false
+                }
+                _ => return Err(RuleError::InvalidFormat),
+            };
+            if repeated {
+                return Err(RuleError::InvalidFormat);
+            }
+        }
+
+        match (birth, survival) {
+            (Some(birth), Some(survival)) => Ok((Self::new(birth, survival, seed), states)),
+            _ => Err(RuleError::InvalidFormat),
+        }
+    }
+
+    pub(super) fn write(&self, f: &mut fmt::Formatter<'_>, states: u8) -> fmt::Result {
+        write!(
+            f,
+            "B{}/S{}",
+            write_chances(self.birth),
+            write_chances(self.survival)
+        )?;
+        if states > 2 {
+            write!(f, "/C{}", states)?;
+        }
+        write!(f, "@{}", self.seed)
+    }
+}
+
+/// Counts split by commas, or all in a row if none has a chance
+fn parse_chances(part: &str) -> Result<[u16; 9], RuleError> {
+    let mut chances = [0; 9];
+    if part.is_empty() {
+        return Ok(chances);
+    }
+
+    let counts: Vec<&str> = if part.contains([',', ':']) {
+        part.split(',').collect()
+    } else {
+        part.char_indices()
+            .map(|(index, character)| &part[index..index + character.len_utf8()])
+            .collect()
+    };
+
+    for count in counts {
+        let (count, chance) = match count.split_once(':') {
+            Some((count, percent)) => match percent.trim().parse::<f64>() {
+                Ok(percent) if (0.0..=100.0).contains(&percent) => {
+                    (count, (percent * 100.0).round() as u16)
+                }
+                _ => return Err(RuleError::InvalidFormat),
+            },
+            None => (count, CERTAIN),
+        };
+
+        let count = count.trim();
+        match count.parse::<usize>() {
+            Ok(neighbors @ 0..=8) => chances[neighbors] = chance,
+            _ => {
+                let character = count.chars().next().unwrap_or(' ');
+                return Err(RuleError::InvalidNeighborCount(character));
+            }
+        }
+    }
+    Ok(chances)
+}
+
+fn write_chances(chances: [u16; 9]) -> String {
+    let counts: Vec<String> = (0..9)
+        .filter(|count| chances[*count] != 0)
+        .map(|count| match chances[count] {
+            CERTAIN => count.to_string(),
+            chance => format!("{}:{}", count, chance as f64 / 100.0),
+        })
+        .collect();
+
+    if counts.iter().all(|count| count.len() == 1) {
+        counts.concat()
+    } else {
+        counts.join(",")
+    }
+}
+
+impl Life {
+    /// [`Life::step`] for stochastic rules, every cell takes its chances
+    pub(super) fn step_stochastic(&mut self, stochastic: Stochastic) {
+        self.step_cells(|life, [x, y], state| {
+            life.rule.next_state(state, || {
+                let neighbors = SQUARE_NEIGHBORS
+                    .iter()
+                    .filter(|[offset_x, offset_y]| life.state_at(x + offset_x, y + offset_y) == 1)
+                    .count();
+                stochastic.next(state == 1, neighbors as u8, life.generation, [x, y])
+            })
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Engine, Rule, SparseLife};
+
+    #[test]
+    fn parses_and_prints_back() {
+        let rule: Rule = "B3,6:0.5/S2:99,3:99@42".parse().unwrap();
+        let stochastic = rule.stochastic().unwrap();
+        assert_eq!(stochastic.seed(), 42);
+        assert_eq!(stochastic.birth_chance(3), 1.0);
+        assert_eq!(stochastic.birth_chance(6), 0.005);
+        assert_eq!(stochastic.survival_chance(2), 0.99);
+        assert_eq!(rule.to_string(), "B3,6:0.5/S2:99,3:99@42");
+
+        // Certain counts all in a row like usual, and the seed is always written
+        let rule: Rule = "b36/s2:50,3/c4".parse().unwrap();
+        assert_eq!(rule.states(), 4);
+        assert_eq!(rule.to_string(), "B36/S2:50,3/C4@0");
+
+        assert_eq!(
+            "B3:101/S23@1".parse::<Rule>(),
+            Err(RuleError::InvalidFormat)
+        );
+        assert_eq!(
+            "B9:50/S23".parse::<Rule>(),
+            Err(RuleError::InvalidNeighborCount('9'))
+        );
+        assert_eq!(
+            "B3é/S23@1".parse::<Rule>(),
+            Err(RuleError::InvalidNeighborCount('é'))
+        );
+    }
+
+    #[test]
+    fn same_seed_same_run() {
+        let rule: Rule = "B3:50,4:50/S2:90,3:90@7".parse().unwrap();
+        let soup = crate::Soup {
+            seed: 5,
+            density: 0.4,
+            ..Default::default()
+        };
+
+        let run = |rule: Rule| {
+            let mut life = Life::new(32, 32);
+            life.set_rule(rule).unwrap();
+            for [x, y] in soup.cells([0, 0, 31, 31]) {
+                life.set(x as usize, y as usize, true);
+            }
+            life.step_n(20);
+            life
+        };
+
+        let life = run(rule.clone());
+        assert_eq!(life.cells(), run(rule.clone()).cells());
+        let other = rule.stochastic().unwrap().with_seed(8);
+        assert_ne!(life.cells(), run(Rule::from_stochastic(other)).cells());
+
+        // An infinite plane takes the same chances for the same cells,
+        // as long as the grid's edges are too far away to matter
+        let mut grid = Life::new(96, 96);
+        grid.set_rule(rule.clone()).unwrap();
+        let cells = soup.cells([32, 32, 63, 63]);
+        for [x, y] in &cells {
+            grid.set(*x as usize, *y as usize, true);
+        }
+        let mut sparse = SparseLife::from_cells(cells, 0);
+        sparse.set_rule(rule).unwrap();
+
+        grid.step_n(10);
+        sparse.step_n(10);
+        let mut cells = sparse.live_cells();
+        cells.sort_unstable_by_key(|[x, y]| (*y, *x));
+        let grid_cells: Vec<_> = (0..96)
+            .flat_map(|y| (0..96).map(move |x| [x, y]))
+            .filter(|[x, y]| grid.get(*x as usize, *y as usize))
+            .collect();
+        assert_eq!(cells, grid_cells);
+    }
+
+    #[test]
+    fn certain_chances_are_plain_life() {
+        let mut chancy = Life::new(16, 16);
+        chancy.set_rule("B3/S2,3@99".parse().unwrap()).unwrap();
+        let mut plain = Life::new(16, 16);
+        for (column, row) in [(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)] {
+            chancy.set(column, row, true);
+            plain.set(column, row, true);
+        }
+
+        chancy.step_n(12);
+        plain.step_n(12);
+        assert_eq!(chancy.cells(), plain.cells());
+    }
+}
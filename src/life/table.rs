@@ -0,0 +1,626 @@
+/*!
+Rule tables, Golly's `.rule` files, which list what state a cell goes to
+for the states of it and its neighbors
+
+Only the sections that matter here are read, the rest is skipped:
+- `@RULE` followed by the name of the rule
+- `@TABLE` with `n_states:`, `neighborhood:` (`Moore`, `vonNeumann` or `hexagonal`)
+  and `symmetries:` lines, then the variables like `var a={0,1,2}`
+  and the transitions, one per line like `0,1,2,0,0,0,0,0,0,1`.
+  Any variable used more than once in a transition has to be the same state every time
+- `@COLORS` with `state red green blue` lines, or a `red green blue red green blue`
+  one for a gradient over every state but 0
+
+The transitions are gone through in order and the first one to match decides,
+cells that match none stay as they are.
+Hexagonal neighborhoods are Golly's, on squares with the top right and bottom left left out
+*/
+
+use std::collections::HashMap;
+use std::fmt;
+
+use super::{Engine, Life, SparseLife};
+
+/// Golly lists the neighbors going clockwise from the top, and so do these
+const MOORE: [[i64; 2]; 8] = [
+    [0, -1],
+    [1, -1],
+    [1, 0],
+    [1, 1],
+    [0, 1],
+    [-1, 1],
+    [-1, 0],
+    [-1, -1],
+];
+const VON_NEUMANN: [[i64; 2]; 4] = [[0, -1], [1, 0], [0, 1], [-1, 0]];
+const HEXAGONAL: [[i64; 2]; 6] = [[0, -1], [1, 0], [1, 1], [0, 1], [-1, 0], [-1, -1]];
+
+/// The biggest neighborhood plus the cell itself
+const MAX_CELLS: usize = 9;
+
+/// The states of a cell and its neighbors, in the order of the table
+type Cells = [u8; MAX_CELLS];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TableError {
+    /// Nothing to run without a `@TABLE`
+    NoTable,
+    /// The line with this number (counting from 1) makes no sense
+    InvalidLine(usize),
+    /// A neighborhood or symmetries that are not supported
+    Unsupported(String),
+}
+
+impl fmt::Display for TableError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NoTable => write!(f, "there is no @TABLE in the file"),
+            Self::InvalidLine(line) => write!(f, "line {} makes no sense", line),
+            Self::Unsupported(what) => write!(f, "{} is not supported", what),
+        }
+    }
+}
+
+impl std::error::Error for TableError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Term {
+    State(u8),
+    /// The index of the variable
+    Variable(usize),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct Transition {
+    /// The cell itself and then its neighbors
+    inputs: Vec<Term>,
+    output: Term,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct RuleTable {
+    name: String,
+    states: u8,
+    /// Where the neighbors are relative to the cell, in the order the transitions have them
+    neighbors: &'static [[i64; 2]],
+    /// Every order the neighbors can be looked at in and still match,
+    /// `None` for any order at all
+    symmetries: Option<Vec<Vec<usize>>>,
+    /// Bit `n` of a variable is set if it can be state `n`
+    variables: Vec<[u64; 4]>,
+    transitions: Vec<Transition>,
+    colors: Vec<Option<[u8; 3]>>,
+    /// The whole file, to be able to save it along with the cells
+    source: String,
+}
+
+impl RuleTable {
+    pub fn parse(source: &str) -> Result<Self, TableError> {
+        enum Section {
+            Rule,
+            Table,
+            Colors,
+            Other,
+        }
+
+        let mut table = Self {
+            name: String::new(),
+            states: 0,
+            neighbors: &MOORE,
+            symmetries: None,
+            variables: Vec::new(),
+            transitions: Vec::new(),
+            colors: Vec::new(),
+            source: source.to_string(),
+        };
+        let mut section = Section::Other;
+        let mut found_table = false;
+        let mut symmetries = "none";
+        let mut names: HashMap<&str, usize> = HashMap::new();
+        let mut gradient = None;
+        let mut colors = Vec::new();
+
+        for (index, line) in source.lines().enumerate() {
+            let invalid = || TableError::InvalidLine(index + 1);
+            let line = line.split('#').next().unwrap_or_default().trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            if let Some(header) = line.strip_prefix('@') {
+                let (kind, rest) = header
+                    .split_once(char::is_whitespace)
+                    .unwrap_or((header, ""));
+                section = match kind {
+                    "RULE" => {
+                        table.name = rest.trim().to_string();
+                        Section::Rule
+                    }
+                    "TABLE" => {
+                        found_table = true;
+                        Section::Table
+                    }
+                    "COLORS" => Section::Colors,
+                    _ => Section::Other,
+                };
+                continue;
+            }
+
+            match section {
+                Section::Rule | Section::Other => {}
+                Section::Colors => {
+                    let numbers = line
+                        .split_whitespace()
+                        .map(|number| number.parse::<u8>().map_err(|_| invalid()))
+                        .collect::<Result<Vec<_>, _>>()?;
+                    match numbers[..] {
+                        [state, red, green, blue] => colors.push((state, [red, green, blue])),
+                        [r1, g1, b1, r2, g2, b2] => gradient = Some([[r1, g1, b1], [r2, g2, b2]]),
+                        _ => return Err(invalid()),
+                    }
+                }
+                Section::Table => {
+                    if let Some(states) = line.strip_prefix("n_states:") {
+                        table.states = match states.trim().parse() {
+                            Ok(states @ 2..) => states,
+                            _ => return Err(invalid()),
+                        };
+                    } else if let Some(neighborhood) = line.strip_prefix("neighborhood:") {
+                        table.neighbors = match neighborhood.trim() {
+                            "Moore" => &MOORE,
+                            "vonNeumann" => &VON_NEUMANN,
+                            "hexagonal" => &HEXAGONAL,
+                            other => {
+                                return Err(TableError::Unsupported(format!(
+                                    "the {} neighborhood",
+                                    other
+                                )))
+                            }
+                        };
+                    } else if let Some(listed) = line.strip_prefix("symmetries:") {
+                        symmetries = listed.trim();
+                    } else if let Some(variable) = line.strip_prefix("var ") {
+                        let (name, values) = variable.split_once('=').ok_or_else(invalid)?;
+                        let values = values
+                            .trim()
+                            .strip_prefix('{')
+                            .and_then(|values| values.strip_suffix('}'))
+                            .ok_or_else(invalid)?;
+
+                        let mut states = [0; 4];
+                        for value in values.split(',') {
+                            match table.term(value.trim(), &names).ok_or_else(invalid)? {
+                                Term::State(state) => {
+                                    states[state as usize / 64] |= 1 << (state % 64)
+                                }
+                                Term::Variable(other) => {
+                                    for (states, others) in
+                                        states.iter_mut().zip(table.variables[other])
+                                    {
+                                        *states |= others;
+                                    }
+                                }
+                            }
+                        }
+
+                        names.insert(name.trim(), table.variables.len());
+                        table.variables.push(states);
+                    } else {
+                        table
+                            .transitions
+                            .push(table.transition(line, &names).ok_or_else(invalid)?);
+                    }
+                }
+            }
+        }
+
+        if !found_table || table.states == 0 {
+            return Err(TableError::NoTable);
+        }
+
+        table.symmetries = symmetry_orders(symmetries, table.neighbors.len())
+            .ok_or_else(|| TableError::Unsupported(format!("the {} symmetries", symmetries)))?;
+
+        table.colors = vec![None; table.states as usize];
+        if let Some([from, to]) = gradient {
+            let last = (table.states - 1).max(2) as f32 - 1.0;
+            for state in 1..table.states {
+                let t = (state - 1) as f32 / last;
+                table.colors[state as usize] = Some(std::array::from_fn(|channel| {
+                    (from[channel] as f32 + (to[channel] as f32 - from[channel] as f32) * t) as u8
+                }));
+            }
+        }
+        for (state, color) in colors {
+            if let Some(slot) = table.colors.get_mut(state as usize) {
+                *slot = Some(color);
+            }
+        }
+
+        Ok(table)
+    }
+
+    /// A state or the name of a variable
+    fn term(&self, token: &str, names: &HashMap<&str, usize>) -> Option<Term> {
+        match token.parse::<u8>() {
+            Ok(state) if state < self.states => Some(Term::State(state)),
+            Ok(_) => None,
+            Err(_) => names.get(token).map(|index| Term::Variable(*index)),
+        }
+    }
+
+    /// Either comma separated or, with up to 10 states, every state being a single digit
+    fn transition(&self, line: &str, names: &HashMap<&str, usize>) -> Option<Transition> {
+        let tokens: Vec<&str> = if line.contains(',') {
+            line.split(',').map(str::trim).collect()
+        } else if self.states <= 10 {
+            (0..line.len())
+                .map(|index| line.get(index..index + 1))
+                .collect::<Option<_>>()?
+        } else {
+            line.split_whitespace().collect()
+        };
+        if tokens.len() != self.neighbors.len() + 2 {
+            return None;
+        }
+
+        let mut terms = tokens
+            .iter()
+            .map(|token| self.term(token, names))
+            .collect::<Option<Vec<_>>>()?;
+        let output = terms.pop()?;
+
+        // The output can only be a variable that is already bound to something
+        if let Term::Variable(_) = output {
+            if !terms.contains(&output) {
+                return None;
+            }
+        }
+
+        Some(Transition {
+            inputs: terms,
+            output,
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn states(&self) -> u8 {
+        self.states
+    }
+
+    /// Where the neighbors are relative to a cell
+    pub fn neighbors(&self) -> &'static [[i64; 2]] {
+        self.neighbors
+    }
+
+    /// The colors `@COLORS` gave to every state, if it gave one
+    pub fn colors(&self) -> &[Option<[u8; 3]>] {
+        &self.colors
+    }
+
+    /// What the file looked like
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// The next state of a cell, `cells` having its state and then the ones of its neighbors
+    pub fn next(&self, cells: &[u8]) -> u8 {
+        self.transitions
+            .iter()
+            .find_map(|transition| self.apply(transition, cells))
+            .unwrap_or(cells[0])
+    }
+
+    fn apply(&self, transition: &Transition, cells: &[u8]) -> Option<u8> {
+        let mut bound = vec![None; self.variables.len()];
+        if !self.matches(transition.inputs[0], cells[0], &mut bound) {
+            return None;
+        }
+
+        let bound = match &self.symmetries {
+            Some(orders) => orders.iter().find_map(|order| {
+                let mut bound = bound.clone();
+                let matched = order.iter().enumerate().all(|(term, neighbor)| {
+                    self.matches(transition.inputs[term + 1], cells[neighbor + 1], &mut bound)
+                });
+                matched.then_some(bound)
+            })?,
+            None => {
+                let mut used = [false; MAX_CELLS];
+                let mut bound = bound;
+                if !self.permuted(&transition.inputs[1..], &cells[1..], &mut used, &mut bound) {
+                    return None;
+                }
+                bound
+            }
+        };
+
+        match transition.output {
+            Term::State(state) => Some(state),
+            Term::Variable(variable) => bound[variable],
+        }
+    }
+
+    /// Whether `state` fits `term`, binding its variable if it wasn't already
+    fn matches(&self, term: Term, state: u8, bound: &mut [Option<u8>]) -> bool {
+        match term {
+            Term::State(expected) => expected == state,
+            Term::Variable(variable) => match bound[variable] {
+                Some(value) => value == state,
+                None if self.variables[variable][state as usize / 64] >> (state % 64) & 1 == 1 => {
+                    bound[variable] = Some(state);
+                    true
+                }
+                None => false,
+            },
+        }
+    }
+
+    /// Whether every term can go with a different one of the neighbors not `used` yet
+    fn permuted(
+        &self,
+        terms: &[Term],
+        neighbors: &[u8],
+        used: &mut [bool; MAX_CELLS],
+        bound: &mut Vec<Option<u8>>,
+    ) -> bool {
+        let Some((term, rest)) = terms.split_first() else {
+            return true;
+        };
+
+        for (index, state) in neighbors.iter().enumerate() {
+            if used[index] {
+                continue;
+            }
+
+            let mut tried = bound.clone();
+            if self.matches(*term, *state, &mut tried) {
+                used[index] = true;
+                if self.permuted(rest, neighbors, used, &mut tried) {
+                    *bound = tried;
+                    return true;
+                }
+                used[index] = false;
+            }
+        }
+        false
+    }
+}
+
+/// The orders the neighbors can be looked at in for the symmetries, `Some(None)` for
+/// `permute`, going around the ring of `count` neighbors and mirroring it
+fn symmetry_orders(symmetries: &str, count: usize) -> Option<Option<Vec<Vec<usize>>>> {
+    if symmetries == "permute" {
+        return Some(None);
+    }
+
+    let (rotations, reflect) = match symmetries {
+        "none" => (1, false),
+        "reflect_horizontal" => (1, true),
+        _ => {
+            let rotations = symmetries.strip_prefix("rotate")?;
+            let (rotations, reflect) = match rotations.strip_suffix("reflect") {
+                Some(rotations) => (rotations, true),
+                None => (rotations, false),
+            };
+            (rotations.parse::<usize>().ok()?, reflect)
+        }
+    };
+    if rotations == 0 || !count.is_multiple_of(rotations) {
+        return None;
+    }
+
+    let step = count / rotations;
+    let mut orders = Vec::new();
+    for rotation in 0..rotations {
+        let turned = |index: usize| (index + rotation * step) % count;
+        orders.push((0..count).map(turned).collect());
+        if reflect {
+            orders.push(
+                (0..count)
+                    .map(|index| turned((count - index) % count))
+                    .collect(),
+            );
+        }
+    }
+    Some(Some(orders))
+}
+
+/// The next state of the cell at `x`, `y` with whatever `state` says is around it,
+/// remembering it in `known` for the next cell with the same around it
+fn next_state(
+    table: &RuleTable,
+    known: &mut HashMap<Cells, u8>,
+    [x, y]: [i64; 2],
+    state: impl Fn(i64, i64) -> u8,
+) -> u8 {
+    let mut around = [0; MAX_CELLS];
+    around[0] = state(x, y);
+    for (cell, [offset_x, offset_y]) in around[1..].iter_mut().zip(table.neighbors()) {
+        *cell = state(x + offset_x, y + offset_y);
+    }
+
+    *known
+        .entry(around)
+        .or_insert_with(|| table.next(&around[..table.neighbors().len() + 1]))
+}
+
+impl Life {
+    /// [`Life::step`] for rule tables
+    pub(super) fn step_table(&mut self, table: &RuleTable) {
+        let mut known = HashMap::new();
+        self.step_cells(|life, cell, _| {
+            next_state(table, &mut known, cell, |x, y| life.state_at(x, y))
+        });
+    }
+}
+
+impl SparseLife {
+    /// [`Engine::step`] for rule tables, only cells that aren't dead
+    /// and the ones around them can change
+    pub(super) fn step_table(&mut self, table: &RuleTable) {
+        let mut known = HashMap::new();
+        self.step_cells(self.around(table.neighbors()), |sparse, cell, _| {
+            next_state(table, &mut known, cell, |x, y| sparse.cell_state(x, y))
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Rule, RuleError, Topology};
+
+    const WIREWORLD: &str = "@RULE WireWorld
+
+@TABLE
+n_states:4
+neighborhood:Moore
+symmetries:rotate8
+
+# 0 empty, 1 electron head, 2 electron tail, 3 wire
+var a={0,1,2,3}
+var b={0,1,2,3}
+var c={0,1,2,3}
+var d={0,1,2,3}
+var e={0,1,2,3}
+var f={0,1,2,3}
+var g={0,1,2,3}
+var h={0,2,3}
+var i={0,2,3}
+var j={0,2,3}
+var k={0,2,3}
+var l={0,2,3}
+var m={0,2,3}
+1,a,b,c,d,e,f,g,h,2
+2,a,b,c,d,e,f,g,h,3
+3,1,h,i,j,k,l,m,b,1
+3,1,1,i,j,k,l,m,h,1
+3,1,h,1,j,k,l,m,i,1
+3,1,h,i,1,k,l,m,j,1
+3,1,h,i,j,1,l,m,k,1
+
+@COLORS
+0 48 48 48
+1 0 128 255
+2 255 255 255
+3 255 128 0
+";
+
+    #[test]
+    fn reads_wireworld() {
+        let table = RuleTable::parse(WIREWORLD).unwrap();
+
+        assert_eq!(table.name(), "WireWorld");
+        assert_eq!(table.states(), 4);
+        assert_eq!(table.colors()[3], Some([255, 128, 0]));
+
+        // A head turns into a tail, and a tail into wire
+        assert_eq!(table.next(&[1, 0, 0, 0, 0, 0, 0, 0, 0]), 2);
+        assert_eq!(table.next(&[2, 3, 3, 0, 0, 0, 0, 0, 0]), 3);
+        // Wire with one or two heads around becomes a head, not with three
+        assert_eq!(table.next(&[3, 0, 0, 0, 1, 0, 0, 0, 0]), 1);
+        assert_eq!(table.next(&[3, 0, 1, 0, 0, 0, 0, 1, 0]), 1);
+        assert_eq!(table.next(&[3, 1, 0, 1, 0, 1, 0, 0, 0]), 3);
+    }
+
+    #[test]
+    fn clones_share_the_table() {
+        let rule = Rule::from_table(RuleTable::parse(WIREWORLD).unwrap());
+        let clone = rule.clone();
+
+        assert_eq!(rule, clone);
+        assert!(std::ptr::eq(rule.table().unwrap(), clone.table().unwrap()));
+    }
+
+    #[test]
+    fn electrons_go_along_the_wire() {
+        let rule = Rule::from_table(RuleTable::parse(WIREWORLD).unwrap());
+
+        // The wire loops around from one side of the grid to the other
+        let mut life = Life::new(6, 3);
+        life.set_rule(rule.clone()).unwrap();
+        life.set_topology(Topology::Torus);
+        for (column, state) in [2, 1, 3, 3, 3, 3].into_iter().enumerate() {
+            life.set_state(column, 1, state);
+        }
+
+        life.step();
+        let states: Vec<u8> = (0..6).map(|column| life.state(column, 1)).collect();
+        assert_eq!(states, vec![3, 2, 1, 3, 3, 3]);
+
+        let mut sparse = SparseLife::new();
+        sparse.set_rule(rule).unwrap();
+        for (x, state) in [2, 1, 3, 3, 3, 3].into_iter().enumerate() {
+            sparse.set_cell_state(x as i64, 0, state);
+        }
+        sparse.step();
+        let states: Vec<u8> = (0..6).map(|x| sparse.cell_state(x, 0)).collect();
+        assert_eq!(states, vec![3, 2, 1, 3, 3, 3]);
+        assert_eq!(sparse.births_and_deaths(), Some([1, 1]));
+    }
+
+    #[test]
+    fn bound_variables_and_permute() {
+        let table = RuleTable::parse(
+            "@TABLE
+n_states:3
+neighborhood:vonNeumann
+symmetries:permute
+var a={1,2}
+# Two neighbors in the same non zero state, wherever they are
+0,a,a,0,0,a
+",
+        )
+        .unwrap();
+
+        assert_eq!(table.next(&[0, 0, 2, 0, 2]), 2);
+        assert_eq!(table.next(&[0, 1, 0, 1, 0]), 1);
+        assert_eq!(table.next(&[0, 1, 2, 0, 0]), 0);
+        assert_eq!(table.next(&[0, 1, 1, 1, 0]), 0);
+    }
+
+    #[test]
+    fn compact_transitions_and_rotations() {
+        let table = RuleTable::parse(
+            "@TABLE
+n_states:2
+neighborhood:vonNeumann
+symmetries:rotate4
+010001
+",
+        )
+        .unwrap();
+
+        // Born with the one neighbor above, or any other side after turning it around
+        assert_eq!(table.next(&[0, 1, 0, 0, 0]), 1);
+        assert_eq!(table.next(&[0, 0, 0, 1, 0]), 1);
+        assert_eq!(table.next(&[0, 1, 1, 0, 0]), 0);
+    }
+
+    #[test]
+    fn rejects_nonsense() {
+        assert_eq!(RuleTable::parse("@RULE Nothing"), Err(TableError::NoTable));
+        assert_eq!(
+            RuleTable::parse("@TABLE\nn_states:2\n0,1,0\n"),
+            Err(TableError::InvalidLine(3))
+        );
+        assert_eq!(
+            RuleTable::parse("@TABLE\nn_states:2\nneighborhood:oneDimensional\n"),
+            Err(TableError::Unsupported(
+                "the oneDimensional neighborhood".to_string()
+            ))
+        );
+
+        // Running forever on an infinite plane if empty cells come alive
+        let b0 = Rule::from_table(RuleTable::parse("@TABLE\nn_states:2\n0000000001\n").unwrap());
+        assert!(b0.has_b0());
+        assert_eq!(
+            SparseLife::new().set_rule(b0),
+            Err(RuleError::B0OnInfinitePlane)
+        );
+    }
+}
@@ -0,0 +1,171 @@
+/*!
+What is right past the edges of a grid
+
+Every topology says where a cell outside of the grid really is,
+the stepper only ever looks one cell past the edges but any distance works
+*/
+
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Topology {
+    /// Everything outside of the grid is dead
+    #[default]
+    Dead,
+    /// Going off one edge brings you back in through the opposite one
+    Torus,
+    /// Like a torus, but going off the top or bottom edge
+    /// flips you left to right
+    KleinBottle,
+    /// Going off any edge flips you along that edge,
+    /// a.k.a. the real projective plane
+    CrossSurface,
+    /// The edges are mirrors, so the cells right outside of the grid
+    /// are copies of the ones right inside of it
+    Reflective,
+}
+
+impl Topology {
+    pub const ALL: [Topology; 5] = [
+        Self::Dead,
+        Self::Torus,
+        Self::KleinBottle,
+        Self::CrossSurface,
+        Self::Reflective,
+    ];
+
+    /// A name for showing to people
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Dead => "Dead Border",
+            Self::Torus => "Torus",
+            Self::KleinBottle => "Klein Bottle",
+            Self::CrossSurface => "Cross-Surface",
+            Self::Reflective => "Reflective",
+        }
+    }
+
+    /// Where the cell at `x`, `y` is in a grid of `columns` by `rows`,
+    /// or `None` if it is outside of the grid and always dead
+    ///
+    /// The rows are dealt with first, so e.g. a corner of a cross-surface
+    /// is flipped along the top or bottom edge before the left or right one
+    pub fn locate(self, x: i64, y: i64, columns: usize, rows: usize) -> Option<(usize, usize)> {
+        if columns == 0 || rows == 0 {
+            return None;
+        }
+
+        let (columns, rows) = (columns as i64, rows as i64);
+        let inside = |value: i64, length: i64| (0..length).contains(&value);
+        let reflect = |value: i64, length: i64| {
+            let value = value.rem_euclid(2 * length);
+            if value < length {
+                value
+            } else {
+                2 * length - 1 - value
+            }
+        };
+
+        let (x, y) = match self {
+            Self::Dead if !inside(x, columns) || !inside(y, rows) => return None,
+            Self::Dead => (x, y),
+            Self::Torus => (x.rem_euclid(columns), y.rem_euclid(rows)),
+            Self::KleinBottle => {
+                let x = if inside(y, rows) {
+                    x
+                } else {
+                    // Every time around flips it once more
+                    let flips = y.div_euclid(rows);
+                    if flips % 2 == 0 {
+                        x
+                    } else {
+                        columns - 1 - x
+                    }
+                };
+                (x.rem_euclid(columns), y.rem_euclid(rows))
+            }
+            Self::CrossSurface => {
+                let (mut x, mut y) = (x, y);
+                if !inside(y, rows) {
+                    x = if y.div_euclid(rows) % 2 == 0 {
+                        x
+                    } else {
+                        columns - 1 - x
+                    };
+                    y = y.rem_euclid(rows);
+                }
+                if !inside(x, columns) {
+                    y = if x.div_euclid(columns) % 2 == 0 {
+                        y
+                    } else {
+                        rows - 1 - y
+                    };
+                    x = x.rem_euclid(columns);
+                }
+                (x, y)
+            }
+            Self::Reflective => (reflect(x, columns), reflect(y, rows)),
+        };
+
+        Some((x as usize, y as usize))
+    }
+}
+
+impl fmt::Display for Topology {
+    /// The name used in files
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Dead => "dead",
+            Self::Torus => "torus",
+            Self::KleinBottle => "klein-bottle",
+            Self::CrossSurface => "cross-surface",
+            Self::Reflective => "reflective",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for Topology {
+    type Err = ();
+
+    fn from_str(name: &str) -> Result<Self, ()> {
+        Self::ALL
+            .into_iter()
+            .find(|topology| topology.to_string() == name)
+            .ok_or(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn inside_is_left_alone() {
+        for topology in Topology::ALL {
+            assert_eq!(topology.locate(2, 3, 5, 4), Some((2, 3)));
+        }
+    }
+
+    #[test]
+    fn just_outside() {
+        use Topology::*;
+
+        assert_eq!(Dead.locate(-1, 0, 5, 4), None);
+        assert_eq!(Torus.locate(-1, 4, 5, 4), Some((4, 0)));
+        assert_eq!(KleinBottle.locate(1, -1, 5, 4), Some((3, 3)));
+        assert_eq!(KleinBottle.locate(5, 1, 5, 4), Some((0, 1)));
+        assert_eq!(CrossSurface.locate(5, 1, 5, 4), Some((0, 2)));
+        assert_eq!(CrossSurface.locate(1, 4, 5, 4), Some((3, 0)));
+        assert_eq!(Reflective.locate(-1, 4, 5, 4), Some((0, 3)));
+    }
+
+    #[test]
+    fn names_parse_back() {
+        for topology in Topology::ALL {
+            assert_eq!(topology.to_string().parse(), Ok(topology));
+        }
+        assert_eq!("moebius".parse::<Topology>(), Err(()));
+    }
+}
@@ -0,0 +1,89 @@
+use super::*;
+
+/// What the application is simulating,
+/// either a grid with borders or the whole plane
+#[derive(Debug, Clone)]
+pub enum Universe {
+    Bounded(Life),
+    Infinite(SparseLife),
+}
+
+impl Universe {
+    pub fn engine(&self) -> &dyn Engine {
+        match self {
+            Self::Bounded(life) => life,
+            Self::Infinite(sparse) => sparse,
+        }
+    }
+
+    pub fn engine_mut(&mut self) -> &mut dyn Engine {
+        match self {
+            Self::Bounded(life) => life,
+            Self::Infinite(sparse) => sparse,
+        }
+    }
+
+    /// An infinite plane copied over to a [`HashLife`], which gets far ahead
+    /// a lot faster, if it can run the rule
+    pub fn to_hashlife(&self) -> Option<HashLife> {
+        let Self::Infinite(sparse) = self else {
+            return None;
+        };
+
+        let mut hashlife = HashLife::new();
+        hashlife.set_rule(sparse.rule()).ok()?;
+        for [x, y] in sparse.live_cells() {
+            hashlife.set_cell(x, y, true);
+        }
+        hashlife.set_generation(sparse.generation());
+        Some(hashlife)
+    }
+
+    /// The infinite plane a [`HashLife`] got to
+    pub fn from_hashlife(hashlife: &HashLife) -> Self {
+        let mut sparse = SparseLife::from_cells(hashlife.live_cells(), hashlife.generation());
+        sparse.set_rule(hashlife.rule()).unwrap();
+        Self::Infinite(sparse)
+    }
+
+    pub fn is_infinite(&self) -> bool {
+        matches!(self, Self::Infinite(_))
+    }
+
+    /// Moves the cells over to an infinite plane, the generation count and rule are kept,
+    /// and so are the states of dying cells
+    ///
+    /// Grids with a B0 rule stay as they are, see [`RuleError::B0OnInfinitePlane`]
+    pub fn into_infinite(self) -> Self {
+        match self {
+            Self::Bounded(life) if !life.rule().has_b0() => {
+                let mut sparse = SparseLife::from_cells(life.live_cells(), life.generation());
+                sparse.set_rule(life.rule()).unwrap();
+                for ([x, y], state) in Engine::dying_cells(&life) {
+                    sparse.set_cell_state(x, y, state);
+                }
+                Self::Infinite(sparse)
+            }
+            universe => universe,
+        }
+    }
+
+    /// Moves the cells over to a grid, only the ones that fit in it are kept
+    pub fn into_bounded(self, columns: usize, rows: usize) -> Self {
+        match self {
+            Self::Infinite(sparse) => {
+                let mut life = Life::new(columns, rows);
+                life.set_rule(sparse.rule()).unwrap();
+                for [x, y] in sparse.live_cells() {
+                    Engine::set_cell(&mut life, x, y, true);
+                }
+                for ([x, y], state) in sparse.dying_cells() {
+                    Engine::set_cell_state(&mut life, x, y, state);
+                }
+                life.set_generation(sparse.generation());
+                Self::Bounded(life)
+            }
+            bounded => bounded,
+        }
+    }
+}
@@ -5,6 +5,8 @@ use grid_drawer::*;
 mod gui;
 use gui::*;
 
+mod headless;
+
 mod settings;
 use settings::*;
 
@@ -19,11 +21,26 @@ use wgpu::*;
 
 use gol::*;
 
+use std::time::{Duration, Instant};
+
 const TRANSLATION_CONSTANT: f32 = 0.001;
 const ZOOMING_CONSTANT: f32 = 0.05;
+/// How long jumping ahead gets every frame, so the window keeps responding
+const JUMP_BUDGET: Duration = Duration::from_millis(30);
 
 fn main() {
     env_logger::init();
+
+    // Anything else is left to winit, which doesn't take arguments anyway
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().map(String::as_str) == Some("search") {
+        if let Err(error) = headless::run_search(&args[1..]) {
+            eprintln!("{}", error);
+            std::process::exit(1);
+        }
+        return;
+    }
+
     let event_loop = EventLoop::new();
     let window = WindowBuilder::new()
         .with_title("Conway's Game of Life")
@@ -42,11 +59,18 @@ fn main() {
 
     let mut gui = Gui::new(&window, &wgpu_state);
 
+    let mut timer = UpdateTimer::new();
+    let mut last_update = Instant::now();
+
     let mut last_cursor: Option<PhysicalPosition<f64>> = None;
     let mut mouse_held = false;
     let mut ctrl = false;
+    let mut shift = false; // Shift + left click toggles squares instead of panning
     let mut color_change = false; // If it is true then you should not be able to pan
 
+    // What the dragged squares are being set to and the last square that was painted
+    let mut painting: Option<(bool, [i64; 2])> = None;
+
     event_loop.run(move |event, _, control_flow| {
         match event {
             Event::RedrawRequested(window_id) if window_id == window.id() => {
@@ -65,9 +89,19 @@ fn main() {
                             &surface_texture,
                         ));
 
+                        // The grid stays as it was until the jump is over
+                        if timer.jump().is_none() {
+                            grid.sync_squares(&settings);
+                        }
                         results.push(grid.draw(&surface_texture));
 
-                        color_change = gui.draw(&window, &surface_texture, &mut grid, &mut settings);
+                        color_change = gui.draw(
+                            &window,
+                            &surface_texture,
+                            &mut grid,
+                            &mut settings,
+                            &mut timer,
+                        );
 
                         if !results.iter().any(|result| result.is_err()) {
                             surface_texture.present()
@@ -85,6 +119,31 @@ fn main() {
             }
 
             Event::MainEventsCleared => {
+                let now = Instant::now();
+                let updates = timer.tick(now - last_update, settings.updates_sec());
+                last_update = now;
+
+                if let Some(jump) = timer.jump_mut() {
+                    grid.catch_up(&mut settings);
+                    if settings.advance_jump(jump, JUMP_BUDGET) {
+                        timer.set_jump(None);
+                    }
+                } else {
+                    let on_gpu =
+                        settings.gpu_stepping() && grid.step_on_gpu(&mut settings, updates);
+                    if !on_gpu {
+                        grid.catch_up(&mut settings);
+                        if settings.step_n(updates) {
+                            timer.set_paused(true);
+                        }
+                    }
+                }
+
+                // Only brought back from the GPU once it stops, never while it is running
+                if timer.paused() {
+                    grid.catch_up(&mut settings);
+                }
+
                 window.request_redraw();
             }
 
@@ -106,26 +165,41 @@ fn main() {
                     grid.resize_window(**new_inner_size);
                 }
 
-                WindowEvent::MouseInput { state, button, .. } => {
-                    if let MouseButton::Left = button {
-                        if let ElementState::Pressed = state {
-                            mouse_held = true
-                        } else {
-                            mouse_held = false
+                WindowEvent::MouseInput {
+                    state,
+                    button: MouseButton::Left,
+                    ..
+                } => {
+                    mouse_held = *state == ElementState::Pressed;
+
+                    painting = None;
+
+                    let clicked_square = match last_cursor {
+                        Some(position) if mouse_held && shift && !gui.wants_mouse() => {
+                            grid.square_clicked(position, &settings, &window)
                         }
+                        _ => None,
+                    };
+
+                    if let Some([x, y]) = clicked_square {
+                        grid.catch_up(&mut settings);
+                        settings.toggle_square(x, y);
+
+                        painting = Some((settings.squares().engine().cell(x, y), [x, y]));
                     }
                 }
 
-                WindowEvent::MouseWheel { delta, .. } => {
-                    if let MouseScrollDelta::LineDelta(_, y) = delta {
-                        let dampening_constant = if grid.grid_zoom() <= 0.1 {
-                            ZOOMING_CONSTANT.powf((ZOOMING_CONSTANT / grid.grid_zoom()) * 2.0)
-                        } else {
-                            ZOOMING_CONSTANT
-                        };
+                WindowEvent::MouseWheel {
+                    delta: MouseScrollDelta::LineDelta(_, y),
+                    ..
+                } => {
+                    let dampening_constant = if grid.grid_zoom() <= 0.1 {
+                        ZOOMING_CONSTANT.powf((ZOOMING_CONSTANT / grid.grid_zoom()) * 2.0)
+                    } else {
+                        ZOOMING_CONSTANT
+                    };
 
-                        grid.change_grid_zoom(y * dampening_constant);
-                    }
+                    grid.change_grid_zoom(y * dampening_constant);
                 }
 
                 WindowEvent::CursorMoved {
@@ -138,7 +212,14 @@ fn main() {
                             y: (new_position.y - position.y) as f32,
                         };
 
-                        if mouse_held && !color_change {
+                        if let Some((alive, last_square)) = painting {
+                            let square = grid.square_at(*new_position, &window);
+
+                            grid.catch_up(&mut settings);
+
+                            paint_squares(&mut settings, last_square, square, alive);
+                            painting = Some((alive, square));
+                        } else if mouse_held && !color_change {
                             let zoom_multiplier = if grid.grid_zoom() > 1.0 {
                                 1.0
                             } else {
@@ -154,7 +235,10 @@ fn main() {
                     last_cursor = Some(*new_position);
                 }
 
-                WindowEvent::ModifiersChanged(state) => ctrl = state.ctrl(),
+                WindowEvent::ModifiersChanged(state) => {
+                    ctrl = state.ctrl();
+                    shift = state.shift();
+                }
 
                 WindowEvent::KeyboardInput { input, .. } if ctrl => {
                     if let Some(VirtualKeyCode::R) = input.virtual_keycode {
@@ -172,3 +256,9 @@ fn main() {
         gui.platform_event_handling(&window, &event)
     });
 }
+
+/// Sets every square crossed from `from` to `to`,
+/// the ones outside of a grid with borders are ignored
+fn paint_squares(settings: &mut Settings, from: [i64; 2], to: [i64; 2], alive: bool) {
+    settings.set_squares(squares_between(settings.lattice(), from, to), alive);
+}
@@ -29,10 +29,10 @@ RGBA representing the color of the grid
 How the game should initially look like, which is represented
 in an enum kind of matter:
 - If the setting is fit the grid to screen, then the entire 5 bytes will be
-zeroed
+  zeroed
 - If the setting is to look at the center with a certain amount of
-zoom, the five bytes will look like a zero byte followed
-by an f32 of zoom
+  zoom, the five bytes will look like a zero byte followed
+  by an f32 of zoom
 
 ### Next 4 Bytes
 RGBA representing the color of the square when it's "off"
@@ -40,25 +40,53 @@ RGBA representing the color of the square when it's "off"
 ### Next 4 Bytes
 RGBA representing the color of the square when it's "on"
 
-### Last 7 Bytes
+### Extra Settings
+Any number of `name=value` ASCII strings, each one ended by an empty byte,
+and then one more empty byte. Names that are not known are skipped.
+So far there is:
+- `rule`, the rulestring in B/S notation, e.g. `rule=B36/S23`
+- `rule-table`, instead of `rule` for rules loaded from Golly's `.rule` files,
+  the whole file as it was
+- `topology`, what is past the edges of the grid, one of `dead`, `torus`,
+  `klein-bottle`, `cross-surface` or `reflective`, e.g. `topology=torus`
+- `lattice`, the shape of the cells of the grid, one of `square`, `hexagonal`
+  or `triangular`, e.g. `lattice=hexagonal`
+- `history-limit`, how many bytes the history of past generations can take,
+  in decimal, e.g. `history-limit=67108864`
+- `soup-seed`, `soup-density` and `soup-symmetry`, what the random soup was made with,
+  e.g. `soup-seed=1234`, `soup-density=0.5` and `soup-symmetry=D4`
+- `age-coloring`, whether squares are colored by age, `true` or `false`
+- `show-blocks`, whether the blocks of block rules are drawn apart, `true` or `false`
+- `age-span`, how many generations it takes to go through the age colors, e.g. `age-span=100`
+- `square-color-old` and `square-color-dying`, the colors of the oldest squares and
+  of the ones that just died, as RGBA in hexadecimal, e.g. `square-color-old=466ec8ff`
+- `infinite`, whether the squares are on an infinite plane, `true` or `false`
+- `origin`, where on the infinite plane the top left square goes, e.g. `origin=-10,-3`
+
+Files from before there were extra settings simply have none
+
+### Last 6 Bytes
 Represent the end of the Prelude,
-they should be an empty byte followed by `\gol!/`
-in ASCII encoding, or `0x00` followed by `0x5C 0x67 0x6F 0x6C 0x21 0x2F`
+they should be `\gol!/` in ASCII encoding,
+or `0x5C 0x67 0x6F 0x6C 0x21 0x2F`
 
 ## Main Content
 
-Width of grid * height of grid of booleans
+Width of grid * height of grid of bytes
 representing all the squares.
 All the squares in the top row
-from left to right, then the second row and etcetera
+from left to right, then the second row and etcetera.
+A square is 0 when off and 1 when on, rules from the Generations family
+also have the dying states from 2 up. Anything else that is not 0
+is taken as on, since older files only said whether a square was 0 or not
 
 # Notes
 
 - I decided to make it completely big-endian,
-    that way moving files accross different endianness works
-    since the program will read big-endian regardless of host endianness,
-    but it also helps when seeing the files in an editor to not
-    think in backward bytes
+  that way moving files accross different endianness works
+  since the program will read big-endian regardless of host endianness,
+  but it also helps when seeing the files in an editor to not
+  think in backward bytes
 */
 
 use std::array::TryFromSliceError;
@@ -67,11 +95,38 @@ use std::io::{self, Write};
 use std::path::Path;
 
 use super::*;
+use gol::{Engine, SparseLife};
 
+/// Where the extra settings start
 #[rustfmt::skip]
-const PRELUDE_LENGTH: usize =
+const FIXED_PRELUDE_LENGTH: usize =
 4+4+4+4+
-5+4+4+7;
+5+4+4;
+
+/// The `name=value` pairs of the extra settings
+type Extras<'a> = Vec<(&'a str, &'a str)>;
+
+/// The extra settings along with how many bytes they took, the final empty byte included
+fn parse_extras(bytes: &[u8]) -> Result<(Extras<'_>, usize), GOLFileError> {
+    let mut extras = Vec::new();
+    let mut length = 0;
+
+    loop {
+        let end = bytes[length..]
+            .iter()
+            .position(|byte| *byte == 0)
+            .ok_or(GOLFileError::NotValidFile)?;
+        let extra = &bytes[length..length + end];
+        length += end + 1;
+
+        if extra.is_empty() {
+            return Ok((extras, length));
+        }
+
+        let extra = std::str::from_utf8(extra).map_err(|_| GOLFileError::NotValidFile)?;
+        extras.push(extra.split_once('=').ok_or(GOLFileError::NotValidFile)?);
+    }
+}
 
 #[derive(Debug)]
 pub enum GOLFileError {
@@ -80,6 +135,18 @@ pub enum GOLFileError {
     IOError(io::Error),
 }
 
+impl std::fmt::Display for GOLFileError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NotValidFile => write!(f, "not a valid .gol file"),
+            Self::UnexpectedEndOfBytes => write!(f, "the file ended before all the squares"),
+            Self::IOError(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl std::error::Error for GOLFileError {}
+
 impl From<io::Error> for GOLFileError {
     fn from(error: io::Error) -> Self {
         Self::IOError(error)
@@ -95,9 +162,21 @@ impl From<TryFromSliceError> for GOLFileError {
 impl Settings {
     pub fn read_from_file(path: impl AsRef<Path>) -> Result<Self, GOLFileError> {
         let mut output = Self {
-            squares: Vec::new(),
+            squares: Universe::Bounded(Life::new(0, 0)),
+            jumping: None,
+            history: History::default(),
+            cycles: CycleDetector::new(),
+            stats: Statistics::default(),
+            ages: CellAges::default(),
+            version: 0,
+            detect_cycles: true,
+            stop_on_cycle: false,
+            gpu_stepping: false,
             squares_x: 0,
             squares_y: 0,
+            topology: Topology::Dead,
+            lattice: Lattice::Square,
+            soup: Soup::default(),
             updates_sec: 0.0,
             background_color: RGBA {
                 r: 0,
@@ -118,6 +197,10 @@ impl Settings {
                 b: 0,
                 a: 0,
             },
+            age_coloring: false,
+            square_color_old: Settings::default().square_color_old,
+            square_color_dying: Settings::default().square_color_dying,
+            show_blocks: Settings::default().show_blocks,
         };
 
         let bytes = fs::read(path)?;
@@ -166,44 +249,151 @@ impl Settings {
             a: bytes[28],
         };
 
-        if bytes[29] != 0 {
-            return Err(GOLFileError::NotValidFile);
+        let (extras, extras_length) = parse_extras(&bytes[FIXED_PRELUDE_LENGTH..])?;
+        let prelude_end = FIXED_PRELUDE_LENGTH + extras_length;
+
+        let mut rule = Rule::default();
+        let mut infinite = false;
+        let mut origin = [0, 0];
+        for (name, value) in extras {
+            match name {
+                "rule" => rule = value.parse().map_err(|_| GOLFileError::NotValidFile)?,
+                "rule-table" => {
+                    let table = RuleTable::parse(value).map_err(|_| GOLFileError::NotValidFile)?;
+                    rule = Rule::from_table(table);
+                }
+                "topology" => {
+                    output.topology = value.parse().map_err(|_| GOLFileError::NotValidFile)?
+                }
+                "lattice" => {
+                    output.lattice = value.parse().map_err(|_| GOLFileError::NotValidFile)?
+                }
+                "history-limit" => {
+                    let limit = value.parse().map_err(|_| GOLFileError::NotValidFile)?;
+                    output.history.set_limit(limit);
+                }
+                "soup-seed" => {
+                    output.soup.seed = value.parse().map_err(|_| GOLFileError::NotValidFile)?
+                }
+                "soup-density" => {
+                    output.soup.density = match value.parse() {
+                        Ok(density) if (0.0..=1.0).contains(&density) => density,
+                        _ => return Err(GOLFileError::NotValidFile),
+                    }
+                }
+                "show-blocks" => {
+                    output.show_blocks = value.parse().map_err(|_| GOLFileError::NotValidFile)?
+                }
+                "age-coloring" => {
+                    output.age_coloring = value.parse().map_err(|_| GOLFileError::NotValidFile)?
+                }
+                "age-span" => {
+                    let span = value.parse().map_err(|_| GOLFileError::NotValidFile)?;
+                    output.ages.set_dead_limit(span);
+                }
+                "square-color-old" => {
+                    output.square_color_old =
+                        rgba_from_hex(value).ok_or(GOLFileError::NotValidFile)?
+                }
+                "square-color-dying" => {
+                    output.square_color_dying =
+                        rgba_from_hex(value).ok_or(GOLFileError::NotValidFile)?
+                }
+                "soup-symmetry" => {
+                    output.soup.symmetry = value.parse().map_err(|_| GOLFileError::NotValidFile)?
+                }
+                "infinite" => infinite = value.parse().map_err(|_| GOLFileError::NotValidFile)?,
+                "origin" => origin = parse_origin(value).ok_or(GOLFileError::NotValidFile)?,
+                _ => {}
+            }
         }
 
-        let last_six: [u8; 6] = bytes[30..36].try_into()?; // last of the prelude
+        let last_six: [u8; 6] = bytes
+            .get(prelude_end..prelude_end + 6)
+            .ok_or(GOLFileError::NotValidFile)?
+            .try_into()?; // last of the prelude
 
         if last_six != *b"\\gol!/" {
             return Err(GOLFileError::NotValidFile);
         }
 
-        for _ in 0..output.squares_y {
-            output.squares.push(Vec::new())
+        let columns = output.squares_x as usize;
+        let rows = output.squares_y as usize;
+
+        let squares = &bytes[prelude_end + 6..];
+
+        if squares.len() != columns * rows {
+            return Err(GOLFileError::UnexpectedEndOfBytes);
         }
 
-        let mut row = 0;
-        for (i, byte) in bytes.iter().skip(PRELUDE_LENGTH).enumerate() {
-            output.squares[row].push(*byte != 0); // a.k.a *byte as bool
-            if (i + 1) % output.squares_x as usize == 0 {
-                row += 1
+        let states = rule.states();
+        let state = |byte: u8| match byte {
+            state if state < states => state,
+            _ => 1,
+        };
+
+        if infinite {
+            // Other lattices only work on a grid
+            if output.lattice != Lattice::Square {
+                return Err(GOLFileError::NotValidFile);
+            }
+
+            let mut sparse = SparseLife::new();
+            sparse
+                .set_rule(rule)
+                .map_err(|_| GOLFileError::NotValidFile)?;
+            for (i, byte) in squares.iter().enumerate() {
+                let x = origin[0] + (i % columns) as i64;
+                let y = origin[1] + (i / columns) as i64;
+                Engine::set_cell_state(&mut sparse, x, y, state(*byte));
             }
+
+            output.squares = Universe::Infinite(sparse);
+            output.observe_ages();
+            return Ok(output);
         }
 
-        if output.squares[0].len() * output.squares.len()
-            != output.squares_x as usize * output.squares_y as usize
-        {
-            return Err(GOLFileError::UnexpectedEndOfBytes);
+        let mut life = Life::new(columns, rows);
+        life.set_rule(rule)
+            .map_err(|_| GOLFileError::NotValidFile)?;
+        life.set_topology(output.topology);
+        life.set_lattice(output.lattice)
+            .map_err(|_| GOLFileError::NotValidFile)?;
+
+        for (i, byte) in squares.iter().enumerate() {
+            life.set_state(i % columns, i / columns, state(*byte));
         }
 
+        output.squares = Universe::Bounded(life);
+        output.observe_ages();
+
         Ok(output)
     }
 
+    /// An infinite plane is saved as the smallest grid that has all of its live squares,
+    /// along with where that grid is on the plane
     pub fn write_in_file(&self, path: impl AsRef<Path>) -> Result<(), io::Error> {
+        let engine = self.squares.engine();
+
+        let (origin, columns, rows) = match (&self.squares, engine.bounding_box()) {
+            (Universe::Bounded(_), _) => ([0, 0], self.squares_x, self.squares_y),
+            (Universe::Infinite(_), None) => ([0, 0], 1, 1),
+            (Universe::Infinite(_), Some([min_x, min_y, max_x, max_y])) => {
+                let too_big = || io::Error::new(io::ErrorKind::InvalidData, "pattern too big");
+                (
+                    [min_x, min_y],
+                    u16::try_from(max_x - min_x + 1).map_err(|_| too_big())?,
+                    u16::try_from(max_y - min_y + 1).map_err(|_| too_big())?,
+                )
+            }
+        };
+
         let mut file = File::create(path)?;
 
         file.write_all(b"gol!")?;
 
-        file.write_all(&self.squares_x.to_be_bytes())?;
-        file.write_all(&self.squares_y.to_be_bytes())?;
+        file.write_all(&columns.to_be_bytes())?;
+        file.write_all(&rows.to_be_bytes())?;
 
         file.write_all(&self.updates_sec.to_be_bytes())?;
 
@@ -215,24 +405,78 @@ impl Settings {
 
         file.write_all(&self.square_color_on.to_be_bytes())?;
 
+        match engine.rule().table() {
+            Some(table) => write!(file, "rule-table={}\0", table.source())?,
+            None => write!(file, "rule={}\0", engine.rule())?,
+        }
+        write!(file, "topology={}\0", self.topology)?;
+        write!(file, "lattice={}\0", self.lattice)?;
+        write!(file, "history-limit={}\0", self.history.limit())?;
+        write!(file, "soup-seed={}\0", self.soup.seed)?;
+        write!(file, "soup-density={}\0", self.soup.density)?;
+        write!(file, "soup-symmetry={}\0", self.soup.symmetry)?;
+        write!(file, "age-coloring={}\0", self.age_coloring)?;
+        write!(file, "show-blocks={}\0", self.show_blocks)?;
+        write!(file, "age-span={}\0", self.ages.dead_limit())?;
+        write!(
+            file,
+            "square-color-old={}\0",
+            rgba_to_hex(self.square_color_old)
+        )?;
+        write!(
+            file,
+            "square-color-dying={}\0",
+            rgba_to_hex(self.square_color_dying)
+        )?;
+        write!(file, "infinite={}\0", self.squares.is_infinite())?;
+        write!(file, "origin={},{}\0", origin[0], origin[1])?;
         file.write_all(b"\0")?;
 
         file.write_all(b"\\gol!/")?;
 
-        for row in self.squares.iter() {
-            for square in row.iter() {
-                file.write_all(&[*square as u8])?;
-            }
+        for y in 0..rows as i64 {
+            let bytes: Vec<u8> = (0..columns as i64)
+                .map(|x| engine.cell_state(origin[0] + x, origin[1] + y))
+                .collect();
+            file.write_all(&bytes)?;
         }
 
         Ok(())
     }
 }
 
+fn rgba_to_hex(color: RGBA) -> String {
+    format!(
+        "{:02x}{:02x}{:02x}{:02x}",
+        color.r, color.g, color.b, color.a
+    )
+}
+
+/// `x,y` in decimal
+fn parse_origin(origin: &str) -> Option<[i64; 2]> {
+    let (x, y) = origin.split_once(',')?;
+    Some([x.parse().ok()?, y.parse().ok()?])
+}
+
+fn rgba_from_hex(hex: &str) -> Option<RGBA> {
+    if hex.len() != 8 || !hex.is_ascii() {
+        return None;
+    }
+
+    let channel = |i: usize| u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16).ok();
+    Some(RGBA {
+        r: channel(0)?,
+        g: channel(1)?,
+        b: channel(2)?,
+        a: channel(3)?,
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use fs::remove_file;
+    use gol::{BlockRule, Symmetry};
 
     const FIRST_T_FILE: &str = "first_tier_test.gol";
     const FIRST_T_SIZE: u16 = 5;
@@ -240,7 +484,7 @@ mod tests {
     fn create_write_read_1tier() {
         {
             let mut settings = Settings::default();
-            settings.resize_grid(FIRST_T_SIZE, FIRST_T_SIZE);
+            settings.resize_grid(FIRST_T_SIZE, FIRST_T_SIZE).unwrap();
 
             let mut success = false;
             if let Ok(()) = settings.write_in_file(FIRST_T_FILE) {
@@ -263,7 +507,7 @@ mod tests {
     fn create_write_read_2tier() {
         {
             let mut settings = Settings::default();
-            settings.resize_grid(SECOND_T_SIZE, SECOND_T_SIZE);
+            settings.resize_grid(SECOND_T_SIZE, SECOND_T_SIZE).unwrap();
 
             let mut success = false;
             if let Ok(()) = settings.write_in_file(SECOND_T_FILE) {
@@ -286,7 +530,7 @@ mod tests {
     fn create_write_read_3tier() {
         {
             let mut settings = Settings::default();
-            settings.resize_grid(THIRD_T_SIZE, THIRD_T_SIZE);
+            settings.resize_grid(THIRD_T_SIZE, THIRD_T_SIZE).unwrap();
 
             let mut success = false;
             if let Ok(()) = settings.write_in_file(THIRD_T_FILE) {
@@ -310,7 +554,9 @@ mod tests {
     fn create_write_read_4tier() {
         {
             let mut settings = Settings::default();
-            settings.resize_grid(FOURTH_T_COLUMNS, FOURTH_T_ROWS);
+            settings
+                .resize_grid(FOURTH_T_COLUMNS, FOURTH_T_ROWS)
+                .unwrap();
 
             let mut success = false;
             if let Ok(()) = settings.write_in_file(FOURTH_T_FILE) {
@@ -326,4 +572,354 @@ mod tests {
         }
         remove_file(FOURTH_T_FILE).unwrap();
     }
+
+    const SQUARES_FILE: &str = "squares_test.gol";
+    #[test]
+    fn squares_survive_write_read() {
+        {
+            let mut settings = Settings::default();
+            settings.resize_grid(7, 3).unwrap();
+            settings.toggle_square(0, 0);
+            settings.toggle_square(6, 2);
+            settings.toggle_square(3, 1);
+            settings.toggle_square(7, 1); // outside of the grid
+
+            settings.write_in_file(SQUARES_FILE).unwrap();
+        }
+        {
+            let settings = Settings::read_from_file(SQUARES_FILE).unwrap();
+
+            for row in 0..3 {
+                for column in 0..7 {
+                    assert_eq!(
+                        settings.squares().engine().cell(column, row),
+                        [(0, 0), (6, 2), (3, 1)].contains(&(column, row))
+                    );
+                }
+            }
+        }
+        remove_file(SQUARES_FILE).unwrap();
+    }
+
+    const GENERATIONS_FILE: &str = "generations_test.gol";
+    #[test]
+    fn dying_squares_survive_write_read() {
+        {
+            let mut settings = Settings::default();
+            settings.resize_grid(5, 5).unwrap();
+            settings.set_rule("B2/S345/C4".parse().unwrap()).unwrap();
+            let engine = settings.squares_mut().engine_mut();
+            engine.set_cell(1, 1, true);
+            engine.set_cell_state(2, 1, 2);
+            engine.set_cell_state(4, 4, 3);
+
+            settings.write_in_file(GENERATIONS_FILE).unwrap();
+        }
+        {
+            let settings = Settings::read_from_file(GENERATIONS_FILE).unwrap();
+            let engine = settings.squares().engine();
+
+            assert_eq!(engine.rule().states(), 4);
+            assert_eq!(engine.cell_state(1, 1), 1);
+            assert_eq!(engine.cell_state(2, 1), 2);
+            assert_eq!(engine.cell_state(4, 4), 3);
+            assert_eq!(engine.cell_state(0, 0), 0);
+        }
+        remove_file(GENERATIONS_FILE).unwrap();
+    }
+
+    const INFINITE_FILE: &str = "infinite_test.gol";
+    #[test]
+    fn infinite_plane_saved_as_its_bounding_box_and_origin() {
+        {
+            let mut settings = Settings::default();
+            settings.set_infinite(true).unwrap();
+            settings.set_squares([[-10, -3], [5, 4]], true);
+
+            settings.write_in_file(INFINITE_FILE).unwrap();
+        }
+        {
+            let settings = Settings::read_from_file(INFINITE_FILE).unwrap();
+
+            assert_eq!(settings.squares_x(), 16);
+            assert_eq!(settings.squares_y(), 8);
+            // Back where they were on the plane
+            assert!(settings.squares().is_infinite());
+            assert!(settings.squares().engine().cell(-10, -3));
+            assert!(settings.squares().engine().cell(5, 4));
+            assert_eq!(settings.squares().engine().population(), 2);
+        }
+        remove_file(INFINITE_FILE).unwrap();
+    }
+
+    const RULE_FILE: &str = "rule_test.gol";
+    #[test]
+    fn rule_survives_write_read() {
+        {
+            let mut settings = Settings::default();
+            settings.set_rule("B36/S23".parse().unwrap()).unwrap();
+
+            settings.write_in_file(RULE_FILE).unwrap();
+        }
+        {
+            let settings = Settings::read_from_file(RULE_FILE).unwrap();
+            assert_eq!(settings.rule().to_string(), "B36/S23");
+        }
+        remove_file(RULE_FILE).unwrap();
+    }
+
+    const TABLE_FILE: &str = "table_test.gol";
+    #[test]
+    fn rule_table_survives_write_read() {
+        let source = "@RULE Seeds\n@TABLE\nn_states:2\nsymmetries:permute\n0,1,1,0,0,0,0,0,0,1\n";
+        {
+            let mut settings = Settings::default();
+            let table = RuleTable::parse(source).unwrap();
+            settings.set_rule(Rule::from_table(table)).unwrap();
+            settings.squares_mut().engine_mut().set_cell(3, 3, true);
+
+            settings.write_in_file(TABLE_FILE).unwrap();
+        }
+        {
+            let settings = Settings::read_from_file(TABLE_FILE).unwrap();
+            let rule = settings.rule();
+            let table = rule.table().unwrap();
+            assert_eq!(table.name(), "Seeds");
+            assert_eq!(table.source(), source);
+            assert!(settings.squares().engine().cell(3, 3));
+        }
+        remove_file(TABLE_FILE).unwrap();
+    }
+
+    const TOPOLOGY_FILE: &str = "topology_test.gol";
+    #[test]
+    fn topology_survives_write_read() {
+        {
+            let mut settings = Settings::default();
+            settings.set_topology(Topology::KleinBottle).unwrap();
+
+            settings.write_in_file(TOPOLOGY_FILE).unwrap();
+        }
+        {
+            let settings = Settings::read_from_file(TOPOLOGY_FILE).unwrap();
+            assert_eq!(settings.topology(), Topology::KleinBottle);
+
+            let Universe::Bounded(life) = settings.squares() else {
+                panic!("a file is always read as a grid");
+            };
+            assert_eq!(life.topology(), Topology::KleinBottle);
+        }
+        remove_file(TOPOLOGY_FILE).unwrap();
+    }
+
+    const LATTICE_FILE: &str = "lattice_test.gol";
+    #[test]
+    fn lattice_survives_write_read() {
+        {
+            let mut settings = Settings::default();
+            settings.set_lattice(Lattice::Hexagonal).unwrap();
+            assert_eq!(
+                settings.set_rule("B2-a/S12".parse().unwrap()),
+                Err(RuleError::NeighborhoodUnsupported)
+            );
+
+            settings.write_in_file(LATTICE_FILE).unwrap();
+        }
+        {
+            let settings = Settings::read_from_file(LATTICE_FILE).unwrap();
+            assert_eq!(settings.lattice(), Lattice::Hexagonal);
+
+            let Universe::Bounded(life) = settings.squares() else {
+                panic!("a file is always read as a grid");
+            };
+            assert_eq!(life.lattice(), Lattice::Hexagonal);
+        }
+        remove_file(LATTICE_FILE).unwrap();
+    }
+
+    const HISTORY_FILE: &str = "history_test.gol";
+    #[test]
+    fn history_limit_survives_write_read() {
+        {
+            let mut settings = Settings::default();
+            settings.set_history_limit(12345);
+
+            settings.write_in_file(HISTORY_FILE).unwrap();
+        }
+        {
+            let settings = Settings::read_from_file(HISTORY_FILE).unwrap();
+            assert_eq!(settings.history().limit(), 12345);
+        }
+        remove_file(HISTORY_FILE).unwrap();
+    }
+
+    const SOUP_FILE: &str = "soup_test.gol";
+    #[test]
+    fn soup_survives_write_read() {
+        let soup = Soup {
+            seed: u64::MAX - 7,
+            density: 0.375,
+            symmetry: Symmetry::D4,
+        };
+        {
+            let mut settings = Settings::default();
+            settings.resize_grid(20, 20).unwrap();
+            settings.set_soup(soup);
+            settings.fill_soup([0, 0, 19, 19]);
+
+            settings.write_in_file(SOUP_FILE).unwrap();
+        }
+        {
+            let mut settings = Settings::read_from_file(SOUP_FILE).unwrap();
+            assert_eq!(settings.soup(), soup);
+
+            // The same soup comes out of it again
+            let cells = settings.squares().engine().live_cells();
+            settings.clear_squares();
+            settings.fill_soup([0, 0, 19, 19]);
+            assert_eq!(settings.squares().engine().live_cells(), cells);
+        }
+        remove_file(SOUP_FILE).unwrap();
+    }
+
+    const BLOCKS_FILE: &str = "blocks_test.gol";
+    #[test]
+    fn block_rule_survives_write_read() {
+        {
+            let mut settings = Settings::default();
+            settings
+                .set_rule(Rule::from_block(BlockRule::CRITTERS))
+                .unwrap();
+            settings.set_show_blocks(false);
+
+            settings.write_in_file(BLOCKS_FILE).unwrap();
+        }
+        {
+            let settings = Settings::read_from_file(BLOCKS_FILE).unwrap();
+            assert_eq!(settings.rule().block(), Some(BlockRule::CRITTERS));
+            assert!(!settings.show_blocks());
+        }
+        remove_file(BLOCKS_FILE).unwrap();
+    }
+
+    const AGE_FILE: &str = "age_test.gol";
+    #[test]
+    fn age_coloring_survives_write_read() {
+        let old = RGBA {
+            r: 1,
+            g: 0x23,
+            b: 0xab,
+            a: 0xff,
+        };
+        {
+            let mut settings = Settings::default();
+            settings.set_age_coloring(true);
+            settings.set_age_span(42);
+            settings.set_sqcolor_old(old);
+
+            settings.write_in_file(AGE_FILE).unwrap();
+        }
+        {
+            let settings = Settings::read_from_file(AGE_FILE).unwrap();
+            assert!(settings.age_coloring());
+            assert!(settings.ages().is_some());
+            assert_eq!(settings.age_span(), 42);
+            assert_eq!(settings.sqcolor_old(), old);
+            assert_eq!(
+                settings.sqcolor_dying(),
+                Settings::default().sqcolor_dying()
+            );
+        }
+        remove_file(AGE_FILE).unwrap();
+    }
+
+    const OLD_FILE: &str = "old_format_test.gol";
+    #[test]
+    fn files_without_extras_are_conway() {
+        let mut bytes = b"gol!\x00\x02\x00\x01".to_vec();
+        bytes.extend(2f32.to_be_bytes());
+        bytes.extend([0; 4 + 5 + 4 + 4]);
+        bytes.extend(b"\0\\gol!/\x01\x00");
+        fs::write(OLD_FILE, bytes).unwrap();
+
+        let settings = Settings::read_from_file(OLD_FILE).unwrap();
+        assert_eq!(settings.rule(), Rule::CONWAY);
+        assert_eq!(settings.topology(), Topology::Dead);
+        assert!(settings.squares().engine().cell(0, 0));
+        assert!(!settings.squares().engine().cell(1, 0));
+
+        remove_file(OLD_FILE).unwrap();
+    }
+
+    const DENSITY_FILE: &str = "density_test.gol";
+    #[test]
+    fn soup_density_has_to_be_a_fraction() {
+        for (density, valid) in [
+            ("0.25", true),
+            ("1.5", false),
+            ("-0.1", false),
+            ("NaN", false),
+        ] {
+            let mut bytes = b"gol!\x00\x02\x00\x01".to_vec();
+            bytes.extend(2f32.to_be_bytes());
+            bytes.extend([0; 4 + 5 + 4 + 4]);
+            bytes.extend(format!("soup-density={}\0\0", density).bytes());
+            bytes.extend(b"\\gol!/\x01\x00");
+            fs::write(DENSITY_FILE, bytes).unwrap();
+
+            let read = Settings::read_from_file(DENSITY_FILE);
+            assert_eq!(read.is_ok(), valid, "{}", density);
+        }
+        remove_file(DENSITY_FILE).unwrap();
+    }
+
+    const ORIGIN_FILE: &str = "origin_test.gol";
+    #[test]
+    fn infinite_planes_need_an_origin_and_no_b0() {
+        for (extras, valid) in [
+            ("infinite=true\0origin=-3,7\0", true),
+            ("infinite=true\0origin=-3\0", false),
+            ("infinite=true\0origin=x,7\0", false),
+            ("rule=B03/S23\0infinite=true\0", false),
+            ("rule=B03/S23\0infinite=false\0", true),
+            ("lattice=hexagonal\0infinite=true\0", false),
+        ] {
+            let mut bytes = b"gol!\x00\x02\x00\x01".to_vec();
+            bytes.extend(2f32.to_be_bytes());
+            bytes.extend([0; 4 + 5 + 4 + 4]);
+            bytes.extend(format!("{}\0", extras).bytes());
+            bytes.extend(b"\\gol!/\x01\x00");
+            fs::write(ORIGIN_FILE, bytes).unwrap();
+
+            let read = Settings::read_from_file(ORIGIN_FILE);
+            assert_eq!(read.is_ok(), valid, "{}", extras);
+            if let (Ok(settings), true) = (read, extras.contains("origin=-3,7")) {
+                assert!(settings.squares().engine().cell(-3, 7));
+            }
+        }
+        remove_file(ORIGIN_FILE).unwrap();
+    }
+
+    const LATTICE_RULE_FILE: &str = "lattice_rule_test.gol";
+    #[test]
+    fn lattices_only_with_rules_they_support() {
+        for (extras, valid) in [
+            ("lattice=hexagonal\0rule=B2/S34\0", true),
+            ("lattice=hexagonal\0rule=B2n3/S23-q\0", false),
+            ("rule=R2,C0,M0,S2..3,B3..3,NM\0lattice=triangular\0", false),
+            // A single row doesn't go around a torus in step
+            ("topology=torus\0lattice=hexagonal\0", false),
+        ] {
+            let mut bytes = b"gol!\x00\x02\x00\x01".to_vec();
+            bytes.extend(2f32.to_be_bytes());
+            bytes.extend([0; 4 + 5 + 4 + 4]);
+            bytes.extend(format!("{}\0", extras).bytes());
+            bytes.extend(b"\\gol!/\x01\x00");
+            fs::write(LATTICE_RULE_FILE, bytes).unwrap();
+
+            let read = Settings::read_from_file(LATTICE_RULE_FILE);
+            assert_eq!(read.is_ok(), valid, "{}", extras);
+        }
+        remove_file(LATTICE_RULE_FILE).unwrap();
+    }
 }
@@ -1,6 +1,22 @@
 mod golfile;
 pub use golfile::*;
 
+use gol::{
+    CellAges, Cycle, CycleDetector, Engine, HashLife, History, Jump, Lattice, Life, Rule,
+    RuleError, RuleTable, Soup, Statistics, Topology, Universe,
+};
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+/// Shared by all settings, so that replacing them with others counts as a change too
+static LAST_VERSION: AtomicU64 = AtomicU64::new(0);
+
+fn next_version() -> u64 {
+    LAST_VERSION.fetch_add(1, Ordering::Relaxed) + 1
+}
+
+#[allow(clippy::upper_case_acronyms)]
 #[derive(Debug, PartialEq, Clone, Copy, Default)]
 pub struct RGBA {
     pub r: u8,
@@ -10,7 +26,7 @@ pub struct RGBA {
 }
 
 impl RGBA {
-    pub fn to_f32(&self) -> [f32; 4] {
+    pub fn to_f32(self) -> [f32; 4] {
         [
             self.r as f32 / 255.0,
             self.g as f32 / 255.0,
@@ -28,7 +44,7 @@ impl RGBA {
         }
     }
 
-    pub fn to_be_bytes(&self) -> [u8; 4] {
+    pub fn to_be_bytes(self) -> [u8; 4] {
         [
             self.r.to_be_bytes()[0],
             self.g.to_be_bytes()[0],
@@ -38,8 +54,9 @@ impl RGBA {
     }
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Default)]
 pub enum StartingView {
+    #[default]
     FitGridToScreen,
     Center(f32),
 }
@@ -80,33 +97,67 @@ impl StartingView {
     }
 }
 
-impl Default for StartingView {
-    fn default() -> Self {
-        Self::FitGridToScreen
-    }
-}
-
 #[derive(Debug, Clone)]
 pub struct Settings {
-    /// ### Order
-    /// Row major order
-    /// i.e. squares\[row\]\[column\]
-    squares: Vec<Vec<bool>>, // TODO: ACTUAL USAGE
+    squares: Universe,
+    /// An infinite plane in the middle of a jump, kept from one batch to the next
+    /// so that it remembers what it already worked out.
+    /// The squares stay where the jump started until it is over
+    jumping: Option<HashLife>,
+    /// The generations that came before, to be able to go back to them
+    history: History,
+    cycles: CycleDetector,
+    stats: Statistics,
+    /// Only kept up to date when coloring by age
+    ages: CellAges,
+    /// Changes whenever the squares or their ages do
+    version: u64,
+    detect_cycles: bool,
+    /// Whether stepping stops as soon as a cycle is found
+    stop_on_cycle: bool,
+    /// Whether grids with borders are stepped on the GPU,
+    /// which knows nothing of the history, cycles or statistics
+    gpu_stepping: bool,
     squares_x: u16,
     squares_y: u16,
+    /// Kept here too since an infinite plane has no edges
+    topology: Topology,
+    /// Also only for grids, infinite planes are always made of squares
+    lattice: Lattice,
+    /// What the last soup was made with, or the next one will be
+    soup: Soup,
     updates_sec: f32,
     background_color: RGBA,
     starting_view: StartingView,
     square_color_off: RGBA,
     square_color_on: RGBA,
+    /// Whether the squares go from `square_color_on` to `square_color_old` as they
+    /// get older, and from `square_color_dying` to `square_color_off` once they die
+    age_coloring: bool,
+    square_color_old: RGBA,
+    square_color_dying: RGBA,
+    /// Whether the 2x2 blocks of block rules are drawn apart from each other
+    show_blocks: bool,
 }
 
 impl Default for Settings {
     fn default() -> Self {
         Self {
-            squares: vec![vec![false; 5]; 5],
+            squares: Universe::Bounded(Life::new(5, 5)),
+            jumping: None,
+            history: History::default(),
+            cycles: CycleDetector::new(),
+            stats: Statistics::default(),
+            ages: CellAges::default(),
+            version: next_version(),
+            detect_cycles: true,
+            stop_on_cycle: false,
+            gpu_stepping: false,
             squares_x: 5,
             squares_y: 5,
+            topology: Topology::Dead,
+            lattice: Lattice::Square,
+            soup: Soup::default(),
             updates_sec: 2.0,
             background_color: RGBA {
                 r: 4,
@@ -127,27 +178,485 @@ impl Default for Settings {
                 b: 211,
                 a: 255,
             },
+            age_coloring: false,
+            square_color_old: RGBA {
+                r: 70,
+                g: 110,
+                b: 200,
+                a: 255,
+            },
+            square_color_dying: RGBA {
+                r: 150,
+                g: 60,
+                b: 60,
+                a: 255,
+            },
+            show_blocks: true,
         }
     }
 }
 
 impl Settings {
-    pub fn toggle_square(&mut self, column: usize, row: usize) {
-        self.squares[row][column] = !self.squares[row][column];
+    /// Squares outside of a grid with borders are left alone
+    pub fn toggle_square(&mut self, x: i64, y: i64) {
+        let engine = self.squares.engine_mut();
+        engine.set_cell(x, y, !engine.cell(x, y));
+        self.edited();
+    }
+
+    /// Squares outside of a grid with borders are left alone,
+    /// the history and the ages only hear about it the one time
+    pub fn set_squares(&mut self, cells: impl IntoIterator<Item = [i64; 2]>, alive: bool) {
+        let engine = self.squares.engine_mut();
+        for [x, y] in cells {
+            engine.set_cell(x, y, alive);
+        }
+        self.edited();
+    }
+
+    /// Lets the history and cycle detection know the squares were changed
+    fn edited(&mut self) {
+        self.jumping = None;
+        self.history.edited();
+        self.cycles.reset();
+        self.observe_ages();
+    }
+
+    /// For when the squares are replaced altogether
+    fn forget_the_past(&mut self) {
+        self.jumping = None;
+        self.history.clear();
+        self.cycles.reset();
+        self.stats.clear();
+        self.ages.clear();
+        self.observe_ages();
+    }
+
+    /// Goes through the cells the history already sorted
+    fn observe_cycles(&mut self) -> Option<Cycle> {
+        let engine = self.squares.engine();
+        let cells = self.history.current_cells(engine);
+        self.cycles.observe_cells(engine.generation(), cells)
+    }
+
+    /// Has to be called whenever the squares change
+    fn observe_ages(&mut self) {
+        self.version = next_version();
+        if self.age_coloring {
+            self.ages.observe(self.squares.engine());
+        }
+    }
+
+    /// Kills every square and starts over from generation 0
+    pub fn clear_squares(&mut self) {
+        self.squares.engine_mut().clear();
+        self.forget_the_past();
+    }
+
+    pub fn soup(&self) -> Soup {
+        self.soup
+    }
+
+    pub fn set_soup(&mut self, soup: Soup) {
+        self.soup = soup;
+    }
+
+    /// Replaces the squares in `[min_x, min_y, max_x, max_y]`, all inclusive,
+    /// with a random soup made out of [`Settings::soup`]
+    pub fn fill_soup(&mut self, region: [i64; 4]) {
+        let [min_x, min_y, max_x, max_y] = region;
+        let engine = self.squares.engine_mut();
+
+        for [x, y] in engine.live_cells() {
+            if (min_x..=max_x).contains(&x) && (min_y..=max_y).contains(&y) {
+                engine.set_cell(x, y, false);
+            }
+        }
+        for [x, y] in self.soup.cells(region) {
+            engine.set_cell(x, y, true);
+        }
+
+        self.edited();
+    }
+
+    /// Computes `n` generations, remembering them in the history
+    /// and keeping statistics about them.
+    /// Returns whether it stopped early because it just found a cycle
+    pub fn step_n(&mut self, n: u64) -> bool {
+        if n == 0 {
+            return false;
+        }
+        if self.jumping.is_some() {
+            self.finish_jump();
+        }
+
+        self.stats.record(self.squares.engine());
+        // The same squares don't always turn out the same with stochastic rules
+        let detect_cycles = self.detect_cycles && self.rule().stochastic().is_none();
+        if detect_cycles {
+            self.observe_cycles();
+        }
+
+        for _ in 0..n {
+            let found_before = self.cycles.cycle().is_some();
+
+            self.history.step(self.squares.engine_mut());
+            self.stats.record(self.squares.engine());
+            self.observe_ages();
+
+            if detect_cycles {
+                let found = self.observe_cycles().is_some();
+                if found && !found_before && self.stop_on_cycle {
+                    return true;
+                }
+            }
+        }
+
+        false
+    }
+
+    /// Computes the generations of `jump` for about as long as `budget`, one at a time
+    /// when looking for cycles and in batches otherwise. Returns whether it is over,
+    /// either at its target or at a cycle to stop at
+    pub fn advance_jump(&mut self, jump: &mut Jump, budget: Duration) -> bool {
+        let started = Instant::now();
+        // The same squares don't always turn out the same with stochastic rules
+        let detect_cycles = self.detect_cycles && self.rule().stochastic().is_none();
+
+        // The history can't go back into a jump, and nothing is known about cycles
+        // without looking at every generation
+        if self.generation() == jump.from() {
+            self.history.clear();
+            if !detect_cycles {
+                self.cycles.reset();
+            }
+        }
+
+        let over = if detect_cycles {
+            self.jump_through_cycles(jump, started, budget)
+        } else {
+            self.jump_in_batches(jump, started, budget)
+        };
+        if over {
+            self.finish_jump();
+        }
+        over
+    }
+
+    fn jump_through_cycles(&mut self, jump: &Jump, started: Instant, budget: Duration) -> bool {
+        self.cycles.observe(self.squares.engine());
+        let found_before = self.cycles.cycle().is_some();
+
+        loop {
+            let engine = self.squares.engine_mut();
+            if jump.is_done(engine.generation()) {
+                return true;
+            }
+
+            engine.step();
+            self.stats.record(self.squares.engine());
+            let found = self.cycles.observe(self.squares.engine()).is_some();
+            if found && !found_before && self.stop_on_cycle {
+                return true;
+            }
+
+            if started.elapsed() >= budget {
+                return false;
+            }
+        }
+    }
+
+    /// An infinite plane goes through the same [`HashLife`] for the whole jump
+    fn jump_in_batches(&mut self, jump: &mut Jump, started: Instant, budget: Duration) -> bool {
+        if self.jumping.is_none() {
+            self.jumping = self.squares.to_hashlife();
+        }
+
+        loop {
+            let generation = self.generation();
+            if jump.is_done(generation) {
+                return true;
+            }
+
+            let batch = jump.next_batch(generation);
+            let batch_started = Instant::now();
+            self.engine_mut().step_n(batch);
+            jump.batch_took(batch_started.elapsed(), budget);
+            match &self.jumping {
+                Some(hashlife) => self.stats.record(hashlife),
+                None => self.stats.record(self.squares.engine()),
+            }
+
+            if started.elapsed() >= budget {
+                return false;
+            }
+        }
+    }
+
+    /// Puts the squares where a jump got to, for when it is over or canceled
+    pub fn finish_jump(&mut self) {
+        if let Some(hashlife) = self.jumping.take() {
+            self.squares = Universe::from_hashlife(&hashlife);
+        }
+        self.observe_ages();
+    }
+
+    /// The squares, or the plane a jump is going through
+    fn engine(&self) -> &dyn Engine {
+        match &self.jumping {
+            Some(hashlife) => hashlife,
+            None => self.squares.engine(),
+        }
+    }
+
+    fn engine_mut(&mut self) -> &mut dyn Engine {
+        match &mut self.jumping {
+            Some(hashlife) => hashlife,
+            None => self.squares.engine_mut(),
+        }
+    }
+
+    /// The generation of the squares, or the one a jump got to
+    pub fn generation(&self) -> u64 {
+        self.engine().generation()
+    }
+
+    /// Returns whether there was a generation to go back to
+    pub fn step_back(&mut self) -> bool {
+        let stepped = self.history.step_back(self.squares.engine_mut());
+        self.stats.forget_after(self.squares.engine().generation());
+        self.observe_ages();
+        stepped
+    }
+
+    /// Goes forward through the generations that were gone back from
+    /// or computes the next one if there are none
+    pub fn step_forward(&mut self) {
+        if self.history.step_forward(self.squares.engine_mut()) {
+            self.observe_ages();
+        } else {
+            self.step_n(1);
+        }
+    }
+
+    /// Returns whether `generation` was remembered and is now the current one
+    pub fn rewind_to(&mut self, generation: u64) -> bool {
+        let rewound = self
+            .history
+            .rewind_to(self.squares.engine_mut(), generation);
+        self.stats.forget_after(self.squares.engine().generation());
+        self.observe_ages();
+        rewound
+    }
+
+    pub fn stats(&self) -> &Statistics {
+        &self.stats
+    }
+
+    /// The cycle the squares are in, if one was found
+    pub fn cycle(&self) -> Option<Cycle> {
+        self.cycles.cycle()
     }
 
-    pub fn resize_grid(&mut self, columns: u16, rows: u16) {
+    pub fn detect_cycles(&self) -> bool {
+        self.detect_cycles
+    }
+
+    pub fn set_detect_cycles(&mut self, detect: bool) {
+        self.detect_cycles = detect;
+        self.cycles.reset();
+    }
+
+    pub fn stop_on_cycle(&self) -> bool {
+        self.stop_on_cycle
+    }
+
+    pub fn set_stop_on_cycle(&mut self, stop: bool) {
+        self.stop_on_cycle = stop;
+    }
+
+    pub fn gpu_stepping(&self) -> bool {
+        self.gpu_stepping
+    }
+
+    pub fn set_gpu_stepping(&mut self, gpu_stepping: bool) {
+        self.gpu_stepping = gpu_stepping;
+    }
+
+    /// For a grid that got to a later generation somewhere else, e.g. on the GPU,
+    /// the history can't go back past it and cycles are looked for from scratch
+    pub fn jump_to(&mut self, life: Life) {
+        self.jumping = None;
+        self.squares = Universe::Bounded(life);
+        self.history.clear();
+        self.cycles.reset();
+        self.stats.record(self.squares.engine());
+        self.observe_ages();
+    }
+
+    /// How old every square is, only when coloring by age
+    pub fn ages(&self) -> Option<&CellAges> {
+        self.age_coloring.then_some(&self.ages)
+    }
+
+    pub fn age_coloring(&self) -> bool {
+        self.age_coloring
+    }
+
+    /// Ages are counted from when it is turned on
+    pub fn set_age_coloring(&mut self, age_coloring: bool) {
+        self.age_coloring = age_coloring;
+        self.ages.clear();
+        self.observe_ages();
+    }
+
+    /// How many generations it takes to go all the way through the colors
+    pub fn age_span(&self) -> u32 {
+        self.ages.dead_limit()
+    }
+
+    pub fn set_age_span(&mut self, age_span: u32) {
+        self.ages.set_dead_limit(age_span);
+        self.version = next_version();
+    }
+
+    pub fn show_blocks(&self) -> bool {
+        self.show_blocks
+    }
+
+    pub fn set_show_blocks(&mut self, show_blocks: bool) {
+        self.show_blocks = show_blocks;
+    }
+
+    pub fn history(&self) -> &History {
+        &self.history
+    }
+
+    /// How many bytes the history can take at most, zero turns it off
+    pub fn set_history_limit(&mut self, limit: usize) {
+        self.history.set_limit(limit);
+    }
+
+    /// The squares that still fit in the new dimensions are kept,
+    /// fails if the cells wouldn't [line up](Lattice::wraps) around the edges anymore
+    pub fn resize_grid(&mut self, columns: u16, rows: u16) -> Result<(), RuleError> {
+        if !self
+            .lattice
+            .wraps(self.topology, columns as usize, rows as usize)
+        {
+            return Err(RuleError::LatticeDoesNotWrap);
+        }
+
         self.squares_x = columns;
         self.squares_y = rows;
 
-        self.squares.clear();
+        if let Universe::Bounded(life) = &mut self.squares {
+            life.resize(columns as usize, rows as usize);
+        }
+        self.forget_the_past();
+        Ok(())
+    }
+
+    pub fn squares(&self) -> &Universe {
+        &self.squares
+    }
+
+    /// Different every time the squares or their ages change, so that whoever
+    /// draws them can tell when to do it again
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+
+    /// Anything done to the squares through here is not known by the history,
+    /// so it should not change them
+    pub fn squares_mut(&mut self) -> &mut Universe {
+        self.version = next_version();
+        &mut self.squares
+    }
+
+    /// Going from an infinite plane to a grid only keeps the squares inside
+    /// of the grid's dimensions, and rules with B0 and other lattices only work on a grid
+    pub fn set_infinite(&mut self, infinite: bool) -> Result<(), RuleError> {
+        if infinite && self.rule().has_b0() {
+            return Err(RuleError::B0OnInfinitePlane);
+        }
+        if infinite && self.lattice != Lattice::Square {
+            return Err(RuleError::NeighborhoodUnsupported);
+        }
+
+        let squares = std::mem::replace(&mut self.squares, Universe::Bounded(Life::new(0, 0)));
+
+        self.squares = if infinite {
+            squares.into_infinite()
+        } else {
+            squares.into_bounded(self.squares_x as usize, self.squares_y as usize)
+        };
+        self.set_topology(self.topology)?;
+        if let Universe::Bounded(life) = &mut self.squares {
+            life.set_lattice(self.lattice)?;
+        }
+        self.forget_the_past();
+
+        Ok(())
+    }
+
+    pub fn topology(&self) -> Topology {
+        self.topology
+    }
+
+    /// Only matters for a grid, but it is remembered for when going back to one.
+    /// Fails if the cells wouldn't [line up](Lattice::wraps) around the edges
+    pub fn set_topology(&mut self, topology: Topology) -> Result<(), RuleError> {
+        let [columns, rows] = [self.squares_x, self.squares_y].map(usize::from);
+        if !self.lattice.wraps(topology, columns, rows) {
+            return Err(RuleError::LatticeDoesNotWrap);
+        }
+
+        self.topology = topology;
+        self.edited();
+
+        if let Universe::Bounded(life) = &mut self.squares {
+            life.set_topology(topology);
+        }
+        Ok(())
+    }
+
+    pub fn lattice(&self) -> Lattice {
+        self.lattice
+    }
+
+    /// Anything but squares only works on a grid and with rules that just count
+    /// the neighbors, see [`Life::set_lattice`]
+    pub fn set_lattice(&mut self, lattice: Lattice) -> Result<(), RuleError> {
+        if !lattice.supports(&self.rule()) {
+            return Err(RuleError::NeighborhoodUnsupported);
+        }
 
-        for row in 0..self.squares_y {
-            self.squares.push(Vec::new());
-            for _column in 0..self.squares_x {
-                self.squares[row as usize].push(false);
+        match &mut self.squares {
+            Universe::Bounded(life) => life.set_lattice(lattice)?,
+            Universe::Infinite(_) if lattice != Lattice::Square => {
+                return Err(RuleError::NeighborhoodUnsupported)
             }
+            Universe::Infinite(_) => {}
         }
+        self.lattice = lattice;
+        self.edited();
+        Ok(())
+    }
+
+    pub fn rule(&self) -> Rule {
+        self.squares.engine().rule()
+    }
+
+    pub fn set_rule(&mut self, rule: Rule) -> Result<(), RuleError> {
+        // Checked here too since the lattice is remembered for an infinite plane
+        if !self.lattice.supports(&rule) {
+            return Err(RuleError::NeighborhoodUnsupported);
+        }
+
+        self.squares.engine_mut().set_rule(rule)?;
+        self.edited();
+        Ok(())
     }
 
     pub fn squares_x(&self) -> u16 {
@@ -158,6 +667,14 @@ impl Settings {
         self.squares_y
     }
 
+    pub fn updates_sec(&self) -> f32 {
+        self.updates_sec
+    }
+
+    pub fn set_updates_sec(&mut self, new: f32) {
+        self.updates_sec = new;
+    }
+
     pub fn set_background_color(&mut self, new: RGBA) {
         self.background_color = new;
     }
@@ -181,4 +698,124 @@ impl Settings {
     pub fn sqcolor_on(&self) -> RGBA {
         self.square_color_on
     }
+
+    pub fn set_sqcolor_old(&mut self, new: RGBA) {
+        self.square_color_old = new;
+    }
+
+    pub fn set_sqcolor_dying(&mut self, new: RGBA) {
+        self.square_color_dying = new;
+    }
+
+    pub fn sqcolor_old(&self) -> RGBA {
+        self.square_color_old
+    }
+
+    pub fn sqcolor_dying(&self) -> RGBA {
+        self.square_color_dying
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn jumps_get_there_a_budget_at_a_time() {
+        let mut settings = Settings::default();
+        settings.resize_grid(16, 16).unwrap();
+        settings.set_squares([[1, 0], [2, 1], [0, 2], [1, 2], [2, 2]], true);
+
+        let mut jump = Jump::to(0, 20);
+        while !settings.advance_jump(&mut jump, Duration::from_millis(5)) {}
+        assert_eq!(settings.squares().engine().generation(), 20);
+        // The glider went 5 squares down and to the right, same as stepping there
+        assert!(settings.squares().engine().cell(6, 7));
+        assert_eq!(settings.squares().engine().population(), 5);
+        // Nothing in between is remembered, only where it got to
+        assert_eq!(settings.history().len(), 0);
+
+        // Without looking for cycles an infinite plane goes through a HashLife,
+        // and the squares stay where they were until the jump is over
+        settings.set_infinite(true).unwrap();
+        settings.set_detect_cycles(false);
+        let mut jump = Jump::by(20, 4000);
+        assert!(!settings.advance_jump(&mut jump, Duration::ZERO));
+        assert_eq!(settings.generation(), 21);
+        assert_eq!(settings.squares().engine().generation(), 20);
+        while !settings.advance_jump(&mut jump, Duration::from_millis(5)) {}
+        assert_eq!(settings.squares().engine().generation(), 4020);
+        assert!(settings.squares().engine().cell(1006, 1007));
+        assert_eq!(settings.squares().engine().population(), 5);
+        assert!(settings.cycle().is_none());
+
+        // Canceled half way
+        let mut jump = Jump::by(4020, 100);
+        assert!(!settings.advance_jump(&mut jump, Duration::ZERO));
+        settings.finish_jump();
+        assert_eq!(settings.squares().engine().generation(), 4021);
+        assert_eq!(settings.squares().engine().population(), 5);
+
+        // A blinker is a cycle to stop at long before the target
+        settings.set_detect_cycles(true);
+        settings.clear_squares();
+        settings.set_squares((4..7).map(|x| [x, 4]), true);
+        settings.set_stop_on_cycle(true);
+        let mut jump = Jump::by(0, 1000);
+        while !settings.advance_jump(&mut jump, Duration::from_millis(5)) {}
+        assert!(settings.squares().engine().generation() < 10);
+        assert!(settings.cycle().is_some());
+    }
+
+    #[test]
+    fn the_version_only_moves_with_the_squares() {
+        let mut settings = Settings::default();
+        let version = settings.version();
+        settings.set_sqcolor_on(RGBA::default());
+        assert_eq!(settings.version(), version);
+
+        settings.set_squares([[1, 1]], true);
+        assert_ne!(settings.version(), version);
+        let version = settings.version();
+        settings.step_n(1);
+        assert_ne!(settings.version(), version);
+    }
+
+    #[test]
+    fn setting_many_squares_at_once() {
+        let mut settings = Settings::default();
+        settings.set_squares((0..5).map(|x| [x, 3]), true);
+        assert_eq!(settings.squares().engine().population(), 5);
+        let version = settings.version();
+
+        settings.set_squares([[1, 3], [2, 3]], false);
+        assert_eq!(settings.squares().engine().population(), 3);
+        assert_ne!(settings.version(), version);
+    }
+
+    #[test]
+    fn other_lattices_stay_on_a_grid_they_wrap_around() {
+        let mut settings = Settings::default();
+        settings.resize_grid(10, 10).unwrap();
+        settings.set_lattice(Lattice::Hexagonal).unwrap();
+        assert_eq!(
+            settings.set_infinite(true),
+            Err(RuleError::NeighborhoodUnsupported)
+        );
+        assert!(!settings.squares().is_infinite());
+
+        settings.set_topology(Topology::Torus).unwrap();
+        assert_eq!(
+            settings.resize_grid(10, 9),
+            Err(RuleError::LatticeDoesNotWrap)
+        );
+        assert_eq!(settings.squares_y(), 10);
+
+        settings.set_lattice(Lattice::Square).unwrap();
+        settings.set_infinite(true).unwrap();
+        assert_eq!(
+            settings.set_lattice(Lattice::Triangular),
+            Err(RuleError::NeighborhoodUnsupported)
+        );
+    }
 }
@@ -0,0 +1,109 @@
+struct Cells {
+    data: [[stride(4)]] array<u32>;
+};
+
+[[group(0), binding(0)]]
+var<storage, read> current: Cells;
+
+[[group(0), binding(1)]]
+var<storage, read_write> next: Cells;
+
+struct StepInfo {
+    columns: i32;
+    rows: i32;
+    // bit n set = born/survives with n neighbors
+    birth: u32;
+    survival: u32;
+    // same order as gol::Topology::ALL
+    topology: u32;
+    // more than two for rules from the Generations family
+    states: u32;
+};
+
+[[group(0), binding(2)]]
+var<uniform> info: StepInfo;
+
+// Whether the square at (x, y) is alive, x and y being at most one square outside of the grid
+fn alive(x_in: i32, y_in: i32) -> u32 {
+    var x: i32 = x_in;
+    var y: i32 = y_in;
+    let x_outside = x < 0 || x >= info.columns;
+    let y_outside = y < 0 || y >= info.rows;
+
+    switch (info.topology) {
+        // dead
+        case 0u: {
+            if (x_outside || y_outside) {
+                return 0u;
+            }
+        }
+        // klein bottle, going over the top or bottom flips it
+        case 2u: {
+            if (y_outside) {
+                x = info.columns - 1 - x;
+            }
+        }
+        // cross-surface, going over any edge flips it
+        case 3u: {
+            if (y_outside) {
+                x = info.columns - 1 - x;
+            }
+            if (x < 0 || x >= info.columns) {
+                y = info.rows - 1 - y;
+            }
+        }
+        // reflective, the edge squares are their own neighbors
+        case 4u: {
+            if (x < 0) {
+                x = -1 - x;
+            } else if (x >= info.columns) {
+                x = 2 * info.columns - 1 - x;
+            }
+            if (y < 0) {
+                y = -1 - y;
+            } else if (y >= info.rows) {
+                y = 2 * info.rows - 1 - y;
+            }
+        }
+        // torus
+        default: {}
+    }
+
+    // wrapping around, which does nothing if it is inside already
+    x = (x + info.columns) % info.columns;
+    y = (y + info.rows) % info.rows;
+
+    // dying squares are not alive
+    return u32(current.data[y * info.columns + x] == 1u);
+}
+
+[[stage(compute), workgroup_size(8, 8)]]
+fn cs_main([[builtin(global_invocation_id)]] id: vec3<u32>) {
+    let x = i32(id.x);
+    let y = i32(id.y);
+    if (x >= info.columns || y >= info.rows) {
+        return;
+    }
+
+    let neighbors =
+        alive(x - 1, y - 1) + alive(x, y - 1) + alive(x + 1, y - 1)
+        + alive(x - 1, y) + alive(x + 1, y)
+        + alive(x - 1, y + 1) + alive(x, y + 1) + alive(x + 1, y + 1);
+
+    let state = current.data[y * info.columns + x];
+    var next_state: u32 = 0u;
+
+    if (state == 0u) {
+        next_state = (info.birth >> neighbors) & 1u;
+    } else if (state == 1u) {
+        next_state = (info.survival >> neighbors) & 1u;
+        // starts dying instead of dying right away
+        if (next_state == 0u && info.states > 2u) {
+            next_state = 2u;
+        }
+    } else if (state + 1u < info.states) {
+        next_state = state + 1u;
+    }
+
+    next.data[y * info.columns + x] = next_state;
+}